@@ -75,12 +75,65 @@ export async function encryptWithPassword(password: string, data: Uint8Array): P
 }
 
 /**
- * Decrypt data encrypted with encryptWithPassword.
+ * Потоковый формат больших файлов, которые шифрует Rust (src-tauri/src/crypto.rs):
+ * "CWSTRM01" + salt (16) + префикс nonce (7) + размер чанка (u32 LE), затем чанки AES-GCM.
+ * Nonce чанка = префикс + номер чанка (u32 BE) + флаг последнего чанка (0/1).
+ */
+const STREAM_MAGIC = new TextEncoder().encode('CWSTRM01')
+const NONCE_PREFIX_LENGTH = 7
+const TAG_LENGTH = 16
+
+function isStreamFormat(data: Uint8Array): boolean {
+	return data.length >= STREAM_MAGIC.length && STREAM_MAGIC.every((b, i) => data[i] === b)
+}
+
+async function decryptStream(password: string, encrypted: Uint8Array): Promise<Uint8Array> {
+	let pos = STREAM_MAGIC.length
+	const salt = encrypted.slice(pos, pos + SALT_LENGTH)
+	pos += SALT_LENGTH
+	const prefix = encrypted.slice(pos, pos + NONCE_PREFIX_LENGTH)
+	pos += NONCE_PREFIX_LENGTH
+	const chunkSize = new DataView(encrypted.buffer, encrypted.byteOffset + pos, 4).getUint32(0, true)
+	pos += 4
+	if (chunkSize === 0) throw new Error('Invalid chunk size')
+	const key = await deriveKey(password, salt)
+	const sealed = chunkSize + TAG_LENGTH
+	const parts: Uint8Array[] = []
+	let total = 0
+	for (let counter = 0; ; counter++) {
+		const end = Math.min(pos + sealed, encrypted.length)
+		const last = end === encrypted.length
+		const iv = new Uint8Array(IV_LENGTH)
+		iv.set(prefix, 0)
+		new DataView(iv.buffer).setUint32(NONCE_PREFIX_LENGTH, counter, false)
+		iv[IV_LENGTH - 1] = last ? 1 : 0
+		const plain = new Uint8Array(
+			await crypto.subtle.decrypt({ name: 'AES-GCM', iv }, key, encrypted.slice(pos, end)),
+		)
+		parts.push(plain)
+		total += plain.length
+		pos = end
+		if (last) break
+	}
+	const result = new Uint8Array(total)
+	let offset = 0
+	for (const part of parts) {
+		result.set(part, offset)
+		offset += part.length
+	}
+	return result
+}
+
+/**
+ * Decrypt data encrypted with encryptWithPassword (или потоковым форматом из Rust).
  */
 export async function decryptWithPassword(
 	password: string,
 	encrypted: Uint8Array,
 ): Promise<Uint8Array> {
+	if (isStreamFormat(encrypted)) {
+		return decryptStream(password, encrypted)
+	}
 	const salt = encrypted.slice(0, SALT_LENGTH)
 	const iv = encrypted.slice(SALT_LENGTH, SALT_LENGTH + IV_LENGTH)
 	const cipher = encrypted.slice(SALT_LENGTH + IV_LENGTH)
@@ -0,0 +1,54 @@
+/**
+ * Ошибки команд Rust (`AppError` в src-tauri/src/error.rs). invoke отклоняется объектом с
+ * устойчивым `code` и полями контекста; текст для пользователя выбирается по коду из `errors.*`.
+ */
+export type AppError =
+	| { code: 'NotFound'; kind: string; id: string }
+	| { code: 'PathNotAllowed'; path: string }
+	| { code: 'Unsupported'; feature: string }
+	| { code: 'NotEncrypted'; id: string }
+	| { code: 'DuplicateName'; kind: string; name: string }
+	| { code: 'DecodeFailed'; path: string; reason: string }
+	| { code: 'Io'; path: string | null; reason: string }
+	| { code: 'Jni'; reason: string }
+	| { code: 'Invalid'; reason: string };
+
+export type AppErrorCode = AppError['code'];
+
+type Translate = (key: string, named?: Record<string, unknown>) => string;
+
+export function isAppError(e: unknown): e is AppError {
+	return typeof e === 'object' && e !== null && typeof (e as { code?: unknown }).code === 'string';
+}
+
+/** Код ошибки команды; null — ошибка не из Rust (например, плагина диалогов). */
+export function errorCode(e: unknown): AppErrorCode | null {
+	return isAppError(e) ? e.code : null;
+}
+
+/** Локализованный текст ошибки для показа пользователю. */
+export function errorMessage(e: unknown, t: Translate): string {
+	if (!isAppError(e)) {
+		return e instanceof Error ? e.message : String(e);
+	}
+	switch (e.code) {
+		case 'NotFound':
+			return t('errors.notFound', { kind: t(`errors.kind.${e.kind}`) });
+		case 'PathNotAllowed':
+			return t('errors.pathNotAllowed', { path: e.path });
+		case 'Unsupported':
+			return t('errors.unsupported', { feature: t(`errors.feature.${e.feature}`) });
+		case 'NotEncrypted':
+			return t('errors.notEncrypted');
+		case 'DuplicateName':
+			return t(`errors.duplicateName.${e.kind}`, { name: e.name });
+		case 'DecodeFailed':
+			return t('errors.decodeFailed', { path: e.path });
+		case 'Io':
+			return t('errors.io', { reason: e.reason });
+		case 'Jni':
+			return t('errors.jni', { reason: e.reason });
+		case 'Invalid':
+			return e.reason;
+	}
+}
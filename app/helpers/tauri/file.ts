@@ -1,6 +1,9 @@
-import { open } from '@tauri-apps/plugin-dialog';
+import { open, save } from '@tauri-apps/plugin-dialog';
 import { readFile } from '@tauri-apps/plugin-fs';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { UnlistenFn } from '@tauri-apps/api/event';
+import { getDeviceInfo } from './device';
 
 /** Тип папки для сохранения (только для обратной совместимости pickAndSaveFile). */
 export type SaveFolderType = 'pictures' | 'videos' | 'audio' | 'documents' | 'other';
@@ -52,29 +55,73 @@ export function getSaveFolderTypeFromFileName(fileName: string): SaveFolderType
 }
 
 /**
- * Сохранить файл в папку коллекции. Возвращает относительный путь (collections/{id}/{fileName}).
+ * Сохранить файл для коллекции в общее хранилище (одинаковое содержимое хранится один раз).
+ * Возвращает относительный путь (blobs/{xx}/{blob}), id блоба и имя файла для addCollectionItem.
+ * Файлы зашифрованной коллекции шифруются в Rust и сохраняются в её папку — тогда blob не задан.
  */
 export async function saveFileToCollection(
 	collectionId: string,
 	fileName: string,
 	options: { sourcePath?: string | null; contents?: Uint8Array | null }
-): Promise<string> {
+): Promise<{ path: string; blob?: string; file: string }> {
 	const { sourcePath = null, contents = null } = options;
+	const uploadId = contents ? await uploadBytes(contents) : null;
 	const relativePath = await invoke<string>('save_file_to_collection', {
 		collectionId,
 		fileName,
 		sourcePath,
-		contents: contents ? Array.from(contents) : null
+		uploadId
 	});
-	return relativePath;
+	const last = relativePath.split('/').pop() ?? '';
+	return relativePath.startsWith('blobs/')
+		? { path: relativePath, blob: last, file: fileName }
+		: { path: relativePath, file: last };
+}
+
+/** Размер части при передаче файла в Rust. */
+const UPLOAD_CHUNK_SIZE = 4 * 1024 * 1024;
+
+/**
+ * Передать байты в Rust частями, без JSON-массива чисел. Возвращает id загрузки для
+ * saveFileToCollection и save_file_to_app. На Android тело запроса IPC может быть только JSON,
+ * поэтому там части идут строкой base64; на ПК — сырым телом.
+ */
+export async function uploadBytes(bytes: Uint8Array): Promise<string> {
+	const uploadId = await invoke<string>('upload_begin');
+	const { platform } = await getDeviceInfo();
+	try {
+		for (let offset = 0; offset < bytes.length; offset += UPLOAD_CHUNK_SIZE) {
+			const chunk = bytes.subarray(offset, offset + UPLOAD_CHUNK_SIZE);
+			if (platform === 'android') {
+				await invoke('upload_chunk', { uploadId, offset, data: toBase64(chunk) });
+			} else {
+				await invoke('upload_chunk', chunk, {
+					headers: { 'Upload-Id': uploadId, 'Upload-Offset': String(offset) }
+				});
+			}
+		}
+	} catch (e) {
+		await invoke('upload_abort', { uploadId }).catch(() => {});
+		throw e;
+	}
+	return uploadId;
+}
+
+function toBase64(bytes: Uint8Array): string {
+	let binary = '';
+	// Частями: у String.fromCharCode ограничено число аргументов
+	for (let i = 0; i < bytes.length; i += 0x8000) {
+		binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+	}
+	return btoa(binary);
 }
 
 /**
  * Прочитать файл из хранилища. path — относительный (collections/...) или полный.
  */
 export async function readAppFile(path: string): Promise<Uint8Array> {
-	const bytes = await invoke<number[]>('read_file_from_app', { path });
-	return new Uint8Array(bytes);
+	const buffer = await invoke<ArrayBuffer>('read_file_from_app', { path });
+	return new Uint8Array(buffer);
 }
 
 /**
@@ -85,11 +132,15 @@ export async function deleteAppFile(path: string): Promise<void> {
 }
 
 /**
- * Установить изображение как обои устройства (Android).
+ * Установить изображение как обои устройства. collectionId попадает в историю смен.
  */
-export async function setDeviceWallpaper(path: string, target: 'both' | 'home' | 'lock' = 'both'): Promise<void> {
+export async function setDeviceWallpaper(
+	path: string,
+	target: 'both' | 'home' | 'lock' = 'both',
+	collectionId: string | null = null
+): Promise<void> {
 	try {
-		await invoke('set_device_wallpaper_target', { path, target });
+		await invoke('set_device_wallpaper_target', { path, target, collectionId });
 	} catch (e) {
 		console.error('Failed to set wallpaper:', e);
 		throw e;
@@ -115,12 +166,12 @@ export async function pickAndSaveFile(): Promise<string | null> {
 	const payload = {
 		saveType,
 		sourcePath: null as string | null,
-		contents: null as number[] | null,
+		uploadId: null as string | null,
 		fileName: null as string | null
 	};
 	if (path.startsWith('content:')) {
 		const data = await readFile(path);
-		payload.contents = Array.from(
+		payload.uploadId = await uploadBytes(
 			data instanceof ArrayBuffer ? new Uint8Array(data) : new Uint8Array(data)
 		);
 		payload.fileName = fileName;
@@ -130,6 +181,57 @@ export async function pickAndSaveFile(): Promise<string | null> {
 	return invoke<string>('save_file_to_app', payload);
 }
 
+export interface CollectionItem {
+	id: number;
+	order: number;
+	file: string;
+	/** id файла в общем хранилище; у старых элементов нет — файл лежит в папке коллекции. */
+	blob?: string;
+	screen?: { width: number; height: number };
+	image?: { width: number; height: number };
+	crop?: { x: number; y: number; width: number; height: number };
+	savedAsCrop: boolean;
+	created_at: number;
+}
+
+export type NewCollectionItem = Omit<CollectionItem, 'id' | 'order' | 'created_at'> & {
+	created_at?: number;
+};
+
+export interface CollectionMeta {
+	id: string;
+	name: string;
+	created_at: number;
+	description?: string;
+	cover_item_id?: number;
+	/** Акцентный цвет `#rrggbb`. */
+	accent_color?: string;
+	/** Есть у зашифрованных коллекций: файлы читаются только после unlockCollection. */
+	encryption?: { salt: string; password_hash: string; iterations: number };
+	items: CollectionItem[];
+}
+
+/** Редактируемые поля коллекции; null очищает поле. */
+export interface CollectionDetails {
+	description: string | null;
+	cover_item_id: number | null;
+	accent_color: string | null;
+}
+
+/** Путь к файлу элемента относительно base (для readAppFile и обоев). */
+export function collectionItemPath(collectionId: string, item: Pick<CollectionItem, 'file' | 'blob'>): string {
+	return item.blob ? `blobs/${item.blob.slice(0, 2)}/${item.blob}` : `collections/${collectionId}/${item.file}`;
+}
+
+/**
+ * Миниатюра (JPEG) для сеток: path — как для readAppFile, size — длинная сторона в px
+ * (Rust округляет вверх до 128/256/512/1024). Кешируется рядом с исходником и пересоздаётся при его изменении.
+ */
+export async function getThumbnail(path: string, size: number): Promise<Uint8Array> {
+	const buffer = await invoke<ArrayBuffer>('get_thumbnail', { path, size });
+	return new Uint8Array(buffer);
+}
+
 /**
  * Создать новую коллекцию. Возвращает уникальный ID коллекции.
  */
@@ -140,10 +242,87 @@ export async function createCollection(name: string): Promise<string> {
 /**
  * Получить список всех коллекций.
  */
-export async function listCollections(): Promise<
-	Array<{ id: string; name: string; created_at: number }>
-> {
-	return invoke<Array<{ id: string; name: string; created_at: number }>>('list_collections');
+export async function listCollections(): Promise<CollectionMeta[]> {
+	return invoke<CollectionMeta[]>('list_collections');
+}
+
+/** Метаданные коллекции вместе с элементами (читает и проверяет _meta.json на стороне Rust). */
+export async function getCollectionMeta(collectionId: string): Promise<CollectionMeta> {
+	return invoke<CollectionMeta>('get_collection_meta', { collectionId });
+}
+
+/** Создать зашифрованную коллекцию (она сразу разблокирована). Пароль не сохраняется. */
+export async function createEncryptedCollection(name: string, password: string): Promise<string> {
+	return invoke<string>('create_encrypted_collection', { name, password });
+}
+
+/** Затемнённая копия коллекции для ночи; brightness — оставляемая яркость, 1–99 %. */
+export async function createDimmedCollection(
+	collectionId: string,
+	name: string,
+	brightness: number
+): Promise<CollectionMeta> {
+	return invoke<CollectionMeta>('create_dimmed_collection', { collectionId, name, brightness });
+}
+
+/** Разблокировать зашифрованную коллекцию: ключ живёт в памяти до lockCollection или выхода. */
+export async function unlockCollection(collectionId: string, password: string): Promise<void> {
+	await invoke('unlock_collection', { collectionId, password });
+}
+
+export async function lockCollection(collectionId: string): Promise<void> {
+	await invoke('lock_collection', { collectionId });
+}
+
+export async function isCollectionUnlocked(collectionId: string): Promise<boolean> {
+	return invoke<boolean>('is_collection_unlocked', { collectionId });
+}
+
+/** Переименовать коллекцию. ID не меняется, активная ротация продолжает работать. */
+export async function renameCollection(collectionId: string, name: string): Promise<CollectionMeta> {
+	return invoke<CollectionMeta>('rename_collection', { collectionId, name });
+}
+
+/** Обновить описание, обложку и акцентный цвет коллекции. */
+export async function updateCollectionDetails(
+	collectionId: string,
+	details: CollectionDetails
+): Promise<CollectionMeta> {
+	return invoke<CollectionMeta>('update_collection_details', { collectionId, details });
+}
+
+/** Добавить элемент в коллекцию. id и order назначаются в Rust. Файл должен быть уже сохранён. */
+export async function addCollectionItem(
+	collectionId: string,
+	item: NewCollectionItem
+): Promise<CollectionItem> {
+	return invoke<CollectionItem>('add_collection_item', { collectionId, item });
+}
+
+/**
+ * Импортировать изображение с диска: обрезка, уменьшение до targetSize и WebP — в Rust,
+ * элемент сразу добавляется в коллекцию. Без crop берётся центральная область по пропорциям targetSize.
+ */
+export async function importImageToCollection(
+	collectionId: string,
+	sourcePath: string,
+	crop: { x: number; y: number; width: number; height: number } | null,
+	targetSize: { width: number; height: number } | null
+): Promise<CollectionItem> {
+	return invoke<CollectionItem>('import_image_to_collection', { collectionId, sourcePath, crop, targetSize });
+}
+
+/** Обновить элемент коллекции (по item.id). */
+export async function updateCollectionItem(
+	collectionId: string,
+	item: CollectionItem
+): Promise<CollectionItem> {
+	return invoke<CollectionItem>('update_collection_item', { collectionId, item });
+}
+
+/** Удалить элемент из коллекции в корзину. */
+export async function removeCollectionItem(collectionId: string, itemId: number): Promise<void> {
+	await invoke('remove_collection_item', { collectionId, itemId });
 }
 
 /**
@@ -164,25 +343,171 @@ export async function listCollectionFiles(collectionId: string): Promise<string[
 	return invoke<string[]>('list_collection_files', { collectionId });
 }
 
+/** Переместить коллекцию в корзину. */
 export async function deleteCollection(collectionId: string): Promise<void> {
 	await invoke('delete_collection', { collectionId });
 }
 
-/** Запустить фоновый сервис смены обоев (Android). Работает 24/7 при закрытом приложении. */
+/**
+ * Экспортировать коллекцию в ZIP: пользователь выбирает, куда сохранить.
+ * Возвращает путь к архиву или null, если выбор отменён.
+ */
+export async function exportCollectionZip(collectionId: string, suggestedName: string): Promise<string | null> {
+	const destPath = await save({
+		defaultPath: `${suggestedName}.zip`,
+		filters: [{ name: 'ZIP', extensions: ['zip'] }]
+	});
+	if (!destPath) return null;
+	await invoke('export_collection_zip', { collectionId, destPath });
+	return destPath;
+}
+
+/**
+ * Импортировать коллекцию из ZIP, выбранного пользователем.
+ * При совпадении id или названия с существующей коллекцией добавляется суффикс. null — выбор отменён.
+ */
+export async function importCollectionZip(): Promise<CollectionMeta | null> {
+	const srcPath = await open({
+		multiple: false,
+		directory: false,
+		filters: [{ name: 'ZIP', extensions: ['zip'] }]
+	});
+	if (!srcPath || Array.isArray(srcPath)) return null;
+	return invoke<CollectionMeta>('import_collection_zip', { srcPath });
+}
+
+export interface PlaylistMember {
+	collection_id: string;
+	/** Доля показов коллекции, 1–100. */
+	weight: number;
+}
+
+/** Плейлист — несколько коллекций с весами; запускается как коллекция (rotationStart с его id). */
+export interface Playlist {
+	id: string;
+	name: string;
+	/** Секунды с эпохи Unix. */
+	created_at: number;
+	members: PlaylistMember[];
+}
+
+export async function listPlaylists(): Promise<Playlist[]> {
+	return invoke<Playlist[]>('list_playlists');
+}
+
+export async function createPlaylist(name: string, members: PlaylistMember[]): Promise<Playlist> {
+	return invoke<Playlist>('create_playlist', { name, members });
+}
+
+/** Изменить плейлист; идущая по нему ротация подхватывает новый состав сразу. */
+export async function updatePlaylist(playlistId: string, name: string, members: PlaylistMember[]): Promise<Playlist> {
+	return invoke<Playlist>('update_playlist', { playlistId, name, members });
+}
+
+export async function deletePlaylist(playlistId: string): Promise<void> {
+	await invoke('delete_playlist', { playlistId });
+}
+
+/** Запись корзины: коллекция целиком, элемент коллекции или отдельный файл. */
+export type TrashEntry = {
+	id: string;
+	name: string;
+	original_path: string;
+	/** Время удаления, мс */
+	deleted_at: number;
+} & (
+	| { kind: 'collection'; collection_id: string }
+	| { kind: 'item'; collection_id: string; item: CollectionItem }
+	| { kind: 'file' }
+);
+
+/** Содержимое корзины (новые сверху). Просроченные записи удаляются при вызове. */
+export async function listTrash(): Promise<TrashEntry[]> {
+	return invoke<TrashEntry[]>('list_trash');
+}
+
+/** Восстановить запись. Коллекция с занятым id/названием получает суффикс. */
+export async function restoreFromTrash(entryId: string): Promise<TrashEntry> {
+	return invoke<TrashEntry>('restore_from_trash', { entryId });
+}
+
+/** Окончательно удалить запись или, без entryId, очистить всю корзину. */
+export async function emptyTrash(entryId?: string): Promise<void> {
+	await invoke('empty_trash', { entryId: entryId ?? null });
+}
+
+/** Срок хранения в корзине, дней (0 — без автоочистки). */
+export async function getTrashRetentionDays(): Promise<number> {
+	return invoke<number>('get_trash_retention_days');
+}
+
+export async function setTrashRetentionDays(days: number): Promise<void> {
+	await invoke('set_trash_retention_days', { days });
+}
+
+/** Запись истории смен обоев. */
+export interface WallpaperHistoryEntry {
+	/** Время смены, мс */
+	at: number;
+	path: string;
+	target: 'both' | 'home' | 'lock';
+	/** app — из приложения, service — фоновый сервис Android, desktop — цикл ротации на ПК. */
+	source: 'app' | 'service' | 'desktop';
+	collection_id?: string;
+}
+
+/** История смен обоев (новые сверху): не больше limit записей, не раньше since (мс). */
+export async function getWallpaperHistory(limit: number | null = null, since: number | null = null): Promise<WallpaperHistoryEntry[]> {
+	return invoke<WallpaperHistoryEntry[]>('get_wallpaper_history', { limit, since });
+}
+
+/** Срок хранения истории, дней (0 — только ограничение по числу записей). */
+export async function getHistoryRetentionDays(): Promise<number> {
+	return invoke<number>('get_history_retention_days');
+}
+
+export async function setHistoryRetentionDays(days: number): Promise<void> {
+	await invoke('set_history_retention_days', { days });
+}
+
+export interface WallpaperBackendInfo {
+	detected: string | null;
+	/** Выбранное вручную окружение; null — автоопределение. */
+	selected: string | null;
+	available: string[];
+	/** Окружение умеет менять экран блокировки — можно запустить отдельную ротацию для него. */
+	supports_lock: boolean;
+}
+
+/** Окружение рабочего стола для смены обоев на ПК (gnome, kde, sway, hyprland, x11). */
+export async function getWallpaperBackend(): Promise<WallpaperBackendInfo> {
+	return invoke<WallpaperBackendInfo>('get_wallpaper_backend');
+}
+
+export async function setWallpaperBackend(backend: string | null): Promise<void> {
+	await invoke('set_wallpaper_backend', { backend });
+}
+
+/**
+ * Слот ротации: главный экран ('home', по умолчанию) и отдельная ротация экрана блокировки ('lock').
+ * У каждого своя коллекция, интервал и позиция; пока идёт 'lock', 'home' меняет только главный экран.
+ */
+export type RotationSlot = 'home' | 'lock';
+
+/**
+ * Запустить смену обоев по движку, собранному rotationStart (на Android — фоновый сервис,
+ * работает 24/7 при закрытом приложении). Порядок и позицию ведёт движок в Rust.
+ */
 export async function startWallpaperRotationService(params: {
 	intervalMinutes: number;
 	target: 'both' | 'lock' | 'home';
-	rotationIndex: number;
-	lastChangeAt: number;
-	sequence: string[];
+	slot?: RotationSlot;
 }): Promise<void> {
 	try {
 		await invoke('start_wallpaper_rotation_service', {
 			intervalMinutes: Math.max(15, params.intervalMinutes),
 			target: params.target,
-			rotationIndex: params.rotationIndex,
-			lastChangeAt: params.lastChangeAt,
-			sequence: params.sequence
+			slot: params.slot ?? null
 		});
 	} catch (e) {
 		console.error('Failed to start wallpaper rotation service:', e);
@@ -190,44 +515,192 @@ export async function startWallpaperRotationService(params: {
 	}
 }
 
-/** Остановить фоновый сервис смены обоев (Android). */
-export async function stopWallpaperRotationService(): Promise<void> {
-	try {
-		await invoke('stop_wallpaper_rotation_service');
-	} catch (e) {
-		console.error('Failed to stop wallpaper rotation service:', e);
-		// Не пробрасываем ошибку дальше - возможно сервис уже остановлен
-	}
+/** Состояние ротации из RotationEngine (Rust): порядок, текущие обои и срок следующей смены. */
+/** Окно расписания: коллекция в часы start–end (местное время, "ЧЧ:ММ") по дням 1–7 (пн–вс). */
+export interface ScheduleRule {
+	collection_id: string;
+	/** Пусто — каждый день. */
+	days: number[];
+	start: string;
+	/** Конец не позже начала — окно через полночь. */
+	end: string;
 }
 
-/** Обновить состояние ротации в prefs (Android), чтобы сервис и таймер были в синхронизации. */
-export async function updateRotationPrefs(params: {
-	intervalMinutes: number;
-	target: 'both' | 'lock' | 'home';
-	rotationIndex: number;
-	lastChangeAt: number;
+/** Смена коллекций по восходу и закату в заданной точке. */
+export interface SolarRule {
+	latitude: number;
+	longitude: number;
+	/** Коллекция от восхода до заката. */
+	day: string;
+	/** Коллекция от заката до восхода (например, затемнённая копия дневной). */
+	night: string;
+}
+
+export interface RotationSchedule {
+	rules: ScheduleRule[];
+	/** Действует вне окон rules; null — не используется. */
+	solar?: SolarRule | null;
+	/** Коллекция вне окон; null — вне окон обои не меняются. */
+	fallback: string | null;
+	/** Моменты смены в формате cron ("0 7,19 * * *"); null — по интервалу. */
+	change_at: string | null;
+}
+
+export interface RotationState {
+	collection_id: string;
+	mode: 'queue' | 'random';
+	interval_minutes: number;
 	sequence: string[];
-}): Promise<void> {
+	index: number;
+	/** Путь обоев, которые нужно поставить сейчас. */
+	current: string | null;
+	last_change_at: number | null;
+	next_due_at: number;
+	schedule: RotationSchedule | null;
+	/** Случайный режим: сколько последних показанных не повторяется на стыке кругов. */
+	no_repeat: number;
+	recent: string[];
+	/** До какого времени закреплены текущие обои (мс); null — не закреплены. */
+	pinned_until: number | null;
+}
+
+export interface RotationTick extends RotationState {
+	/** Обои сменились — current нужно поставить. */
+	due: boolean;
+}
+
+/**
+ * Начать ротацию коллекции или плейлиста (collectionId — id любого из них). current нужно поставить сразу — смена уже отмечена в движке.
+ * С расписанием ротация начинается с коллекции, которую оно выбирает сейчас.
+ */
+export async function rotationStart(
+	collectionId: string,
+	mode: 'queue' | 'random',
+	intervalMinutes: number,
+	schedule: RotationSchedule | null = null,
+	noRepeat: number | null = null,
+	slot: RotationSlot = 'home'
+): Promise<RotationState> {
+	return invoke<RotationState>('rotation_start', { collectionId, mode, intervalMinutes, schedule, noRepeat, slot });
+}
+
+/** Окно без повторов идущей ротации. null — ротация не запущена. */
+export async function rotationSetNoRepeat(noRepeat: number, slot: RotationSlot = 'home'): Promise<RotationState | null> {
+	return invoke<RotationState | null>('rotation_set_no_repeat', { noRepeat, slot });
+}
+
+/** Шаг по таймеру: обои меняются (due), только если срок наступил. null — ротация не запущена. */
+export async function rotationTick(slot: RotationSlot = 'home'): Promise<RotationTick | null> {
+	return invoke<RotationTick | null>('rotation_tick', { slot });
+}
+
+/** Задать или снять (null) расписание идущей ротации. */
+export async function rotationSetSchedule(
+	schedule: RotationSchedule | null,
+	slot: RotationSlot = 'home'
+): Promise<RotationState | null> {
+	return invoke<RotationState | null>('rotation_set_schedule', { schedule, slot });
+}
+
+/** Перейти к следующим обоям (current в ответе). */
+export async function rotationAdvance(slot: RotationSlot = 'home'): Promise<RotationState> {
+	return invoke<RotationState>('rotation_advance', { slot });
+}
+
+/** Следующие обои идущей ротации: ставятся сразу, следующая смена — через интервал. */
+export async function rotateNext(slot: RotationSlot = 'home'): Promise<RotationState> {
+	return invoke<RotationState>('rotate_next', { slot });
+}
+
+/** Предыдущие обои идущей ротации. */
+export async function rotatePrevious(slot: RotationSlot = 'home'): Promise<RotationState> {
+	return invoke<RotationState>('rotate_previous', { slot });
+}
+
+/** Закрепить текущие обои на durationMinutes минут; 0 — снять закрепление. */
+export async function pinCurrent(durationMinutes: number, slot: RotationSlot = 'home'): Promise<RotationState> {
+	return invoke<RotationState>('pin_current', { durationMinutes, slot });
+}
+
+/** Изменить интервал идущей ротации. null — ротация не запущена. */
+export async function rotationSetInterval(intervalMinutes: number, slot: RotationSlot = 'home'): Promise<RotationState | null> {
+	return invoke<RotationState | null>('rotation_set_interval', { intervalMinutes, slot });
+}
+
+export async function rotationStop(slot: RotationSlot = 'home'): Promise<void> {
+	await invoke('rotation_stop', { slot });
+}
+
+/** Текущее состояние ротации слота из движка; null — слот не запущен. */
+export async function rotationState(slot: RotationSlot = 'home'): Promise<RotationState | null> {
+	return invoke<RotationState | null>('rotation_state', { slot });
+}
+
+/** Остановить фоновый сервис смены обоев (Android) для слота; сервис работает, пока запущен другой слот. */
+export async function stopWallpaperRotationService(slot: RotationSlot = 'home'): Promise<void> {
 	try {
-		await invoke('update_rotation_prefs', {
-			intervalMinutes: params.intervalMinutes,
-			target: params.target,
-			rotationIndex: params.rotationIndex,
-			lastChangeAt: params.lastChangeAt,
-			sequence: params.sequence
-		});
+		await invoke('stop_wallpaper_rotation_service', { slot });
 	} catch (e) {
-		console.error('Failed to update rotation prefs:', e);
-		// Не пробрасываем ошибку дальше
+		console.error('Failed to stop wallpaper rotation service:', e);
+		// Не пробрасываем ошибку дальше - возможно сервис уже остановлен
 	}
 }
 
-/** Получить текущее состояние ротации из prefs (Android). Для синхронизации при открытии приложения. */
-export async function getWallpaperRotationState(): Promise<[number, number] | null> {
+/**
+ * Текущее состояние ротации слота: индекс, время последней смены
+ * и срок закрепления (0 — не закреплены). Для синхронизации при открытии приложения.
+ */
+export async function getWallpaperRotationState(slot: RotationSlot = 'home'): Promise<[number, number, number] | null> {
 	try {
-		return await invoke<[number, number]>('get_wallpaper_rotation_state');
+		return await invoke<[number, number, number]>('get_wallpaper_rotation_state', { slot });
 	} catch {
 		return null;
 	}
 }
 
+
+/** Обои поставлены — кем угодно: приложением, фоновым сервисом Android или циклом на ПК. */
+export interface WallpaperChangedEvent extends WallpaperHistoryEntry {
+	/** Слот ротации, сменившей обои; null — поставлены вручную вне ротации. */
+	slot: RotationSlot | null;
+	/** Состояние слота после смены. */
+	state: RotationState | null;
+}
+
+export interface RotationStartedEvent {
+	slot: RotationSlot;
+	state: RotationState | null;
+}
+
+/** Состояние идущей ротации изменилось без смены обоев (расписание, интервал, закрепление, шаг). */
+export interface RotationUpdatedEvent {
+	slot: RotationSlot;
+	state: RotationState;
+}
+
+export interface RotationStoppedEvent {
+	slot: RotationSlot;
+}
+
+export interface RotationErrorEvent {
+	slot: RotationSlot | null;
+	source: WallpaperHistoryEntry['source'];
+	message: string;
+}
+
+/** События ротации из Rust: имя события → данные. */
+export interface RotationEvents {
+	'wallpaper-changed': WallpaperChangedEvent;
+	'rotation-started': RotationStartedEvent;
+	'rotation-updated': RotationUpdatedEvent;
+	'rotation-stopped': RotationStoppedEvent;
+	'rotation-error': RotationErrorEvent;
+}
+
+/** Подписаться на событие ротации; возвращает функцию отписки. */
+export async function onRotationEvent<K extends keyof RotationEvents>(
+	event: K,
+	handler: (payload: RotationEvents[K]) => void
+): Promise<UnlistenFn> {
+	return listen<RotationEvents[K]>(event, (e) => handler(e.payload));
+}
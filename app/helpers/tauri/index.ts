@@ -1,4 +1,6 @@
 export { getDeviceInfo } from './device'
 export type { DeviceInfo, PlatformType } from './device'
-export { pickAndSaveFile, getSaveFolderTypeFromFileName, setDeviceWallpaper, readAppFile, saveFileToCollection, deleteAppFile } from './file'
-export type { SaveFolderType } from './file'
+export { pickAndSaveFile, getSaveFolderTypeFromFileName, setDeviceWallpaper, readAppFile, saveFileToCollection, uploadBytes, deleteAppFile } from './file'
+export type { SaveFolderType, CollectionMeta, CollectionItem, NewCollectionItem, CollectionDetails, TrashEntry } from './file'
+export { errorCode, errorMessage, isAppError } from './error'
+export type { AppError, AppErrorCode } from './error'
@@ -15,15 +15,26 @@ export default {
 	settings: {
 		title: 'Settings',
 		frequencyLabel: 'Wallpaper change frequency',
+		lockFrequencyLabel: 'Lock screen change frequency',
 		currentLabel: 'Current: {label}',
 		orderLabel: 'Display order',
 		orderQueue: 'Queue (newest → oldest)',
 		orderRandom: 'Random, no repeats',
 		orderHint: 'Queue: newest → oldest; Random: no repeats per cycle',
+		noRepeatLabel: 'Do not repeat recent wallpapers',
+		noRepeatCount: 'Last {n}',
 		targetLabel: 'Where to set wallpaper',
 		targetBoth: 'Home and lock screen',
 		targetLock: 'Lock screen only',
 		targetHome: 'Home screen only',
+		trashRetentionLabel: 'Keep deleted items in trash',
+		trashRetentionDays: '{n} days',
+		trashRetentionNever: 'Until emptied manually',
+		historyRetentionLabel: 'Keep wallpaper history',
+		historyRetentionNoLimit: 'Only the latest 10,000 changes',
+		wallpaperBackendLabel: 'Desktop environment',
+		wallpaperBackendAuto: 'Detect automatically',
+		wallpaperBackendAutoDetected: 'Detect automatically ({name})',
 	},
 
 	interval: {
@@ -41,7 +52,29 @@ export default {
 		active: 'Active',
 		pause: 'Pause',
 		start: 'Start',
+		previous: 'Previous wallpaper',
+		next: 'Next wallpaper',
+		pin: 'Keep this wallpaper',
+		pinFor: 'Keep for {n} h',
+		pinnedUntil: 'Kept until {time}',
+		unpin: 'Resume rotation',
 		defaultName: 'Collection',
+		import: 'Import from ZIP',
+		importFailed: 'Could not import the collection: {error}',
+		encrypted: 'Encrypted',
+		lockStart: 'Rotate lock screen',
+		lockStop: 'Stop lock screen rotation',
+		lockActive: 'Lock screen',
+	},
+
+	playlists: {
+		title: 'Playlists',
+		create: 'Create playlist',
+		edit: 'Edit playlist',
+		emptyHint: 'A playlist rotates several collections at once, each with its own weight',
+		nameLabel: 'Playlist name',
+		weightHint: 'Pick collections; a higher weight shows a collection more often',
+		missing: 'Deleted collection',
 	},
 
 	collectionCreate: {
@@ -49,12 +82,15 @@ export default {
 		nameLabel: 'Collection name',
 		nameRequired: 'Name is required',
 		nameExists: 'A collection with this name already exists',
+		encrypt: 'Encrypt with a password',
+		passwordLabel: 'Password',
+		passwordHint: 'The password is not stored and cannot be recovered',
 	},
 
 	collectionDelete: {
 		title: 'Delete collection',
 		confirm: 'Are you sure you want to delete the collection "{name}"?',
-		deleteFiles: 'Delete files too',
+		deleteFiles: 'Move the collection and its photos to the trash',
 	},
 
 	addPhoto: {
@@ -72,11 +108,87 @@ export default {
 		pageOf: 'Page {current} of {total} · {count} photos',
 		deletePhotoTitle: 'Delete photo',
 		deletePhotoConfirm: 'Are you sure you want to delete this photo from the collection?',
+		edit: 'Edit',
+		editTitle: 'Edit collection',
+		descriptionLabel: 'Description',
+		accentLabel: 'Accent colour',
+		setCover: 'Use as cover',
+		export: 'Export ZIP',
+		lock: 'Lock',
+		unlock: 'Unlock',
+		lockedHint: 'This collection is encrypted. Enter the password to view it.',
+		wrongPassword: 'Wrong password',
+	},
+
+	trash: {
+		title: 'Trash',
+		empty: 'Empty trash',
+		emptyTitle: 'Trash is empty',
+		emptyConfirm: 'Permanently delete everything in the trash?',
+		restore: 'Restore',
+		deleteForever: 'Delete permanently',
+		retentionHint: 'Items are deleted permanently after {n} days',
+		retentionNever: 'Items are kept until the trash is emptied',
+		restoreCollectionFirst: 'This photo belongs to a deleted collection. Restore the collection first',
+		kind: {
+			collection: 'Collection',
+			item: 'Photo',
+			file: 'File',
+		},
+	},
+
+	history: {
+		title: 'Wallpaper history',
+		emptyTitle: 'No wallpaper changes yet',
+		applyAgain: 'Set as wallpaper again',
+		periodDays: 'Last {n} days',
+		periodAll: 'All time',
+		source: {
+			app: 'App',
+			service: 'Background service',
+			desktop: 'Desktop rotation',
+		},
+		target: {
+			both: 'Home and lock screen',
+			home: 'Home screen',
+			lock: 'Lock screen',
+		},
+	},
+
+	errors: {
+		notFound: '{kind} not found',
+		pathNotAllowed: 'Access to this path is not allowed: {path}',
+		unsupported: '{feature} is not supported on this device',
+		notEncrypted: 'This collection is not encrypted',
+		decodeFailed: 'Could not read the image: {path}',
+		io: 'File error: {reason}',
+		jni: 'Android system error: {reason}',
+		duplicateName: {
+			collection: 'A collection named "{name}" already exists',
+			playlist: 'A playlist named "{name}" already exists',
+			file: '"{name}" already exists',
+		},
+		kind: {
+			collection: 'Collection',
+			item: 'Photo',
+			playlist: 'Playlist',
+			trash_entry: 'Trash item',
+			blob: 'Stored file',
+			file: 'File',
+			rotation: 'Running rotation',
+			upload: 'Upload',
+		},
+		feature: {
+			lock_screen: 'A separate lock screen wallpaper',
+			desktop_environment: 'This desktop environment',
+			wallpaper_backend: 'Choosing the desktop environment',
+			screen_size: 'Reading the screen size',
+			encrypted_collection: 'This action for an encrypted collection',
+			thumbnail: 'A preview for this file',
+		},
 	},
 
 	warnings: {
 		rotationStoppedSettings: 'Rotation stopped: settings changed. Start the collection again.',
-		rotationStoppedPhotoAdded: 'Rotation stopped: photo added to collection. Start the collection again.',
-		rotationStoppedPhotoDeleted: 'Rotation stopped: photo deleted from collection. Start the collection again.',
 	},
 };
@@ -15,15 +15,26 @@ export default {
 	settings: {
 		title: 'Настройки',
 		frequencyLabel: 'Частота смены фото',
+		lockFrequencyLabel: 'Частота смены на экране блокировки',
 		currentLabel: 'Текущая: {label}',
 		orderLabel: 'Отображать по',
 		orderQueue: 'Очереди (новые → старые)',
 		orderRandom: 'Рандомно без повторений',
 		orderHint: 'Очереди: новые → старые; Рандом: без повторений за круг',
+		noRepeatLabel: 'Не повторять недавние обои',
+		noRepeatCount: 'Последние {n}',
 		targetLabel: 'Куда ставить обои',
 		targetBoth: 'Экран и блокировка',
 		targetLock: 'Только блокировка',
 		targetHome: 'Только главный экран',
+		trashRetentionLabel: 'Хранить удалённое в корзине',
+		trashRetentionDays: '{n} дн.',
+		trashRetentionNever: 'Пока не очищу вручную',
+		historyRetentionLabel: 'Хранить историю обоев',
+		historyRetentionNoLimit: 'Только последние 10 000 смен',
+		wallpaperBackendLabel: 'Окружение рабочего стола',
+		wallpaperBackendAuto: 'Определять автоматически',
+		wallpaperBackendAutoDetected: 'Определять автоматически ({name})',
 	},
 
 	interval: {
@@ -41,7 +52,29 @@ export default {
 		active: 'Активна',
 		pause: 'Пауза',
 		start: 'Старт',
+		previous: 'Предыдущие обои',
+		next: 'Следующие обои',
+		pin: 'Оставить эти обои',
+		pinFor: 'Оставить на {n} ч',
+		pinnedUntil: 'Оставлены до {time}',
+		unpin: 'Продолжить ротацию',
 		defaultName: 'Коллекция',
+		import: 'Импорт из ZIP',
+		importFailed: 'Не удалось импортировать коллекцию: {error}',
+		encrypted: 'Зашифрована',
+		lockStart: 'Ротация экрана блокировки',
+		lockStop: 'Остановить ротацию блокировки',
+		lockActive: 'Блокировка',
+	},
+
+	playlists: {
+		title: 'Плейлисты',
+		create: 'Создать плейлист',
+		edit: 'Изменить плейлист',
+		emptyHint: 'Плейлист чередует несколько коллекций, у каждой свой вес',
+		nameLabel: 'Название плейлиста',
+		weightHint: 'Выберите коллекции; чем больше вес, тем чаще показывается коллекция',
+		missing: 'Удалённая коллекция',
 	},
 
 	collectionCreate: {
@@ -49,12 +82,15 @@ export default {
 		nameLabel: 'Название коллекции',
 		nameRequired: 'Название обязательно',
 		nameExists: 'Коллекция с таким названием уже существует',
+		encrypt: 'Зашифровать паролем',
+		passwordLabel: 'Пароль',
+		passwordHint: 'Пароль не сохраняется, восстановить его нельзя',
 	},
 
 	collectionDelete: {
 		title: 'Удаление коллекции',
 		confirm: 'Вы уверены, что хотите удалить коллекцию «{name}»?',
-		deleteFiles: 'Файлы тоже удалить',
+		deleteFiles: 'Переместить коллекцию и её фото в корзину',
 	},
 
 	addPhoto: {
@@ -72,11 +108,87 @@ export default {
 		pageOf: 'Страница {current} из {total} · {count} фото',
 		deletePhotoTitle: 'Удаление фото',
 		deletePhotoConfirm: 'Вы уверены, что хотите удалить это фото из коллекции?',
+		edit: 'Изменить',
+		editTitle: 'Редактирование коллекции',
+		descriptionLabel: 'Описание',
+		accentLabel: 'Акцентный цвет',
+		setCover: 'Сделать обложкой',
+		export: 'Экспорт ZIP',
+		lock: 'Заблокировать',
+		unlock: 'Разблокировать',
+		lockedHint: 'Коллекция зашифрована. Введите пароль, чтобы открыть её.',
+		wrongPassword: 'Неверный пароль',
+	},
+
+	trash: {
+		title: 'Корзина',
+		empty: 'Очистить корзину',
+		emptyTitle: 'Корзина пуста',
+		emptyConfirm: 'Удалить всё содержимое корзины без возможности восстановления?',
+		restore: 'Восстановить',
+		deleteForever: 'Удалить навсегда',
+		retentionHint: 'Удалённое хранится {n} дн., затем удаляется окончательно',
+		retentionNever: 'Удалённое хранится, пока корзину не очистят',
+		restoreCollectionFirst: 'Фото из удалённой коллекции. Сначала восстановите коллекцию',
+		kind: {
+			collection: 'Коллекция',
+			item: 'Фото',
+			file: 'Файл',
+		},
+	},
+
+	history: {
+		title: 'История обоев',
+		emptyTitle: 'Обои ещё не менялись',
+		applyAgain: 'Поставить снова',
+		periodDays: 'За {n} дн.',
+		periodAll: 'За всё время',
+		source: {
+			app: 'Приложение',
+			service: 'Фоновый сервис',
+			desktop: 'Ротация на ПК',
+		},
+		target: {
+			both: 'Рабочий стол и экран блокировки',
+			home: 'Рабочий стол',
+			lock: 'Экран блокировки',
+		},
+	},
+
+	errors: {
+		notFound: 'Не найдено: {kind}',
+		pathNotAllowed: 'Доступ к этому пути запрещён: {path}',
+		unsupported: 'Не поддерживается на этом устройстве: {feature}',
+		notEncrypted: 'Эта коллекция не зашифрована',
+		decodeFailed: 'Не удалось прочитать изображение: {path}',
+		io: 'Ошибка файла: {reason}',
+		jni: 'Ошибка системы Android: {reason}',
+		duplicateName: {
+			collection: 'Коллекция «{name}» уже существует',
+			playlist: 'Плейлист «{name}» уже существует',
+			file: '«{name}» уже существует',
+		},
+		kind: {
+			collection: 'коллекция',
+			item: 'фото',
+			playlist: 'плейлист',
+			trash_entry: 'запись корзины',
+			blob: 'файл хранилища',
+			file: 'файл',
+			rotation: 'запущенная ротация',
+			upload: 'загрузка',
+		},
+		feature: {
+			lock_screen: 'отдельные обои экрана блокировки',
+			desktop_environment: 'это окружение рабочего стола',
+			wallpaper_backend: 'выбор окружения рабочего стола',
+			screen_size: 'определение размера экрана',
+			encrypted_collection: 'это действие для зашифрованной коллекции',
+			thumbnail: 'миниатюра для этого файла',
+		},
 	},
 
 	warnings: {
 		rotationStoppedSettings: 'Ротация отключена: изменены настройки. Запустите коллекцию заново.',
-		rotationStoppedPhotoAdded: 'Ротация отключена: в коллекцию добавлено фото. Запустите коллекцию заново.',
-		rotationStoppedPhotoDeleted: 'Ротация отключена: фото удалено из коллекции. Запустите коллекцию заново.',
 	},
 };
@@ -3,13 +3,21 @@ import { useTheme } from 'vuetify';
 import { computed } from 'vue';
 import type { IUserData } from '~/types/appStore';
 import {
-	listCollectionFiles,
-	readAppFile,
+	onRotationEvent,
+	pinCurrent,
+	rotateNext,
+	rotatePrevious,
+	rotationSetInterval,
+	rotationSetNoRepeat,
+	rotationSetSchedule,
+	rotationStart,
+	rotationState,
+	rotationStop,
 	setDeviceWallpaper,
 	startWallpaperRotationService,
-	stopWallpaperRotationService,
-	updateRotationPrefs
+	stopWallpaperRotationService
 } from '~/helpers/tauri/file';
+import type { RotationSchedule, RotationState } from '~/helpers/tauri/file';
 
 export const useAppStore = defineStore('app', () => {
 	const userDark = ref(false);
@@ -17,14 +25,24 @@ export const useAppStore = defineStore('app', () => {
 	const changeIntervalMinutes = ref(60);
 	const wallpaperTarget = ref<'both' | 'lock' | 'home'>('both');
 	const rotationMode = ref<'queue' | 'random'>('queue');
+	/** Случайный режим: сколько последних обоев не повторять. */
+	const noRepeat = ref(1);
 	const activeCollectionId = ref<string | null>(null);
 	const isRotating = ref(false);
 	/** Сообщение для предупреждения на главной (ротация отключена из‑за смены настроек или добавления фото). */
 	const rotationStoppedWarning = ref<string | null>(null);
+	/** Последняя ошибка ротации из события rotation-error (сервис или цикл не смогли сменить обои). */
+	const rotationError = ref<string | null>(null);
 	const currentIndex = ref(0);
 	const sequence = ref<string[]>([]);
 	const lastChangeAt = ref<number | null>(null);
-	let timer: any = null;
+	/** Текущие обои закреплены до этого времени (мс). */
+	const pinnedUntil = ref<number | null>(null);
+	/** Расписание (коллекции по времени, cron); считается в Rust. */
+	const schedule = ref<RotationSchedule | null>(null);
+	/** Отдельная ротация экрана блокировки: своя коллекция, интервал и позиция. */
+	const lockCollectionId = ref<string | null>(null);
+	const lockChangeIntervalMinutes = ref(60);
 
 	const theme = useTheme();
 
@@ -58,6 +76,8 @@ export const useAppStore = defineStore('app', () => {
 		if (savedRotation === 'queue' || savedRotation === 'random') {
 			rotationMode.value = savedRotation as 'queue' | 'random';
 		}
+		const savedNoRepeat = Number(localStorage.getItem('rotationNoRepeat'));
+		if (Number.isInteger(savedNoRepeat) && savedNoRepeat > 0) noRepeat.value = savedNoRepeat;
 		const savedActive = localStorage.getItem('activeCollectionId');
 		if (savedActive) {
 			activeCollectionId.value = savedActive;
@@ -68,6 +88,18 @@ export const useAppStore = defineStore('app', () => {
 		if (savedIdx) currentIndex.value = Math.max(0, Number(savedIdx) || 0);
 		const savedLast = localStorage.getItem('rotationLastChangeAt');
 		if (savedLast) lastChangeAt.value = Number(savedLast) || null;
+		const savedLockInterval = Number(localStorage.getItem('lockIntervalMinutes'));
+		if (savedLockInterval >= MIN_INTERVAL_MINUTES) lockChangeIntervalMinutes.value = savedLockInterval;
+		lockCollectionId.value = localStorage.getItem('lockCollectionId');
+		const savedSchedule = localStorage.getItem('rotationSchedule');
+		if (savedSchedule) {
+			try {
+				schedule.value = JSON.parse(savedSchedule);
+			} catch {
+				localStorage.removeItem('rotationSchedule');
+			}
+		}
+		subscribeRotationEvents().catch((e) => console.warn('Rotation events are unavailable:', e));
 	}
 
 	const intervalMinutes = computed({
@@ -78,9 +110,35 @@ export const useAppStore = defineStore('app', () => {
 			if (typeof window !== 'undefined') {
 				localStorage.setItem('changeIntervalMinutes', String(clamped));
 			}
+			if (isRotating.value) {
+				rotationSetInterval(clamped)
+					.then((state) => {
+						if (state) applyState(state);
+					})
+					.catch(() => {});
+			}
 		}
 	});
 
+	const lockIntervalMinutes = computed({
+		get: () => lockChangeIntervalMinutes.value,
+		set: (val: number) => {
+			const clamped = Math.max(MIN_INTERVAL_MINUTES, val);
+			lockChangeIntervalMinutes.value = clamped;
+			if (typeof window !== 'undefined') {
+				localStorage.setItem('lockIntervalMinutes', String(clamped));
+			}
+			if (lockCollectionId.value) {
+				rotationSetInterval(clamped, 'lock').catch(() => {});
+			}
+		}
+	});
+
+	const isLockRotating = computed(() => lockCollectionId.value !== null);
+
+	/** Куда ставит обои главная ротация: пока идёт ротация блокировки, только на главный экран. */
+	const homeTarget = computed(() => (isLockRotating.value ? 'home' : wallpaperTarget.value));
+
 	const wallpaperTargetMode = computed({
 		get: () => wallpaperTarget.value,
 		set: (val: 'both' | 'lock' | 'home') => {
@@ -98,16 +156,39 @@ export const useAppStore = defineStore('app', () => {
 			if (typeof window !== 'undefined') {
 				localStorage.setItem('rotationMode', val);
 			}
-			if (activeCollectionId.value) {
-				loadSequenceForCollection(activeCollectionId.value).then((seq) => {
-					sequence.value = seq;
-					currentIndex.value = 0;
-					persistRotation();
-				});
+		}
+	});
+
+	const noRepeatWindow = computed({
+		get: () => noRepeat.value,
+		set: (val: number) => {
+			noRepeat.value = Math.max(0, Math.floor(val));
+			if (typeof window !== 'undefined') {
+				localStorage.setItem('rotationNoRepeat', String(noRepeat.value));
+			}
+			if (isRotating.value) {
+				rotationSetNoRepeat(noRepeat.value).catch(() => {});
 			}
 		}
 	});
 
+	/** Задать расписание; идущая ротация подхватывает его сразу. */
+	async function setSchedule(value: RotationSchedule | null) {
+		if (typeof window !== 'undefined') {
+			if (value) localStorage.setItem('rotationSchedule', JSON.stringify(value));
+			else localStorage.removeItem('rotationSchedule');
+		}
+		if (!isRotating.value) {
+			schedule.value = value;
+			return;
+		}
+		// Ошибку проверки расписания (неверное окно, cron) получает вызывающий
+		const state = await rotationSetSchedule(value);
+		schedule.value = value;
+		if (!state) return;
+		applyState(state);
+	}
+
 	function persistRotation() {
 		if (typeof window === 'undefined') return;
 		if (activeCollectionId.value) {
@@ -121,105 +202,110 @@ export const useAppStore = defineStore('app', () => {
 		}
 	}
 
-	function clearTimer() {
-		if (timer) {
-			clearTimeout(timer);
-			timer = null;
-		}
+	/** Принять состояние из RotationEngine: порядок и время следующей смены считает Rust. */
+	function applyState(state: RotationState) {
+		sequence.value = state.sequence;
+		currentIndex.value = state.index;
+		lastChangeAt.value = state.last_change_at;
+		pinnedUntil.value = state.pinned_until;
 	}
 
-	function shuffle<T>(arr: T[]): T[] {
-		const a = [...arr];
-		for (let i = a.length - 1; i > 0; i--) {
-			const j = Math.floor(Math.random() * (i + 1));
-			[a[i], a[j]] = [a[j], a[i]];
-		}
-		return a;
+	/**
+	 * Ручное управление идущей ротацией. Обои ставит Rust, и сервис на Android продолжает с того же
+	 * состояния; здесь только принимаем новое.
+	 */
+	async function manualStep(action: () => Promise<RotationState>) {
+		const state = await action();
+		applyState(state);
+		persistRotation();
 	}
 
-	async function loadSequenceForCollection(id: string): Promise<string[]> {
-		try {
-			const bytes = await readAppFile(`collections/${id}/_meta.json`);
-			const text = new TextDecoder().decode(bytes);
-			const meta = JSON.parse(text);
-			if (Array.isArray(meta.items) && meta.items.length > 0) {
-				let items = [...meta.items];
-				if (rotationMode.value === 'queue') {
-					items.sort((a: any, b: any) => {
-						const ao = Number(a.order) || Number(a.id) || 0;
-						const bo = Number(b.order) || Number(b.id) || 0;
-						return bo - ao; // новые -> старые
-					});
-				} else {
-					items.sort((a: any, b: any) => {
-						const ao = Number(a.order) || Number(a.id) || 0;
-						const bo = Number(b.order) || Number(b.id) || 0;
-						return ao - bo;
-					});
-					items = shuffle(items);
-				}
-				return items
-					.map((it: any) => it?.file)
-					.filter((f: any) => typeof f === 'string' && f.length > 0)
-					.map((f: string) => `collections/${id}/${f}`);
+	const nextWallpaper = () => manualStep(rotateNext);
+	const previousWallpaper = () => manualStep(rotatePrevious);
+	/** Закрепить текущие обои на minutes минут; 0 — снять закрепление. */
+	const pinWallpaper = (minutes: number) => manualStep(() => pinCurrent(minutes));
+
+	async function applyWallpaper(path: string, collectionId: string | null = activeCollectionId.value) {
+		await setDeviceWallpaper(path, homeTarget.value, collectionId);
+	}
+
+	/**
+	 * Обои меняют WallpaperRotationService (Android) и цикл в Rust (ПК), а Rust сообщает о каждой смене
+	 * событием — интерфейс обновляется по ним, без своих таймеров.
+	 */
+	async function subscribeRotationEvents() {
+		await onRotationEvent('wallpaper-changed', ({ slot, state }) => {
+			if (!state) return;
+			if (slot === 'home') {
+				applyState(state);
+				persistRotation();
+			}
+		});
+		await onRotationEvent('rotation-started', ({ slot, state }) => {
+			if (slot === 'home' && state) applyState(state);
+		});
+		await onRotationEvent('rotation-updated', ({ slot, state }) => {
+			if (slot !== 'home') return;
+			applyState(state);
+			persistRotation();
+		});
+		await onRotationEvent('rotation-stopped', ({ slot }) => {
+			if (slot === 'lock') {
+				lockCollectionId.value = null;
+				persistLockRotation();
+				return;
 			}
-		} catch {}
-		const files = await listCollectionFiles(id);
-		if (rotationMode.value === 'queue') {
-			return [...files].reverse(); // приблизительно новые -> старые
+			isRotating.value = false;
+			activeCollectionId.value = null;
+			pinnedUntil.value = null;
+			persistRotation();
+		});
+		await onRotationEvent('rotation-error', ({ message }) => {
+			rotationError.value = message;
+		});
+	}
+
+	function persistLockRotation() {
+		if (typeof window === 'undefined') return;
+		if (lockCollectionId.value) localStorage.setItem('lockCollectionId', lockCollectionId.value);
+		else localStorage.removeItem('lockCollectionId');
+	}
+
+	/** Запустить отдельную ротацию экрана блокировки; главная ротация дальше меняет только главный экран. */
+	async function startLockCollection(id: string) {
+		const state = await rotationStart(id, rotationMode.value, lockIntervalMinutes.value, null, noRepeat.value, 'lock');
+		if (!state.current) {
+			rotationStop('lock').catch(() => {});
+			return;
 		}
-		return shuffle(files);
+		lockCollectionId.value = id;
+		persistLockRotation();
+		await setDeviceWallpaper(state.current, 'lock', state.collection_id);
+		await startWallpaperRotationService({
+			intervalMinutes: state.interval_minutes,
+			target: wallpaperTarget.value,
+			slot: 'lock'
+		});
 	}
 
-	async function applyWallpaper(path: string) {
-		await setDeviceWallpaper(path, wallpaperTarget.value);
+	async function stopLockRotation() {
+		lockCollectionId.value = null;
+		persistLockRotation();
+		await rotationStop('lock').catch(() => {});
+		await stopWallpaperRotationService('lock');
 	}
 
-	function scheduleNext() {
-		clearTimer();
-		if (!isRotating.value || sequence.value.length === 0) return;
-		const intervalMs = Math.max(MIN_INTERVAL_MINUTES, intervalMinutes.value) * 60 * 1000;
-		const now = Date.now();
-		const nextAt = (lastChangeAt.value ?? now) + intervalMs;
-		const delay = Math.max(0, nextAt - now);
-		timer = setTimeout(async () => {
-			try {
-				if (!isRotating.value || sequence.value.length === 0) return;
-				if (
-					rotationMode.value === 'random' &&
-					currentIndex.value === sequence.value.length - 1
-				) {
-					sequence.value = shuffle(sequence.value);
-					currentIndex.value = 0;
-				} else {
-					currentIndex.value = (currentIndex.value + 1) % sequence.value.length;
-				}
-				const nextPath = sequence.value[currentIndex.value];
-				await applyWallpaper(nextPath);
-				lastChangeAt.value = Date.now();
-				persistRotation();
-				// Обновляем prefs асинхронно, не блокируя основной поток
-				updateRotationPrefs({
-					intervalMinutes: Math.max(MIN_INTERVAL_MINUTES, intervalMinutes.value),
-					target: wallpaperTarget.value,
-					rotationIndex: currentIndex.value,
-					lastChangeAt: lastChangeAt.value ?? 0,
-					sequence: sequence.value
-				}).catch(() => {});
-			} finally {
-				scheduleNext();
-			}
-		}, delay);
+	function isLockCollection(id: string): boolean {
+		return lockCollectionId.value === id;
 	}
 
 	async function startCollection(id: string) {
-		clearTimer();
 		rotationStoppedWarning.value = null;
 		activeCollectionId.value = id;
 		isRotating.value = true;
-		currentIndex.value = 0;
+		let state: RotationState;
 		try {
-			sequence.value = await loadSequenceForCollection(id);
+			state = await rotationStart(id, rotationMode.value, intervalMinutes.value, schedule.value, noRepeat.value);
 		} catch (e) {
 			// Если не удалось загрузить последовательность, очищаем состояние
 			isRotating.value = false;
@@ -227,28 +313,27 @@ export const useAppStore = defineStore('app', () => {
 			persistRotation();
 			throw e;
 		}
-		if (sequence.value.length === 0) {
+		applyState(state);
+		if (!state.current) {
 			isRotating.value = false;
 			activeCollectionId.value = null;
 			persistRotation();
+			rotationStop().catch(() => {});
 			return;
 		}
+		const current = state.current;
+		const collectionId = state.collection_id;
 		// Откладываем установку обоев и запуск сервиса, чтобы избежать вылета
 		setTimeout(async () => {
 			try {
-				await applyWallpaper(sequence.value[currentIndex.value]);
-				lastChangeAt.value = Date.now();
+				await applyWallpaper(current, collectionId);
 				persistRotation();
-				scheduleNext();
 				// Запускаем фоновый сервис с дополнительной задержкой
 				setTimeout(async () => {
 					try {
 						await startWallpaperRotationService({
-							intervalMinutes: Math.max(MIN_INTERVAL_MINUTES, intervalMinutes.value),
-							target: wallpaperTarget.value,
-							rotationIndex: currentIndex.value,
-							lastChangeAt: lastChangeAt.value ?? Date.now(),
-							sequence: sequence.value
+							intervalMinutes: state.interval_minutes,
+							target: wallpaperTarget.value
 						});
 					} catch (e) {
 						console.error('Failed to start background service:', e);
@@ -262,11 +347,12 @@ export const useAppStore = defineStore('app', () => {
 	}
 
 	async function pauseRotation() {
-		clearTimer();
 		isRotating.value = false;
+		pinnedUntil.value = null;
 		// Очищаем activeCollectionId при паузе, чтобы UI правильно обновлялся
 		activeCollectionId.value = null;
 		persistRotation();
+		rotationStop().catch(() => {});
 		// Останавливаем фоновый сервис с задержкой и обработкой ошибок
 		setTimeout(async () => {
 			try {
@@ -281,7 +367,6 @@ export const useAppStore = defineStore('app', () => {
 	function resumeRotation() {
 		if (!activeCollectionId.value || sequence.value.length === 0) return;
 		isRotating.value = true;
-		scheduleNext();
 		persistRotation();
 	}
 
@@ -301,11 +386,21 @@ export const useAppStore = defineStore('app', () => {
 		// Если ротация уже активна, ничего не делаем
 		if (isRotating.value) return;
 		// Восстанавливаем только флаг isRotating для правильного отображения в UI
-		// НЕ загружаем последовательность и НЕ запускаем таймер/сервис при старте
-		// Это предотвращает вылет при открытии приложения
+		// НЕ запускаем сервис при старте — это предотвращает вылет при открытии приложения
 		isRotating.value = true;
 		persistRotation();
-		// Последовательность загрузится когда пользователь откроет коллекцию или когда таймер попытается сработать
+		// Позицию берём из движка; дальнейшие смены придут событиями wallpaper-changed
+		const state = await rotationState();
+		if (state) applyState(state);
+	}
+
+	/** Ротация блокировки продолжается по сохранённому движку; если его нет, слот считается остановленным. */
+	async function restoreLockRotationIfNeeded() {
+		if (typeof window === 'undefined' || !lockCollectionId.value) return;
+		if (!(await rotationState('lock'))) {
+			lockCollectionId.value = null;
+			persistLockRotation();
+		}
 	}
 
 	return {
@@ -313,14 +408,29 @@ export const useAppStore = defineStore('app', () => {
 		intervalMinutes,
 		wallpaperTarget: wallpaperTargetMode,
 		rotationMode: rotationModeSetting,
+		noRepeatWindow,
 		activeCollectionId,
 		isRotating,
 		rotationStoppedWarning,
 		setRotationStoppedWarning,
+		rotationError,
 		startCollection,
 		pauseRotation,
 		resumeRotation,
 		isActiveCollection,
-		restoreRotationIfNeeded
+		restoreRotationIfNeeded,
+		pinnedUntil,
+		nextWallpaper,
+		previousWallpaper,
+		pinWallpaper,
+		schedule,
+		setSchedule,
+		lockIntervalMinutes,
+		lockCollectionId,
+		isLockRotating,
+		startLockCollection,
+		stopLockRotation,
+		isLockCollection,
+		restoreLockRotationIfNeeded
 	};
 });
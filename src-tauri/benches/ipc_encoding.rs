@@ -0,0 +1,77 @@
+//! Во что обходится кодирование файла для IPC: JSON-массив чисел (как было у `Vec<u8>`
+//! в командах), base64 в JSON (части upload_chunk на Android) и сырые байты (`tauri::ipc::Response`,
+//! тело upload_chunk на ПК). Сравниваются только размер на проводе и время сериализации
+//! и разбора на стороне Rust — сам путь через IPC и WebView здесь не измеряется. У сырых байт
+//! шага кодирования нет: они уходят как есть.
+//!
+//! Запуск: `cargo bench --bench ipc_encoding`.
+
+use base64::Engine;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZES_MB: [usize; 3] = [1, 10, 25];
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const RUNS: u32 = 5;
+
+fn main() {
+  println!("{:>6}  {:<14} {:>12} {:>10} {:>10}", "MB", "encoding", "wire bytes", "encode", "decode");
+  for mb in SIZES_MB {
+    let data = sample(mb * 1024 * 1024);
+
+    let json = serde_json::to_vec(&data).unwrap();
+    report(
+      mb,
+      "json array",
+      json.len(),
+      Some(time(|| serde_json::to_vec(black_box(&data)).unwrap())),
+      Some(time(|| serde_json::from_slice::<Vec<u8>>(black_box(&json)).unwrap())),
+    );
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let chunks: Vec<String> = data.chunks(CHUNK_SIZE).map(|c| base64.encode(c)).collect();
+    report(
+      mb,
+      "base64 chunks",
+      chunks.iter().map(|c| c.len()).sum(),
+      Some(time(|| data.chunks(CHUNK_SIZE).map(|c| base64.encode(black_box(c))).collect::<Vec<_>>())),
+      Some(time(|| chunks.iter().map(|c| base64.decode(black_box(c)).unwrap()).collect::<Vec<_>>())),
+    );
+
+    report(mb, "raw bytes", data.len(), None, None);
+  }
+}
+
+/// Байты, похожие на сжатое изображение: все значения 0–255 встречаются равномерно.
+fn sample(len: usize) -> Vec<u8> {
+  let mut state = 0x2545_f491_4f6c_dd1d_u64;
+  (0..len)
+    .map(|_| {
+      state ^= state << 13;
+      state ^= state >> 7;
+      state ^= state << 17;
+      (state >> 24) as u8
+    })
+    .collect()
+}
+
+/// Лучшее время из `RUNS` запусков.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+  (0..RUNS)
+    .map(|_| {
+      let start = Instant::now();
+      black_box(f());
+      start.elapsed()
+    })
+    .min()
+    .unwrap()
+}
+
+/// None — у кодировки нет такого шага.
+fn report(mb: usize, encoding: &str, wire: usize, encode: Option<Duration>, decode: Option<Duration>) {
+  let ms = |d: Option<Duration>| match d {
+    Some(d) => format!("{:.1}ms", d.as_secs_f64() * 1000.0),
+    None => "-".to_string(),
+  };
+  println!("{:>6}  {:<14} {:>12} {:>10} {:>10}", mb, encoding, wire, ms(encode), ms(decode));
+}
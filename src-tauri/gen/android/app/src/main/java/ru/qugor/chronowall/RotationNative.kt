@@ -0,0 +1,34 @@
+package ru.qugor.chronowall
+
+import android.content.Context
+
+/**
+ * Мост к RotationEngine из Rust-библиотеки приложения. Сервис не считает порядок сам,
+ * а спрашивает у движка, пора ли менять обои и какие ставить следующими.
+ */
+object RotationNative {
+    init {
+        System.loadLibrary("app_lib")
+    }
+
+    /**
+     * Шаг слота [slot] ("home" / "lock") по файлу состояния [stateFile]: если пора и [advance],
+     * движок переходит к следующим обоям и сохраняет это в файл. Возвращает JSON с полями
+     * `running`, `due`, `current`, `target`, `collection_id`, `next_due_at`. [context] нужен
+     * один раз — перенести состояние из SharedPreferences прежних версий.
+     */
+    @JvmStatic
+    external fun tick(context: Context, stateFile: String, slot: String, nowMs: Long, advance: Boolean): String
+
+    /**
+     * Дописывает смену обоев сервисом в историю (`history/log.jsonl` внутри [baseDir]) —
+     * тот же журнал, что читает `get_wallpaper_history` в приложении. Если приложение открыто,
+     * оно получает событие `wallpaper-changed` для слота [slot] ("home" / "lock").
+     */
+    @JvmStatic
+    external fun recordHistory(baseDir: String, path: String, target: String, collectionId: String, atMs: Long, slot: String)
+
+    /** Сообщает открытому приложению (`rotation-error`), что сервис не смог сменить обои слота. */
+    @JvmStatic
+    external fun reportError(slot: String, message: String)
+}
@@ -14,6 +14,8 @@ import android.os.Environment
 import android.os.IBinder
 import androidx.core.app.ServiceCompat
 import androidx.core.app.NotificationCompat
+import android.util.Log
+import org.json.JSONObject
 import java.io.File
 
 /**
@@ -32,61 +34,105 @@ class WallpaperRotationService : Service() {
     }
 
     override fun onStartCommand(intent: Intent?, flags: Int, startId: Int): Int {
-
-        val prefs = getSharedPreferences(PREFS_NAME, Context.MODE_PRIVATE)
-        if (!prefs.getBoolean(KEY_RUNNING, false)) {
-            stopForegroundAndRemove()
-            return START_NOT_STICKY
-        }
-
-        val sequenceStr = prefs.getString(KEY_SEQUENCE, null) ?: run {
-            stopForegroundAndRemove()
-            return START_NOT_STICKY
-        }
-        val paths = sequenceStr.split(SEQUENCE_DELIMITER).filter { it.isNotBlank() }
-        if (paths.isEmpty()) {
-            stopForegroundAndRemove()
-            return START_NOT_STICKY
-        }
-
+        // Главный экран и экран блокировки ротируются независимо: у каждого слота свой будильник
+        val slot = intent?.getStringExtra(EXTRA_SLOT) ?: SLOT_HOME
         val scheduleOnly = intent?.getBooleanExtra(EXTRA_SCHEDULE_ONLY, false) == true
-        if (scheduleOnly) {
-            val intervalMinutes = prefs.getInt(KEY_INTERVAL_MINUTES, 15).coerceAtLeast(15)
-            scheduleNextAlarm(this, prefs, intervalMinutes)
-            stopForegroundAndRemove()
-            return START_NOT_STICKY
-        }
+        runSlot(slot, scheduleOnly)
+        stopForegroundAndRemove()
+        return START_NOT_STICKY
+    }
+
+    private fun runSlot(slot: String, scheduleOnly: Boolean) {
+        // Состояние ротации пишет только Rust; prefs остались от прежних версий и переносятся при первом tick
+        val stateFile = stateFile(this)
+        if (!isSlotRunning(stateFile, slot) && !hasLegacyPrefs(slot)) return
 
-        val intervalMinutes = prefs.getInt(KEY_INTERVAL_MINUTES, 15).coerceAtLeast(15)
-        val lastChangeAt = prefs.getLong(KEY_LAST_CHANGE_AT, 0L)
-        val nextChangeAt = lastChangeAt + intervalMinutes * 60_000L
+        // Пора ли менять и на что — решает RotationEngine (Rust), он же сохраняет новый шаг в файл
         val now = System.currentTimeMillis()
-        // Если приложение недавно обновило обои (JS таймер), только перепланируем будильник
-        if (now < nextChangeAt - 15_000) {
-            scheduleNextAlarm(this, prefs, intervalMinutes)
-            stopForegroundAndRemove()
-            return START_NOT_STICKY
+        val tick = try {
+            JSONObject(RotationNative.tick(this, stateFile.absolutePath, slot, now, !scheduleOnly))
+        } catch (e: Throwable) {
+            Log.e(TAG, "RotationEngine tick failed", e)
+            reportError(slot, "RotationEngine tick failed: ${e.message}")
+            scheduleNextAlarm(this, now + RETRY_AFTER_MS, slot)
+            return
         }
+        if (!tick.getBoolean("running")) return
 
-        val target = prefs.getString(KEY_TARGET, "both") ?: "both"
-        var rotationIndex = prefs.getInt(KEY_ROTATION_INDEX, 0)
-        rotationIndex = (rotationIndex + 1) % paths.size
-        val nextPath = paths[rotationIndex]
+        // Если обои недавно сменили из приложения или сработала граница окна расписания
+        // без смены коллекции, только перепланируем будильник
+        if (tick.getBoolean("due")) {
+            applyTick(slot, tick)
+        }
+        val nextDueAt = tick.getLong("next_due_at")
+        if (nextDueAt > 0) scheduleNextAlarm(this, nextDueAt, slot)
+    }
 
+    private fun applyTick(slot: String, tick: JSONObject) {
+        val target = tick.getString("target")
+        val nextPath = tick.getString("current")
         val pictureDir = getPictureDir()
-        if (pictureDir != null) {
-            val fullPath = File(pictureDir, nextPath).absolutePath
+        if (pictureDir == null) {
+            reportError(slot, "Pictures directory is not available")
+            return
+        }
+        val fullPath = File(pictureDir, nextPath).absolutePath
+        val applied = try {
             setWallpaperFromPath(fullPath, target)
+        } catch (e: Exception) {
+            Log.e(TAG, "Failed to set wallpaper", e)
+            false
         }
+        if (!applied) {
+            reportError(slot, "Failed to set wallpaper: $nextPath")
+            return
+        }
+        try {
+            RotationNative.recordHistory(
+                pictureDir.absolutePath,
+                nextPath,
+                target,
+                tick.getString("collection_id"),
+                System.currentTimeMillis(),
+                slot
+            )
+        } catch (e: Throwable) {
+            Log.e(TAG, "Failed to record wallpaper history", e)
+        }
+    }
+
+    /**
+     * Идёт ли слот по файлу состояния. Файл более новой версии, чем [STATE_VERSION], не разбираем —
+     * его записала более новая сборка приложения.
+     */
+    private fun isSlotRunning(stateFile: File, slot: String): Boolean {
+        if (!stateFile.isFile) return false
+        return try {
+            val state = JSONObject(stateFile.readText())
+            if (state.optInt("version", 1) > STATE_VERSION) {
+                Log.w(TAG, "Unsupported rotation state version ${state.optInt("version")}")
+                false
+            } else if (slot == SLOT_LOCK) {
+                state.optJSONObject("lock")?.optBoolean("running") == true
+            } else {
+                state.optBoolean("running")
+            }
+        } catch (e: Exception) {
+            Log.e(TAG, "Unreadable rotation state", e)
+            false
+        }
+    }
 
-        prefs.edit()
-            .putInt(KEY_ROTATION_INDEX, rotationIndex)
-            .putLong(KEY_LAST_CHANGE_AT, System.currentTimeMillis())
-            .apply()
+    /** Ротация слота запущена прежней версией приложения и ещё не перенесена в файл состояния. */
+    private fun hasLegacyPrefs(slot: String): Boolean =
+        getSharedPreferences(legacyPrefsName(slot), Context.MODE_PRIVATE).getBoolean("running", false)
 
-        scheduleNextAlarm(this, prefs, intervalMinutes)
-        stopForegroundAndRemove()
-        return START_NOT_STICKY
+    private fun reportError(slot: String, message: String) {
+        try {
+            RotationNative.reportError(slot, message)
+        } catch (e: Throwable) {
+            Log.e(TAG, "Failed to report rotation error", e)
+        }
     }
 
     private fun stopForegroundAndRemove() {
@@ -103,8 +149,9 @@ class WallpaperRotationService : Service() {
         }
     }
 
-    private fun setWallpaperFromPath(path: String, target: String) {
-        val bitmap = BitmapFactory.decodeFile(path) ?: return
+    /** false — файл не удалось декодировать, обои не менялись. */
+    private fun setWallpaperFromPath(path: String, target: String): Boolean {
+        val bitmap = BitmapFactory.decodeFile(path) ?: return false
         val wm = WallpaperManager.getInstance(this)
         val which = target.lowercase()
         if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.N) {
@@ -119,6 +166,7 @@ class WallpaperRotationService : Service() {
         } else {
             wm.setBitmap(bitmap)
         }
+        return true
     }
 
     private fun createNotificationChannel() {
@@ -141,27 +189,37 @@ class WallpaperRotationService : Service() {
         .build()
 
     companion object {
-        private const val PREFS_NAME = "chrono_wall_rotation"
-        const val KEY_RUNNING = "running"
-        const val KEY_INTERVAL_MINUTES = "interval_minutes"
-        const val KEY_TARGET = "target"
-        const val KEY_ROTATION_INDEX = "rotation_index"
-        const val KEY_LAST_CHANGE_AT = "last_change_at"
-        const val KEY_SEQUENCE = "sequence"
-        const val SEQUENCE_DELIMITER = "\u0000"
+        /** Файл состояния ротации в dataDir (`rotation_loop::STATE_FILE_NAME` в Rust). */
+        private const val STATE_FILE_NAME = "rotation.json"
+        /** Старшая версия формата файла состояния, которую понимает сервис (`STATE_VERSION` в Rust). */
+        private const val STATE_VERSION = 2
+        private const val LEGACY_PREFS_NAME = "chrono_wall_rotation"
+        private const val LEGACY_PREFS_NAME_LOCK = "chrono_wall_rotation_lock"
+        /** Если шаг не удался, сервис пробует снова через это время. */
+        private const val RETRY_AFTER_MS = 15 * 60_000L
         const val EXTRA_SCHEDULE_ONLY = "schedule_only"
+        const val EXTRA_SLOT = "slot"
+        const val SLOT_HOME = "home"
+        const val SLOT_LOCK = "lock"
+        private const val TAG = "WallpaperRotation"
         private const val CHANNEL_ID = "wallpaper_rotation"
         private const val NOTIFICATION_ID = 1
         private const val REQUEST_CODE_NEXT = 2
+        private const val REQUEST_CODE_NEXT_LOCK = 3
+
+        fun legacyPrefsName(slot: String) = if (slot == SLOT_LOCK) LEGACY_PREFS_NAME_LOCK else LEGACY_PREFS_NAME
+
+        /** Тот же файл, что Tauri открывает в app_data_dir: на Android это dataDir приложения. */
+        fun stateFile(context: Context) = File(context.applicationInfo.dataDir, STATE_FILE_NAME)
 
-        fun scheduleNextAlarm(context: Context, prefs: android.content.SharedPreferences, intervalMinutes: Int) {
-            val lastChange = prefs.getLong(KEY_LAST_CHANGE_AT, 0L)
-            val nextAt = lastChange + intervalMinutes * 60_000L
+        /** Будильник слота на [nextAt] (срок из RotationEngine с учётом расписания), не раньше чем через минуту. */
+        fun scheduleNextAlarm(context: Context, nextAt: Long, slot: String = SLOT_HOME) {
             val alarmMgr = context.getSystemService(Context.ALARM_SERVICE) as AlarmManager
-            val intent = Intent(context, WallpaperRotationService::class.java)
+            val intent = Intent(context, WallpaperRotationService::class.java).putExtra(EXTRA_SLOT, slot)
+            // У слотов разные request code — будильник одного не заменяет будильник другого
             val pending = PendingIntent.getService(
                 context,
-                REQUEST_CODE_NEXT,
+                if (slot == SLOT_LOCK) REQUEST_CODE_NEXT_LOCK else REQUEST_CODE_NEXT,
                 intent,
                 PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE
             )
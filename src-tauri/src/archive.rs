@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::atomic_file;
+use crate::blobs::BlobStore;
+use crate::collection::{self, CollectionItem, CollectionMeta};
+use crate::paths;
+
+/// Манифест в корне архива. Изображения лежат в images/{id элемента}_{имя файла}.
+const MANIFEST_NAME: &str = "manifest.json";
+const IMAGES_DIR: &str = "images";
+const FORMAT: &str = "chrono-wall-collection";
+/// Версия формата архива. Импорт отклоняет архивы новее поддерживаемой.
+pub const FORMAT_VERSION: u32 = 1;
+/// Манифест — небольшой JSON; ограничение защищает от подсунутого огромного файла.
+const MAX_MANIFEST_SIZE: u64 = 16 * 1024 * 1024;
+/// Предел для одного изображения: на нём остановится чтение zip-бомбы, а не займёт всю память.
+const MAX_IMAGE_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+  format: String,
+  version: u32,
+  /// Время экспорта, мс с эпохи Unix.
+  exported_at: u64,
+  /// `_meta.json` коллекции без ссылок на блобы: в другой установке их нет.
+  collection: CollectionMeta,
+  files: Vec<ManifestFile>,
+}
+
+/// Файл изображения элемента внутри архива.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestFile {
+  item_id: u64,
+  entry: String,
+  sha256: String,
+}
+
+/// Упаковать коллекцию в ZIP по пути `dest` (пишется во временный файл рядом и переименовывается).
+pub fn export(base: &Path, collection_id: &str, dest: &Path) -> Result<(), String> {
+  let dir = paths::collection_dir(base, collection_id)?;
+  let meta = collection::read_meta(&dir)?;
+  let store = BlobStore::new(base);
+
+  let tmp = dest.with_extension("zip.tmp");
+  let result = (|| {
+    let out = File::create(&tmp).map_err(|e| format!("create {:?}: {}", tmp, e))?;
+    let mut zip = ZipWriter::new(BufWriter::new(out));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let mut files = Vec::new();
+    for item in &meta.items {
+      let source = match &item.blob {
+        Some(blob) => store.path(blob)?,
+        None => dir.join(&item.file),
+      };
+      let data = fs::read(&source).map_err(|e| format!("read {:?}: {}", source, e))?;
+      let entry = format!("{}/{}_{}", IMAGES_DIR, item.id, item.file);
+      // Изображения уже сжаты — кладём без компрессии
+      zip.start_file(entry.as_str(), stored).map_err(|e| e.to_string())?;
+      zip.write_all(&data).map_err(|e| e.to_string())?;
+      files.push(ManifestFile {
+        item_id: item.id,
+        entry,
+        sha256: hex(&Sha256::digest(&data)),
+      });
+    }
+
+    let mut collection = meta.clone();
+    for item in &mut collection.items {
+      item.blob = None;
+    }
+    let manifest = Manifest {
+      format: FORMAT.to_string(),
+      version: FORMAT_VERSION,
+      exported_at: collection::now_millis(),
+      collection,
+      files,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip
+      .start_file(MANIFEST_NAME, SimpleFileOptions::default())
+      .map_err(|e| e.to_string())?;
+    zip.write_all(&json).map_err(|e| e.to_string())?;
+
+    let out = zip.finish().map_err(|e| e.to_string())?;
+    let file = out.into_inner().map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    fs::rename(&tmp, dest).map_err(|e| format!("rename {:?} -> {:?}: {}", tmp, dest, e))
+  })();
+  if result.is_err() {
+    let _ = fs::remove_file(&tmp);
+  }
+  result
+}
+
+/// Импортировать коллекцию из ZIP. id и название при совпадении с `existing` получают суффикс.
+/// Изображения попадают в хранилище блобов (у зашифрованной коллекции — в её папку);
+/// при ошибке ничего не остаётся.
+pub fn import(base: &Path, src: &Path, existing: &[CollectionMeta]) -> Result<CollectionMeta, String> {
+  let file = File::open(src).map_err(|e| format!("open {:?}: {}", src, e))?;
+  let mut zip = ZipArchive::new(BufReader::new(file)).map_err(|e| format!("read {:?}: {}", src, e))?;
+
+  // Небезопасные имена (../, абсолютные пути) — признак подделанного архива: отклоняем целиком
+  for i in 0..zip.len() {
+    let entry = zip.by_index_raw(i).map_err(|e| e.to_string())?;
+    if !is_safe_entry_name(entry.name()) || entry.enclosed_name().is_none() {
+      return Err(format!("Unsafe archive entry '{}'", entry.name()));
+    }
+  }
+
+  let manifest = read_manifest(&mut zip)?;
+  let mut meta = manifest.collection;
+  let items: Vec<CollectionItem> = std::mem::take(&mut meta.items);
+
+  let taken_ids: Vec<&str> = existing.iter().map(|c| c.id.as_str()).collect();
+  let taken_names: Vec<String> = existing.iter().map(|c| c.name.clone()).collect();
+  let id_base = if paths::validate_segment(&meta.id).is_ok() {
+    meta.id.clone()
+  } else {
+    collection::sanitize_id(&meta.name)
+  };
+  let collections = base.join("collections");
+  let mut final_id = id_base.clone();
+  let mut counter = 0;
+  while taken_ids.contains(&final_id.as_str()) || collections.join(&final_id).exists() {
+    counter += 1;
+    final_id = format!("{}_{}", id_base, counter);
+  }
+  meta.id = final_id;
+  meta.name = collection::unique_name(meta.name.trim(), &taken_names);
+
+  let store = BlobStore::new(base);
+  let mut retained = Vec::new();
+  let dir = collections.join(&meta.id);
+  let encrypted = meta.encryption.is_some();
+  let result = (|| {
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    for mut item in items {
+      let file = manifest
+        .files
+        .iter()
+        .find(|f| f.item_id == item.id)
+        .ok_or_else(|| format!("Item {} has no file in archive", item.id))?;
+      let data = read_entry(&mut zip, &file.entry, MAX_IMAGE_SIZE)?;
+      if hex(&Sha256::digest(&data)) != file.sha256 {
+        return Err(format!("Checksum mismatch for '{}'", file.entry));
+      }
+      if encrypted {
+        // Зашифрованные файлы остаются в папке коллекции, как были
+        collection::validate_item_file_name(&item.file)?;
+        atomic_file::write(&dir.join(&item.file), &data)?;
+      } else {
+        let blob = store.put_bytes(&data, &item.file)?;
+        store.retain(&blob)?;
+        retained.push(blob.clone());
+        item.blob = Some(blob);
+      }
+      meta.items.push(item);
+    }
+    Ok(collection::write_meta(&dir, &meta)?)
+  })();
+
+  if let Err(e) = result {
+    let _ = fs::remove_dir_all(&dir);
+    for blob in retained {
+      let _ = store.release(&blob);
+    }
+    return Err(e);
+  }
+  Ok(meta)
+}
+
+fn read_manifest<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>) -> Result<Manifest, String> {
+  let entry = zip
+    .by_name(MANIFEST_NAME)
+    .map_err(|_| format!("Archive has no {}", MANIFEST_NAME))?;
+  let mut json = Vec::new();
+  entry
+    .take(MAX_MANIFEST_SIZE)
+    .read_to_end(&mut json)
+    .map_err(|e| e.to_string())?;
+  let manifest: Manifest =
+    serde_json::from_slice(&json).map_err(|e| format!("parse {}: {}", MANIFEST_NAME, e))?;
+  if manifest.format != FORMAT {
+    return Err(format!("Not a collection archive (format '{}')", manifest.format));
+  }
+  if manifest.version == 0 || manifest.version > FORMAT_VERSION {
+    return Err(format!(
+      "Unsupported archive version {} (supported up to {})",
+      manifest.version, FORMAT_VERSION
+    ));
+  }
+  manifest.collection.validate()?;
+  Ok(manifest)
+}
+
+/// Прочитать запись не больше `limit` байт. Память под данные растёт по мере чтения:
+/// размеру из заголовка записи доверять нельзя.
+fn read_entry<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>, name: &str, limit: u64) -> Result<Vec<u8>, String> {
+  if !is_safe_entry_name(name) {
+    return Err(format!("Unsafe archive entry '{}'", name));
+  }
+  let entry = zip
+    .by_name(name)
+    .map_err(|_| format!("Archive entry '{}' not found", name))?;
+  let mut data = Vec::new();
+  entry
+    .take(limit + 1)
+    .read_to_end(&mut data)
+    .map_err(|e| format!("read '{}': {}", name, e))?;
+  if data.len() as u64 > limit {
+    return Err(format!("Archive entry '{}' is larger than {} bytes", name, limit));
+  }
+  Ok(data)
+}
+
+/// Относительный путь только из обычных компонентов: без `..`, корня, префиксов диска и `\`.
+fn is_safe_entry_name(name: &str) -> bool {
+  !name.is_empty()
+    && !name.contains('\\')
+    && Path::new(name)
+      .components()
+      .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Коллекция c1 с элементом в хранилище блобов и файлом прежнего формата в папке коллекции.
+  fn base_with_collection() -> (tempfile::TempDir, std::path::PathBuf) {
+    let (tmp, base) = collection::test_base("c1", None, &["a.webp"]);
+    let dir = base.join("collections/c1");
+    fs::write(dir.join("legacy.jpg"), b"legacy image").unwrap();
+    collection::update_meta(&dir, |meta| {
+      meta.push_item(serde_json::from_value(serde_json::json!({ "file": "legacy.jpg" })).unwrap())
+    })
+    .unwrap();
+    (tmp, base)
+  }
+
+  #[test]
+  fn export_then_import_resolves_id_and_name_clash() {
+    let (tmp, base) = base_with_collection();
+    let zip_path = tmp.path().join("c1.zip");
+    export(&base, "c1", &zip_path).unwrap();
+
+    let existing = vec![collection::read_meta(&base.join("collections/c1")).unwrap()];
+    let imported = import(&base, &zip_path, &existing).unwrap();
+    assert_eq!(imported.id, "c1_1");
+    assert_eq!(imported.name, "One (2)");
+    assert_eq!(imported.items.len(), 2);
+    assert!(imported.items.iter().all(|it| it.blob.is_some()));
+
+    let store = BlobStore::new(&base);
+    let legacy = imported.items.iter().find(|it| it.file == "legacy.jpg").unwrap();
+    let data = fs::read(store.path(legacy.blob.as_ref().unwrap()).unwrap()).unwrap();
+    assert_eq!(data, b"legacy image");
+    assert_eq!(collection::read_meta(&base.join("collections/c1_1")).unwrap(), imported);
+  }
+
+  #[test]
+  fn import_rejects_zip_slip_entries() {
+    let (tmp, base) = base_with_collection();
+    for name in ["../evil.txt", "/abs/evil.txt", "images/../../evil.txt", "a\\..\\evil.txt"] {
+      let zip_path = tmp.path().join("evil.zip");
+      let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+      zip.start_file(name, SimpleFileOptions::default()).unwrap();
+      zip.write_all(b"x").unwrap();
+      zip.finish().unwrap();
+
+      let err = import(&base, &zip_path, &[]).unwrap_err();
+      assert!(err.contains("Unsafe"), "{}: {}", name, err);
+    }
+    assert!(!tmp.path().join("evil.txt").exists());
+  }
+
+  #[test]
+  fn import_rejects_newer_version_and_bad_checksum() {
+    let (tmp, base) = base_with_collection();
+    let write_manifest = |path: &Path, manifest: serde_json::Value, image: &[u8]| {
+      let mut zip = ZipWriter::new(File::create(path).unwrap());
+      zip.start_file("images/1_a.webp", SimpleFileOptions::default()).unwrap();
+      zip.write_all(image).unwrap();
+      zip.start_file(MANIFEST_NAME, SimpleFileOptions::default()).unwrap();
+      zip.write_all(manifest.to_string().as_bytes()).unwrap();
+      zip.finish().unwrap();
+    };
+    let manifest = |version: u32| {
+      serde_json::json!({
+        "format": FORMAT,
+        "version": version,
+        "exported_at": 0,
+        "collection": { "id": "x", "name": "X", "items": [{ "id": 1, "order": 1, "file": "a.webp" }] },
+        "files": [{ "item_id": 1, "entry": "images/1_a.webp", "sha256": hex(&Sha256::digest(b"img")) }]
+      })
+    };
+    let zip_path = tmp.path().join("x.zip");
+
+    write_manifest(&zip_path, manifest(FORMAT_VERSION + 1), b"img");
+    assert!(import(&base, &zip_path, &[]).unwrap_err().contains("version"));
+
+    write_manifest(&zip_path, manifest(FORMAT_VERSION), b"tampered");
+    assert!(import(&base, &zip_path, &[]).unwrap_err().contains("Checksum"));
+    assert!(!base.join("collections/x").exists());
+
+    write_manifest(&zip_path, manifest(FORMAT_VERSION), b"img");
+    assert_eq!(import(&base, &zip_path, &[]).unwrap().id, "x");
+  }
+
+  #[test]
+  fn oversized_entries_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let zip_path = tmp.path().join("big.zip");
+    let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+    zip.start_file("images/1_a.webp", SimpleFileOptions::default()).unwrap();
+    zip.write_all(&[0u8; 4096]).unwrap();
+    zip.finish().unwrap();
+
+    let mut zip = ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+    assert_eq!(read_entry(&mut zip, "images/1_a.webp", 4096).unwrap().len(), 4096);
+    let err = read_entry(&mut zip, "images/1_a.webp", 1024).unwrap_err();
+    assert!(err.contains("larger"), "{}", err);
+  }
+}
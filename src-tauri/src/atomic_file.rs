@@ -0,0 +1,93 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Атомарная запись: данные пишутся во временный файл рядом с целевым, сбрасываются на диск (fsync)
+/// и переименовываются поверх целевого. При сбое на любом шаге старый файл остаётся нетронутым.
+pub fn write(path: &Path, data: &[u8]) -> Result<(), String> {
+  write_with(path, |file| file.write_all(data).map_err(|e| e.to_string()))
+}
+
+/// То же, но содержимое пишет `fill` прямо во временный файл — для данных, которые не нужно
+/// целиком держать в памяти (потоковое шифрование).
+pub fn write_with(path: &Path, fill: impl FnOnce(&mut File) -> Result<(), String>) -> Result<(), String> {
+  let dir = path
+    .parent()
+    .ok_or_else(|| format!("No parent directory for {:?}", path))?;
+  let tmp = tmp_path(path)?;
+
+  let result = (|| {
+    let mut file = OpenOptions::new()
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(&tmp)
+      .map_err(|e| format!("create {:?}: {}", tmp, e))?;
+    fill(&mut file).map_err(|e| format!("write {:?}: {}", tmp, e))?;
+    file.sync_all().map_err(|e| format!("fsync {:?}: {}", tmp, e))?;
+    drop(file);
+    fs::rename(&tmp, path).map_err(|e| format!("rename {:?} -> {:?}: {}", tmp, path, e))
+  })();
+
+  if result.is_err() {
+    let _ = fs::remove_file(&tmp);
+    return result;
+  }
+  sync_dir(dir);
+  Ok(())
+}
+
+/// Временный файл в той же папке (rename атомарен только в пределах одной ФС).
+fn tmp_path(path: &Path) -> Result<PathBuf, String> {
+  let name = path
+    .file_name()
+    .and_then(|n| n.to_str())
+    .ok_or_else(|| format!("Invalid file name {:?}", path))?;
+  Ok(path.with_file_name(format!("{}.tmp", name)))
+}
+
+/// Сбросить запись каталога, чтобы rename пережил сбой питания. На Windows каталог не открыть — пропускаем.
+fn sync_dir(dir: &Path) {
+  #[cfg(unix)]
+  {
+    if let Ok(d) = fs::File::open(dir) {
+      let _ = d.sync_all();
+    }
+  }
+  #[cfg(not(unix))]
+  {
+    let _ = dir;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn failed_write_keeps_old_contents() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("state.json");
+    write(&path, b"old").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"old");
+    assert!(!tmp_path(&path).unwrap().exists());
+
+    // Временный файл не создать: на его месте папка
+    fs::create_dir(tmp_path(&path).unwrap()).unwrap();
+    assert!(write(&path, b"new").is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"old");
+
+    fs::remove_dir(tmp_path(&path).unwrap()).unwrap();
+    write(&path, b"new").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+
+    // Сбой посреди потоковой записи: начало не попадает в файл, временный удаляется
+    let result = write_with(&path, |file| {
+      file.write_all(b"half").map_err(|e| e.to_string())?;
+      Err("source closed".to_string())
+    });
+    assert!(result.is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"new");
+    assert!(!tmp_path(&path).unwrap().exists());
+  }
+}
@@ -0,0 +1,252 @@
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_file;
+use crate::error::AppError;
+
+/// Папка хранилища внутри base: blobs/{первые 2 символа хеша}/{хеш}.{ext}
+pub const BLOBS_DIR: &str = "blobs";
+/// Счётчики ссылок: id блоба → сколько элементов коллекций на него ссылаются.
+const REFS_FILE_NAME: &str = "_refs.json";
+
+/// Все изменения счётчиков и файлов хранилища идут под одной блокировкой.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Контентно-адресуемое хранилище изображений с подсчётом ссылок.
+/// Одинаковые файлы в разных коллекциях хранятся один раз.
+pub struct BlobStore {
+  root: PathBuf,
+}
+
+impl BlobStore {
+  pub fn new(base: &Path) -> Self {
+    Self {
+      root: base.join(BLOBS_DIR),
+    }
+  }
+
+  /// Сохранить байты. Возвращает id блоба; если такой уже есть — ничего не пишет.
+  pub fn put_bytes(&self, data: &[u8], file_name: &str) -> Result<String, String> {
+    let id = blob_id(&hex_digest(Sha256::digest(data)), file_name);
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = self.path(&id)?;
+    if !dest.is_file() {
+      fs::create_dir_all(dest.parent().unwrap()).map_err(|e| e.to_string())?;
+      atomic_file::write(&dest, data)?;
+    }
+    Ok(id)
+  }
+
+  /// Сохранить файл с диска. Сначала только хешируем: если блоб уже есть, копирования не будет.
+  pub fn put_file(&self, source: &Path, file_name: &str) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    let mut src = File::open(source).map_err(|e| format!("open {:?}: {}", source, e))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+      let n = src.read(&mut buf).map_err(|e| format!("read {:?}: {}", source, e))?;
+      if n == 0 {
+        break;
+      }
+      hasher.update(&buf[..n]);
+    }
+    let id = blob_id(&hex_digest(hasher.finalize()), file_name);
+
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dest = self.path(&id)?;
+    if dest.is_file() {
+      return Ok(id);
+    }
+    let dir = dest.parent().unwrap();
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!("{}.tmp", id));
+    let copied = (|| {
+      let mut out = File::create(&tmp).map_err(|e| format!("create {:?}: {}", tmp, e))?;
+      let mut src = File::open(source).map_err(|e| format!("open {:?}: {}", source, e))?;
+      std::io::copy(&mut src, &mut out).map_err(|e| format!("copy {:?}: {}", source, e))?;
+      out.flush().map_err(|e| e.to_string())?;
+      out.sync_all().map_err(|e| e.to_string())?;
+      fs::rename(&tmp, &dest).map_err(|e| format!("rename {:?}: {}", tmp, e))
+    })();
+    if copied.is_err() {
+      let _ = fs::remove_file(&tmp);
+    }
+    copied.map(|_| id)
+  }
+
+  /// Полный путь к блобу.
+  pub fn path(&self, id: &str) -> Result<PathBuf, String> {
+    validate_blob_id(id)?;
+    Ok(self.root.join(&id[..2]).join(id))
+  }
+
+  /// Добавить ссылку на блоб (элемент коллекции начал его использовать).
+  pub fn retain(&self, id: &str) -> Result<u64, AppError> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if !self.path(id)?.is_file() {
+      return Err(AppError::not_found("blob", id));
+    }
+    let mut refs = self.read_refs()?;
+    let count = refs.entry(id.to_string()).or_insert(0);
+    *count += 1;
+    let count = *count;
+    self.write_refs(&refs)?;
+    Ok(count)
+  }
+
+  /// Убрать ссылку. Когда ссылок не осталось, файл блоба удаляется.
+  pub fn release(&self, id: &str) -> Result<u64, String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = self.path(id)?;
+    let mut refs = self.read_refs()?;
+    let count = refs.get(id).copied().unwrap_or(0).saturating_sub(1);
+    if count == 0 {
+      refs.remove(id);
+      if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("remove {:?}: {}", path, e))?;
+      }
+    } else {
+      refs.insert(id.to_string(), count);
+    }
+    self.write_refs(&refs)?;
+    Ok(count)
+  }
+
+  /// Пересчитать счётчики по фактическим ссылкам из всех коллекций и удалить блобы без ссылок
+  /// (например, сохранённые, но так и не добавленные в коллекцию из-за сбоя).
+  pub fn reconcile(&self, referenced: &HashMap<String, u64>) -> Result<(), String> {
+    let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if !self.root.exists() {
+      return Ok(());
+    }
+    for shard in fs::read_dir(&self.root).map_err(|e| e.to_string())? {
+      let shard = shard.map_err(|e| e.to_string())?.path();
+      // Служебные папки (`_thumbs`) — не шарды хранилища
+      let service = shard.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('_'));
+      if !shard.is_dir() || service {
+        continue;
+      }
+      for entry in fs::read_dir(&shard).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let orphan = validate_blob_id(name).is_err() || !referenced.contains_key(name);
+        if orphan && path.is_file() {
+          log::info!("blob store: removing unreferenced {:?}", path);
+          let _ = fs::remove_file(&path);
+        }
+      }
+    }
+    let refs: BTreeMap<String, u64> = referenced
+      .iter()
+      .filter(|(id, _)| self.path(id).map(|p| p.is_file()).unwrap_or(false))
+      .map(|(id, n)| (id.clone(), *n))
+      .collect();
+    self.write_refs(&refs)
+  }
+
+  fn read_refs(&self) -> Result<BTreeMap<String, u64>, String> {
+    let file = self.root.join(REFS_FILE_NAME);
+    if !file.exists() {
+      return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(&file).map_err(|e| format!("read {:?}: {}", file, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("parse {:?}: {}", file, e))
+  }
+
+  fn write_refs(&self, refs: &BTreeMap<String, u64>) -> Result<(), String> {
+    fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(refs).map_err(|e| e.to_string())?;
+    atomic_file::write(&self.root.join(REFS_FILE_NAME), json.as_bytes())
+  }
+}
+
+/// Относительный путь блоба от base (для фронтенда и сервиса обоев).
+pub fn relative_path(id: &str) -> String {
+  format!("{}/{}/{}", BLOBS_DIR, &id[..2.min(id.len())], id)
+}
+
+/// id блоба: 64 hex-символа SHA-256 и необязательное расширение (`.webp`), чтобы по имени был виден тип.
+pub fn validate_blob_id(id: &str) -> Result<(), String> {
+  let (hash, ext) = match id.split_once('.') {
+    Some((hash, ext)) => (hash, Some(ext)),
+    None => (id, None),
+  };
+  let hash_ok = hash.len() == 64 && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'));
+  let ext_ok = ext.map_or(true, |e| {
+    !e.is_empty() && e.len() <= 8 && e.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+  });
+  if hash_ok && ext_ok {
+    Ok(())
+  } else {
+    Err(format!("Invalid blob id '{}'", id))
+  }
+}
+
+fn blob_id(hash: &str, file_name: &str) -> String {
+  let ext = Path::new(file_name)
+    .extension()
+    .and_then(|e| e.to_str())
+    .map(|e| e.to_ascii_lowercase())
+    .filter(|e| e.len() <= 8 && e.chars().all(|c| c.is_ascii_alphanumeric()));
+  match ext {
+    Some(ext) => format!("{}.{}", hash, ext),
+    None => hash.to_string(),
+  }
+}
+
+fn hex_digest(bytes: impl AsRef<[u8]>) -> String {
+  bytes.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn same_bytes_share_one_blob_until_last_release() {
+    let tmp = tempfile::tempdir().unwrap();
+    let store = BlobStore::new(tmp.path());
+    let id = store.put_bytes(b"cat", "a.JPG").unwrap();
+    let source = tmp.path().join("copy.jpg");
+    fs::write(&source, b"cat").unwrap();
+    assert_eq!(store.put_file(&source, "copy.jpg").unwrap(), id);
+    assert!(id.ends_with(".jpg"));
+    let shard: Vec<_> = fs::read_dir(store.path(&id).unwrap().parent().unwrap()).unwrap().collect();
+    assert_eq!(shard.len(), 1);
+
+    assert_eq!(store.retain(&id).unwrap(), 1);
+    assert_eq!(store.retain(&id).unwrap(), 2);
+    assert_eq!(store.read_refs().unwrap()[&id], 2);
+    assert_eq!(store.release(&id).unwrap(), 1);
+    assert!(store.path(&id).unwrap().is_file());
+    assert_eq!(store.release(&id).unwrap(), 0);
+    assert!(!store.path(&id).unwrap().exists());
+    assert!(store.read_refs().unwrap().is_empty());
+    assert_eq!(store.retain(&id), Err(AppError::not_found("blob", id.as_str())));
+  }
+
+  #[test]
+  fn reconcile_drops_orphans_and_rewrites_refs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let store = BlobStore::new(tmp.path());
+    let kept = store.put_bytes(b"kept", "k.png").unwrap();
+    let orphan = store.put_bytes(b"orphan", "o.png").unwrap();
+    store.retain(&kept).unwrap();
+    store.retain(&orphan).unwrap();
+    let thumbs = store.root.join("_thumbs");
+    fs::create_dir_all(&thumbs).unwrap();
+    fs::write(thumbs.join("t.webp"), b"thumb").unwrap();
+    let missing = blob_id(&"0".repeat(64), "m.png");
+
+    let referenced = HashMap::from([(kept.clone(), 3), (missing.clone(), 1)]);
+    store.reconcile(&referenced).unwrap();
+    assert!(store.path(&kept).unwrap().is_file());
+    assert!(!store.path(&orphan).unwrap().exists());
+    assert!(thumbs.join("t.webp").is_file());
+    // Ссылка на отсутствующий файл в счётчики не попадает
+    assert_eq!(store.read_refs().unwrap(), BTreeMap::from([(kept, 3)]));
+  }
+}
@@ -0,0 +1,548 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::atomic_file;
+use crate::blobs;
+use crate::crypto::EncryptionInfo;
+use crate::error::AppError;
+
+/// Имя файла метаданных внутри папки коллекции.
+pub const META_FILE_NAME: &str = "_meta.json";
+/// Последняя успешно записанная копия метаданных — источник восстановления.
+const META_BACKUP_NAME: &str = "_meta.json.bak";
+/// Сюда откладывается повреждённый файл перед восстановлением (для разбора).
+const META_CORRUPT_NAME: &str = "_meta.json.corrupt";
+
+/// Размер в пикселях (экран устройства или исходное изображение).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Size {
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Прямоугольник обрезки в координатах исходного изображения.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+/// Элемент коллекции — одно изображение из `items` в `_meta.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionItem {
+  pub id: u64,
+  pub order: u64,
+  /// Имя файла: внутри папки коллекции или, если задан `blob`, исходное имя для показа.
+  pub file: String,
+  /// id блоба в общем хранилище (blobs/). Старые элементы хранят файл прямо в папке коллекции.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub blob: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub screen: Option<Size>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub image: Option<Size>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub crop: Option<CropRect>,
+  /// Файл уже обрезан по `crop` (новый формат); иначе crop применяется при показе.
+  #[serde(default, rename = "savedAsCrop")]
+  pub saved_as_crop: bool,
+  /// Время добавления, мс с эпохи Unix.
+  #[serde(default)]
+  pub created_at: u64,
+}
+
+/// Данные нового элемента от фронтенда: id и order назначает Rust.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewCollectionItem {
+  pub file: String,
+  #[serde(default)]
+  pub blob: Option<String>,
+  #[serde(default)]
+  pub screen: Option<Size>,
+  #[serde(default)]
+  pub image: Option<Size>,
+  #[serde(default)]
+  pub crop: Option<CropRect>,
+  #[serde(default, rename = "savedAsCrop")]
+  pub saved_as_crop: bool,
+  #[serde(default)]
+  pub created_at: Option<u64>,
+}
+
+/// Метаданные коллекции (`_meta.json`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionMeta {
+  pub id: String,
+  pub name: String,
+  /// Время создания коллекции, секунды с эпохи Unix.
+  #[serde(default)]
+  pub created_at: u64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  /// id элемента, который показывается обложкой; по умолчанию — первый по order.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cover_item_id: Option<u64>,
+  /// Акцентный цвет в формате `#RRGGBB`.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub accent_color: Option<String>,
+  /// Параметры шифрования. Файлы зашифрованной коллекции лежат в её папке, не в общем хранилище.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub encryption: Option<EncryptionInfo>,
+  #[serde(default)]
+  pub items: Vec<CollectionItem>,
+}
+
+/// Редактируемые поля коллекции (кроме имени). `null` очищает поле.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CollectionDetails {
+  #[serde(default)]
+  pub description: Option<String>,
+  #[serde(default)]
+  pub cover_item_id: Option<u64>,
+  #[serde(default)]
+  pub accent_color: Option<String>,
+}
+
+impl CollectionMeta {
+  pub fn new(id: String, name: String, created_at: u64) -> Self {
+    Self {
+      id,
+      name,
+      created_at,
+      description: None,
+      cover_item_id: None,
+      accent_color: None,
+      encryption: None,
+      items: Vec::new(),
+    }
+  }
+
+  /// Проверка согласованности метаданных: имя, уникальность id, имена файлов, непустые размеры.
+  pub fn validate(&self) -> Result<(), AppError> {
+    if self.id.trim().is_empty() {
+      return Err("Collection id is empty".to_string().into());
+    }
+    if self.name.trim().is_empty() {
+      return Err(format!("Collection '{}' has empty name", self.id).into());
+    }
+    if let Some(color) = &self.accent_color {
+      validate_accent_color(color)?;
+    }
+    if let Some(encryption) = &self.encryption {
+      encryption.validate()?;
+      if self.items.iter().any(|it| it.blob.is_some()) {
+        return Err(format!("Encrypted collection '{}' cannot reference shared blobs", self.id).into());
+      }
+    }
+    if let Some(cover) = self.cover_item_id {
+      if !self.items.iter().any(|it| it.id == cover) {
+        return Err(AppError::not_found("item", cover.to_string()));
+      }
+    }
+    let mut ids = HashSet::new();
+    for item in &self.items {
+      if !ids.insert(item.id) {
+        return Err(format!("Duplicate item id {} in collection '{}'", item.id, self.id).into());
+      }
+      item.validate()?;
+    }
+    Ok(())
+  }
+
+  /// Переименовать коллекцию. id (имя папки) не меняется, поэтому активная ротация продолжает работать.
+  pub fn rename(&mut self, name: &str) -> Result<(), AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+      return Err("Collection name is empty".to_string().into());
+    }
+    self.name = name.to_string();
+    Ok(())
+  }
+
+  /// Применить редактируемые поля. Пустые строки очищают описание и цвет.
+  pub fn apply_details(&mut self, details: CollectionDetails) -> Result<(), AppError> {
+    self.description = details
+      .description
+      .map(|d| d.trim().to_string())
+      .filter(|d| !d.is_empty());
+    self.accent_color = details
+      .accent_color
+      .map(|c| c.trim().to_ascii_lowercase())
+      .filter(|c| !c.is_empty());
+    self.cover_item_id = details.cover_item_id;
+    self.validate()
+  }
+
+  /// Сколько раз каждый блоб используется элементами этой коллекции.
+  pub fn blob_refs(&self) -> HashMap<String, u64> {
+    let mut refs = HashMap::new();
+    for blob in self.items.iter().filter_map(|it| it.blob.as_ref()) {
+      *refs.entry(blob.clone()).or_insert(0) += 1;
+    }
+    refs
+  }
+
+  /// Следующий свободный id элемента.
+  pub fn next_item_id(&self) -> u64 {
+    self.items.iter().map(|it| it.id).max().unwrap_or(0) + 1
+  }
+
+  /// Добавить элемент в конец коллекции, назначив id и order.
+  pub fn push_item(&mut self, new_item: NewCollectionItem) -> Result<CollectionItem, AppError> {
+    let item = CollectionItem {
+      id: self.next_item_id(),
+      order: self.items.len() as u64 + 1,
+      file: new_item.file,
+      blob: new_item.blob,
+      screen: new_item.screen,
+      image: new_item.image,
+      crop: new_item.crop,
+      saved_as_crop: new_item.saved_as_crop,
+      created_at: new_item.created_at.unwrap_or_else(now_millis),
+    };
+    item.validate()?;
+    self.items.push(item.clone());
+    Ok(item)
+  }
+
+  /// Заменить элемент с тем же id.
+  pub fn replace_item(&mut self, item: CollectionItem) -> Result<CollectionItem, AppError> {
+    item.validate()?;
+    let slot = self
+      .items
+      .iter_mut()
+      .find(|it| it.id == item.id)
+      .ok_or_else(|| AppError::not_found("item", item.id.to_string()))?;
+    *slot = item.clone();
+    Ok(item)
+  }
+
+  /// Удалить элемент по id и перенумеровать order (1..n в текущем порядке).
+  pub fn remove_item(&mut self, item_id: u64) -> Result<CollectionItem, AppError> {
+    let pos = self
+      .items
+      .iter()
+      .position(|it| it.id == item_id)
+      .ok_or_else(|| AppError::not_found("item", item_id.to_string()))?;
+    let removed = self.items.remove(pos);
+    if self.cover_item_id == Some(item_id) {
+      self.cover_item_id = None;
+    }
+    self.items.sort_by_key(|it| it.order);
+    for (idx, it) in self.items.iter_mut().enumerate() {
+      it.order = idx as u64 + 1;
+    }
+    Ok(removed)
+  }
+}
+
+impl CollectionItem {
+  /// Путь к файлу элемента относительно base.
+  pub fn relative_path(&self, collection_id: &str) -> String {
+    match &self.blob {
+      Some(blob) => blobs::relative_path(blob),
+      None => format!("collections/{}/{}", collection_id, self.file),
+    }
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    validate_item_file_name(&self.file)?;
+    if let Some(blob) = &self.blob {
+      blobs::validate_blob_id(blob)?;
+    }
+    for size in [self.screen, self.image].into_iter().flatten() {
+      if size.width == 0 || size.height == 0 {
+        return Err(format!("Item {} has zero size", self.id));
+      }
+    }
+    if let Some(crop) = self.crop {
+      if crop.width == 0 || crop.height == 0 {
+        return Err(format!("Item {} has empty crop", self.id));
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Ссылки на блобы из всех коллекций. Ошибка, если хоть одни метаданные не читаются —
+/// тогда по неполным данным нельзя решать, какие блобы лишние.
+pub fn collect_blob_refs(collections_dir: &Path) -> Result<HashMap<String, u64>, String> {
+  let mut refs = HashMap::new();
+  if !collections_dir.exists() {
+    return Ok(refs);
+  }
+  for entry in fs::read_dir(collections_dir).map_err(|e| e.to_string())? {
+    let path = entry.map_err(|e| e.to_string())?.path();
+    if !path.is_dir() || !path.join(META_FILE_NAME).exists() {
+      continue;
+    }
+    for (blob, n) in read_meta(&path)?.blob_refs() {
+      *refs.entry(blob).or_insert(0) += n;
+    }
+  }
+  Ok(refs)
+}
+
+/// Название, не совпадающее с `taken`: при совпадении добавляется « (2)», « (3)»…
+pub fn unique_name(name: &str, taken: &[String]) -> String {
+  if !taken.iter().any(|n| n == name) {
+    return name.to_string();
+  }
+  (2..)
+    .map(|i| format!("{} ({})", name, i))
+    .find(|candidate| !taken.contains(candidate))
+    .unwrap()
+}
+
+/// Основа id (имени папки) из названия: всё, кроме букв, цифр, `-` и `_`, заменяется на `_`.
+pub fn sanitize_id(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+    .collect()
+}
+
+/// Цвет в формате `#RRGGBB`.
+pub fn validate_accent_color(color: &str) -> Result<(), String> {
+  let hex = color.strip_prefix('#').unwrap_or("");
+  if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+    Ok(())
+  } else {
+    Err(format!("Invalid accent color '{}', expected #RRGGBB", color))
+  }
+}
+
+/// Имя файла элемента: без разделителей путей и не служебное (`_`-префикс зарезервирован).
+pub fn validate_item_file_name(name: &str) -> Result<(), String> {
+  if name.is_empty() || name == "." || name == ".." {
+    return Err(format!("Invalid file name '{}'", name));
+  }
+  if name.contains('/') || name.contains('\\') {
+    return Err(format!("File name '{}' must not contain path separators", name));
+  }
+  if name.starts_with('_') {
+    return Err(format!("File name '{}' is reserved", name));
+  }
+  Ok(())
+}
+
+/// Прочитать и проверить `_meta.json` из папки коллекции.
+/// Если файл повреждён, восстанавливает его из последней удачной копии.
+pub fn read_meta(dir: &Path) -> Result<CollectionMeta, AppError> {
+  with_dir_lock(dir, || read_meta_unlocked(dir))
+}
+
+/// Проверить и атомарно записать `_meta.json` в папку коллекции.
+pub fn write_meta(dir: &Path, meta: &CollectionMeta) -> Result<(), AppError> {
+  with_dir_lock(dir, || write_meta_unlocked(dir, meta))
+}
+
+/// Прочитать, изменить и записать метаданные под блокировкой коллекции,
+/// чтобы параллельные импорты не теряли элементы друг друга.
+pub fn update_meta<T>(
+  dir: &Path,
+  f: impl FnOnce(&mut CollectionMeta) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+  with_dir_lock(dir, || {
+    let mut meta = read_meta_unlocked(dir)?;
+    let result = f(&mut meta)?;
+    write_meta_unlocked(dir, &meta)?;
+    Ok(result)
+  })
+}
+
+fn read_meta_unlocked(dir: &Path) -> Result<CollectionMeta, AppError> {
+  let meta_file = dir.join(META_FILE_NAME);
+  if !meta_file.exists() && !dir.join(META_BACKUP_NAME).exists() {
+    let id = dir.file_name().unwrap_or_default().to_string_lossy();
+    return Err(AppError::not_found("collection", id));
+  }
+  match parse_meta_file(&meta_file) {
+    Ok(meta) => Ok(meta),
+    Err(err) => {
+      let backup_file = dir.join(META_BACKUP_NAME);
+      let meta = match parse_meta_file(&backup_file) {
+        Ok(meta) => meta,
+        Err(_) => return Err(err.into()),
+      };
+      log::warn!("{}; restoring from {:?}", err, backup_file);
+      if meta_file.exists() {
+        let _ = fs::rename(&meta_file, dir.join(META_CORRUPT_NAME));
+      }
+      write_json(&meta_file, &meta)?;
+      Ok(meta)
+    }
+  }
+}
+
+fn write_meta_unlocked(dir: &Path, meta: &CollectionMeta) -> Result<(), AppError> {
+  meta.validate()?;
+  write_json(&dir.join(META_FILE_NAME), meta)?;
+  Ok(write_json(&dir.join(META_BACKUP_NAME), meta)?)
+}
+
+fn parse_meta_file(path: &Path) -> Result<CollectionMeta, String> {
+  let content = fs::read_to_string(path).map_err(|e| format!("read {:?}: {}", path, e))?;
+  let meta: CollectionMeta =
+    serde_json::from_str(&content).map_err(|e| format!("parse {:?}: {}", path, e))?;
+  meta.validate()?;
+  Ok(meta)
+}
+
+fn write_json(path: &Path, meta: &CollectionMeta) -> Result<(), String> {
+  let json = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
+  atomic_file::write(path, json.as_bytes())
+}
+
+/// Выполнить `f` под блокировкой папки коллекции. Мьютексы живут всё время работы процесса — коллекций немного.
+pub fn with_dir_lock<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+  static LOCKS: OnceLock<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> = OnceLock::new();
+  let lock = {
+    let mut locks = LOCKS
+      .get_or_init(Default::default)
+      .lock()
+      .unwrap_or_else(|e| e.into_inner());
+    locks.entry(dir.to_path_buf()).or_default().clone()
+  };
+  let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+  f()
+}
+
+/// Выполнить `f` под общей блокировкой названий: проверка, что название свободно, и запись
+/// `_meta.json` с ним должны идти одним шагом, иначе параллельные вызовы займут одно название дважды.
+pub fn with_names_lock<T>(f: impl FnOnce() -> T) -> T {
+  static NAMES_LOCK: Mutex<()> = Mutex::new(());
+  let _guard = NAMES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+  f()
+}
+
+pub fn now_millis() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+/// Тестовый base (`{tmp}/files`) с коллекцией `id` «One». `encryption` — параметры шифрования
+/// коллекции, `blob_items` — имена файлов, которые кладутся в хранилище блобов и становятся элементами.
+#[cfg(test)]
+pub(crate) fn test_base(
+  id: &str,
+  encryption: Option<EncryptionInfo>,
+  blob_items: &[&str],
+) -> (tempfile::TempDir, PathBuf) {
+  let tmp = tempfile::tempdir().unwrap();
+  let base = tmp.path().join("files");
+  let dir = base.join("collections").join(id);
+  fs::create_dir_all(&dir).unwrap();
+  let mut meta = CollectionMeta::new(id.into(), "One".into(), 1);
+  meta.encryption = encryption;
+  let store = blobs::BlobStore::new(&base);
+  for file in blob_items {
+    let blob = store.put_bytes(format!("{} image", file).as_bytes(), file).unwrap();
+    store.retain(&blob).unwrap();
+    meta
+      .push_item(NewCollectionItem {
+        file: file.to_string(),
+        blob: Some(blob),
+        screen: None,
+        image: None,
+        crop: None,
+        saved_as_crop: false,
+        created_at: Some(1),
+      })
+      .unwrap();
+  }
+  write_meta(&dir, &meta).unwrap();
+  (tmp, base)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn new_item(file: &str) -> NewCollectionItem {
+    NewCollectionItem {
+      file: file.into(),
+      blob: None,
+      screen: None,
+      image: None,
+      crop: None,
+      saved_as_crop: false,
+      created_at: Some(1),
+    }
+  }
+
+  #[test]
+  fn corrupt_meta_is_restored_from_backup() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let mut meta = CollectionMeta::new("c1".into(), "Cats".into(), 1);
+    meta.push_item(new_item("a.jpg")).unwrap();
+    write_meta(dir, &meta).unwrap();
+
+    // Оборванная запись: от файла осталось начало
+    let content = fs::read(dir.join(META_FILE_NAME)).unwrap();
+    fs::write(dir.join(META_FILE_NAME), &content[..content.len() / 2]).unwrap();
+    assert_eq!(read_meta(dir).unwrap(), meta);
+    assert_eq!(parse_meta_file(&dir.join(META_FILE_NAME)).unwrap(), meta);
+    assert_eq!(fs::read(dir.join(META_CORRUPT_NAME)).unwrap(), &content[..content.len() / 2]);
+
+    // Без обоих файлов восстанавливать не из чего
+    fs::write(dir.join(META_FILE_NAME), b"{}").unwrap();
+    fs::remove_file(dir.join(META_BACKUP_NAME)).unwrap();
+    assert!(read_meta(dir).is_err());
+  }
+
+  #[test]
+  fn missing_collection_and_items_are_not_found() {
+    let tmp = tempfile::tempdir().unwrap();
+    assert_eq!(read_meta(&tmp.path().join("gone")), Err(AppError::not_found("collection", "gone")));
+
+    let mut meta = CollectionMeta::new("c1".into(), "Cats".into(), 1);
+    let item = meta.push_item(new_item("a.jpg")).unwrap();
+    assert_eq!(meta.remove_item(7), Err(AppError::not_found("item", "7")));
+    assert_eq!(
+      meta.replace_item(CollectionItem { id: 7, ..item }),
+      Err(AppError::not_found("item", "7"))
+    );
+    let details = CollectionDetails {
+      description: None,
+      accent_color: None,
+      cover_item_id: Some(7),
+    };
+    assert_eq!(meta.apply_details(details), Err(AppError::not_found("item", "7")));
+  }
+
+  #[test]
+  fn concurrent_updates_keep_every_item() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path().to_path_buf();
+    write_meta(&dir, &CollectionMeta::new("c1".into(), "Cats".into(), 1)).unwrap();
+
+    let threads: Vec<_> = (0..8)
+      .map(|t| {
+        let dir = dir.clone();
+        std::thread::spawn(move || {
+          for i in 0..5 {
+            update_meta(&dir, |meta| meta.push_item(new_item(&format!("{}-{}.jpg", t, i)))).unwrap();
+          }
+        })
+      })
+      .collect();
+    for t in threads {
+      t.join().unwrap();
+    }
+
+    let meta = read_meta(&dir).unwrap();
+    assert_eq!(meta.items.len(), 40);
+    let files: HashSet<_> = meta.items.iter().map(|it| it.file.as_str()).collect();
+    assert_eq!(files.len(), 40);
+    assert_eq!(meta.next_item_id(), 41);
+  }
+}
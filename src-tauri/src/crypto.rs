@@ -0,0 +1,514 @@
+//! Шифрование коллекций, совместимое с `app/helpers/crypto.ts`.
+//!
+//! Пароль нигде не хранится — только SHA-256 хеш для проверки при разблокировке.
+//! Ключ (PBKDF2-SHA256, 100 000 итераций, 256 бит) живёт в памяти, пока коллекция разблокирована.
+//!
+//! Форматы файлов:
+//! - одиночный (как в crypto.ts): salt (16) + iv (12) + ciphertext с тегом GCM;
+//! - потоковый для больших изображений: заголовок `CWSTRM01` + salt (16) + префикс nonce (7) +
+//!   размер чанка (u32 LE), затем чанки AES-GCM по `chunk_size` байт открытого текста.
+//!   Nonce чанка = префикс + номер чанка (u32 BE) + флаг последнего чанка (0/1), поэтому
+//!   перестановка, обрезка или дописывание чанков не проходят проверку тега.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::atomic_file;
+
+pub const PBKDF2_ITERATIONS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+const IV_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+const TAG_LENGTH: usize = 16;
+const STREAM_MAGIC: &[u8; 8] = b"CWSTRM01";
+const NONCE_PREFIX_LENGTH: usize = 7;
+/// Размер чанка открытого текста в потоковом формате.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Верхняя граница размера чанка из заголовка (защита от огромных аллокаций).
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+/// Файлы больше этого размера шифруются потоково; меньше — одиночным форматом, который читает crypto.ts.
+pub const STREAM_THRESHOLD: u64 = 1024 * 1024;
+
+type Salt = [u8; SALT_LENGTH];
+type Key = [u8; KEY_LENGTH];
+
+/// Параметры шифрования коллекции в `_meta.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionInfo {
+  /// Соль для файлов, которые шифрует Rust (hex, 16 байт). У файлов из crypto.ts соль своя — в самом файле.
+  pub salt: String,
+  /// SHA-256 пароля (hex), только для проверки при разблокировке.
+  pub password_hash: String,
+  #[serde(default = "default_iterations")]
+  pub iterations: u32,
+}
+
+fn default_iterations() -> u32 {
+  PBKDF2_ITERATIONS
+}
+
+impl EncryptionInfo {
+  /// Новые параметры для пароля со случайной солью.
+  pub fn new(password: &str) -> Result<Self, String> {
+    if password.is_empty() {
+      return Err("Password is empty".to_string());
+    }
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    Ok(Self {
+      salt: to_hex(&salt),
+      password_hash: hash_password(password),
+      iterations: PBKDF2_ITERATIONS,
+    })
+  }
+
+  pub fn validate(&self) -> Result<(), String> {
+    parse_salt(&self.salt)?;
+    if self.password_hash.len() != 64 || !self.password_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+      return Err("Invalid password hash".to_string());
+    }
+    if self.iterations == 0 {
+      return Err("Invalid PBKDF2 iteration count".to_string());
+    }
+    Ok(())
+  }
+}
+
+/// Ключи разблокированной коллекции. Пароль держим, чтобы читать файлы с собственной солью (из crypto.ts).
+#[derive(Clone)]
+pub struct Session {
+  password: Vec<u8>,
+  iterations: u32,
+  salt: Salt,
+  keys: HashMap<Salt, Key>,
+}
+
+impl Session {
+  /// Проверить пароль по хешу и получить ключ коллекции.
+  pub fn unlock(info: &EncryptionInfo, password: &str) -> Result<Self, String> {
+    info.validate()?;
+    if !constant_time_eq(hash_password(password).as_bytes(), info.password_hash.as_bytes()) {
+      return Err("Wrong password".to_string());
+    }
+    let salt = parse_salt(&info.salt)?;
+    let mut session = Self {
+      password: password.as_bytes().to_vec(),
+      iterations: info.iterations,
+      salt,
+      keys: HashMap::new(),
+    };
+    session.key(&salt);
+    Ok(session)
+  }
+
+  fn key(&mut self, salt: &Salt) -> Key {
+    let (password, iterations) = (&self.password, self.iterations);
+    *self
+      .keys
+      .entry(*salt)
+      .or_insert_with(|| derive_key(password, salt, iterations))
+  }
+}
+
+impl Drop for Session {
+  fn drop(&mut self) {
+    self.password.iter_mut().for_each(|b| *b = 0);
+    self.keys.values_mut().for_each(|k| k.fill(0));
+  }
+}
+
+/// Разблокированные коллекции: id → сессия. Только в памяти процесса.
+fn sessions() -> &'static Mutex<HashMap<String, Session>> {
+  static SESSIONS: OnceLock<Mutex<HashMap<String, Session>>> = OnceLock::new();
+  SESSIONS.get_or_init(Default::default)
+}
+
+pub fn unlock(collection_id: &str, info: &EncryptionInfo, password: &str) -> Result<(), String> {
+  let session = Session::unlock(info, password)?;
+  sessions()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .insert(collection_id.to_string(), session);
+  Ok(())
+}
+
+pub fn lock(collection_id: &str) {
+  sessions()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .remove(collection_id);
+}
+
+pub fn is_unlocked(collection_id: &str) -> bool {
+  sessions()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .contains_key(collection_id)
+}
+
+/// Выполнить `f` с копией сессии коллекции; ошибка, если коллекция заблокирована.
+/// Общая блокировка держится только на время копирования: шифрование большого файла и вывод
+/// ключа для чужой соли не задерживают unlock/lock других коллекций.
+pub fn with_session<T>(
+  collection_id: &str,
+  f: impl FnOnce(&mut Session) -> Result<T, String>,
+) -> Result<T, String> {
+  let mut session = sessions()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .get(collection_id)
+    .cloned()
+    .ok_or_else(|| format!("Collection '{}' is locked", collection_id))?;
+  let result = f(&mut session);
+  // Выведенные по дороге ключи остаются в сессии, если её не сменили заново открытой с другим паролем
+  let mut sessions = sessions().lock().unwrap_or_else(|e| e.into_inner());
+  if let Some(shared) = sessions.get_mut(collection_id).filter(|s| s.password == session.password) {
+    for (salt, key) in &session.keys {
+      shared.keys.entry(*salt).or_insert(*key);
+    }
+  }
+  result
+}
+
+/// Зашифровать `len` байт из `src` в файл папки зашифрованной коллекции (не в общее хранилище:
+/// одинаковые изображения в разных коллекциях не должны выдавать друг друга).
+/// Имя при совпадении получает суффикс; возвращает итоговое имя файла. Данные шифруются
+/// потоком прямо во временный файл атомарной записи и целиком в памяти не собираются.
+pub fn save_to_collection(
+  dir: &Path,
+  collection_id: &str,
+  name: &str,
+  src: &mut impl Read,
+  len: u64,
+) -> Result<String, String> {
+  let path = Path::new(name);
+  let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+  let ext = path.extension().and_then(|e| e.to_str());
+  let mut final_name = name.to_string();
+  let mut counter = 0;
+  // Имя занимается пустым файлом (create_new): параллельное сохранение с тем же именем возьмёт следующее
+  loop {
+    match OpenOptions::new().write(true).create_new(true).open(dir.join(&final_name)) {
+      Ok(_) => break,
+      Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+      Err(e) => return Err(format!("create {:?}: {}", dir.join(&final_name), e)),
+    }
+    counter += 1;
+    final_name = match ext {
+      Some(ext) => format!("{}_{}.{}", stem, counter, ext),
+      None => format!("{}_{}", stem, counter),
+    };
+  }
+
+  let path = dir.join(&final_name);
+  let written = with_session(collection_id, |session| {
+    atomic_file::write_with(&path, |file| encrypt(session, src, len, file))
+  });
+  if let Err(e) = written {
+    let _ = fs::remove_file(&path);
+    return Err(e);
+  }
+  Ok(final_name)
+}
+
+/// Хеш пароля для хранения: SHA-256 в hex, как `hashPassword` в crypto.ts.
+pub fn hash_password(password: &str) -> String {
+  to_hex(&Sha256::digest(password.as_bytes()))
+}
+
+fn derive_key(password: &[u8], salt: &Salt, iterations: u32) -> Key {
+  let mut key = [0u8; KEY_LENGTH];
+  pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut key);
+  key
+}
+
+/// Зашифровать `len` байт из `src` в `out`. Большие данные — потоково, чанками.
+pub fn encrypt(session: &mut Session, src: &mut impl Read, len: u64, out: &mut impl Write) -> Result<(), String> {
+  let salt = session.salt;
+  let cipher = Aes256Gcm::new(&session.key(&salt).into());
+  if len <= STREAM_THRESHOLD {
+    let mut data = Vec::with_capacity(len as usize);
+    src.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    let mut iv = [0u8; IV_LENGTH];
+    OsRng.fill_bytes(&mut iv);
+    let ciphertext = cipher
+      .encrypt(Nonce::from_slice(&iv), data.as_slice())
+      .map_err(|_| "Encryption failed".to_string())?;
+    for part in [&salt[..], &iv[..], &ciphertext[..]] {
+      out.write_all(part).map_err(|e| e.to_string())?;
+    }
+    return Ok(());
+  }
+
+  let mut prefix = [0u8; NONCE_PREFIX_LENGTH];
+  OsRng.fill_bytes(&mut prefix);
+  for part in [&STREAM_MAGIC[..], &salt[..], &prefix[..], &(CHUNK_SIZE as u32).to_le_bytes()[..]] {
+    out.write_all(part).map_err(|e| e.to_string())?;
+  }
+  let mut current = vec![0u8; CHUNK_SIZE];
+  let mut next = vec![0u8; CHUNK_SIZE];
+  let mut current_len = read_full(src, &mut current)?;
+  let mut counter: u32 = 0;
+  loop {
+    let next_len = if current_len == CHUNK_SIZE { read_full(src, &mut next)? } else { 0 };
+    let last = next_len == 0;
+    let nonce = chunk_nonce(&prefix, counter, last);
+    let ciphertext = cipher
+      .encrypt(Nonce::from_slice(&nonce), &current[..current_len])
+      .map_err(|_| "Encryption failed".to_string())?;
+    out.write_all(&ciphertext).map_err(|e| e.to_string())?;
+    if last {
+      return Ok(());
+    }
+    counter = counter
+      .checked_add(1)
+      .ok_or_else(|| "File too large to encrypt".to_string())?;
+    std::mem::swap(&mut current, &mut next);
+    current_len = next_len;
+  }
+}
+
+/// Расшифровать файл любого из двух форматов из `src` в `out`.
+pub fn decrypt(session: &mut Session, src: &mut impl Read, out: &mut impl Write) -> Result<(), String> {
+  let mut head = [0u8; STREAM_MAGIC.len()];
+  let head_len = read_full(src, &mut head)?;
+  if head_len == head.len() && &head == STREAM_MAGIC {
+    return decrypt_stream(session, src, out);
+  }
+
+  let mut data = head[..head_len].to_vec();
+  src.read_to_end(&mut data).map_err(|e| e.to_string())?;
+  if data.len() < SALT_LENGTH + IV_LENGTH + TAG_LENGTH {
+    return Err("Encrypted file is truncated".to_string());
+  }
+  let (salt, rest) = data.split_at(SALT_LENGTH);
+  let (iv, ciphertext) = rest.split_at(IV_LENGTH);
+  let salt: Salt = salt.try_into().unwrap();
+  let cipher = Aes256Gcm::new(&session.key(&salt).into());
+  let plain = cipher
+    .decrypt(Nonce::from_slice(iv), ciphertext)
+    .map_err(|_| "Decryption failed: wrong password or corrupted file".to_string())?;
+  out.write_all(&plain).map_err(|e| e.to_string())
+}
+
+fn decrypt_stream(session: &mut Session, src: &mut impl Read, out: &mut impl Write) -> Result<(), String> {
+  let mut header = [0u8; SALT_LENGTH + NONCE_PREFIX_LENGTH + 4];
+  if read_full(src, &mut header)? != header.len() {
+    return Err("Encrypted file is truncated".to_string());
+  }
+  let salt: Salt = header[..SALT_LENGTH].try_into().unwrap();
+  let prefix: [u8; NONCE_PREFIX_LENGTH] = header[SALT_LENGTH..SALT_LENGTH + NONCE_PREFIX_LENGTH]
+    .try_into()
+    .unwrap();
+  let chunk_size = u32::from_le_bytes(header[SALT_LENGTH + NONCE_PREFIX_LENGTH..].try_into().unwrap()) as usize;
+  if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+    return Err(format!("Invalid chunk size {}", chunk_size));
+  }
+  let cipher = Aes256Gcm::new(&session.key(&salt).into());
+
+  let sealed = chunk_size + TAG_LENGTH;
+  let mut current = vec![0u8; sealed];
+  let mut next = vec![0u8; sealed];
+  let mut current_len = read_full(src, &mut current)?;
+  let mut counter: u32 = 0;
+  loop {
+    let next_len = if current_len == sealed { read_full(src, &mut next)? } else { 0 };
+    let last = next_len == 0;
+    let nonce = chunk_nonce(&prefix, counter, last);
+    let plain = cipher
+      .decrypt(Nonce::from_slice(&nonce), &current[..current_len])
+      .map_err(|_| "Decryption failed: wrong password or corrupted file".to_string())?;
+    out.write_all(&plain).map_err(|e| e.to_string())?;
+    if last {
+      return Ok(());
+    }
+    counter = counter
+      .checked_add(1)
+      .ok_or_else(|| "Encrypted file is too large".to_string())?;
+    std::mem::swap(&mut current, &mut next);
+    current_len = next_len;
+  }
+}
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_LENGTH], counter: u32, last: bool) -> [u8; IV_LENGTH] {
+  let mut nonce = [0u8; IV_LENGTH];
+  nonce[..NONCE_PREFIX_LENGTH].copy_from_slice(prefix);
+  nonce[NONCE_PREFIX_LENGTH..IV_LENGTH - 1].copy_from_slice(&counter.to_be_bytes());
+  nonce[IV_LENGTH - 1] = u8::from(last);
+  nonce
+}
+
+/// Читать, пока буфер не заполнится или не кончится поток.
+fn read_full(src: &mut impl Read, buf: &mut [u8]) -> Result<usize, String> {
+  let mut filled = 0;
+  while filled < buf.len() {
+    match src.read(&mut buf[filled..]) {
+      Ok(0) => break,
+      Ok(n) => filled += n,
+      Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+      Err(e) => return Err(e.to_string()),
+    }
+  }
+  Ok(filled)
+}
+
+fn parse_salt(hex: &str) -> Result<Salt, String> {
+  let bytes = from_hex(hex).ok_or_else(|| "Invalid salt".to_string())?;
+  bytes.try_into().map_err(|_| "Invalid salt length".to_string())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+  if hex.len() % 2 != 0 {
+    return None;
+  }
+  (0..hex.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn session(password: &str) -> (EncryptionInfo, Session) {
+    let info = EncryptionInfo::new(password).unwrap();
+    let session = Session::unlock(&info, password).unwrap();
+    (info, session)
+  }
+
+  fn roundtrip(session: &mut Session, data: &[u8]) -> Vec<u8> {
+    let mut encrypted = Vec::new();
+    encrypt(session, &mut &data[..], data.len() as u64, &mut encrypted).unwrap();
+    let mut plain = Vec::new();
+    decrypt(session, &mut encrypted.as_slice(), &mut plain).unwrap();
+    plain
+  }
+
+  #[test]
+  fn decrypts_file_written_by_crypto_ts() {
+    // encryptWithPassword('secret', 'hello from webcrypto') из app/helpers/crypto.ts
+    let encrypted = from_hex(concat!(
+      "000102030405060708090a0b0c0d0e0f",
+      "101112131415161718191a1b",
+      "f8794f0674dff93f74f7244248652656e83d61f8982b3a3c7fcb15af6ee3786900f816ba",
+    ))
+    .unwrap();
+    let info = EncryptionInfo {
+      salt: "ffffffffffffffffffffffffffffffff".to_string(),
+      password_hash: hash_password("secret"),
+      iterations: PBKDF2_ITERATIONS,
+    };
+    let mut session = Session::unlock(&info, "secret").unwrap();
+    let mut plain = Vec::new();
+    let result = decrypt(&mut session, &mut encrypted.as_slice(), &mut plain);
+    assert_eq!(result.map(|_| plain), Ok(b"hello from webcrypto".to_vec()));
+  }
+
+  #[test]
+  fn small_files_use_crypto_ts_layout() {
+    let (info, mut session) = session("pw");
+    let data = b"small image";
+    let mut encrypted = Vec::new();
+    encrypt(&mut session, &mut &data[..], data.len() as u64, &mut encrypted).unwrap();
+    assert_eq!(to_hex(&encrypted[..SALT_LENGTH]), info.salt);
+    assert_eq!(encrypted.len(), SALT_LENGTH + IV_LENGTH + data.len() + TAG_LENGTH);
+    assert_eq!(roundtrip(&mut session, data), data);
+  }
+
+  #[test]
+  fn large_files_roundtrip_in_chunks() {
+    let (_, mut session) = session("pw");
+    for len in [STREAM_THRESHOLD as usize + 1, CHUNK_SIZE * 20, CHUNK_SIZE * 20 + 7] {
+      let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+      let mut encrypted = Vec::new();
+      encrypt(&mut session, &mut data.as_slice(), len as u64, &mut encrypted).unwrap();
+      assert_eq!(&encrypted[..8], STREAM_MAGIC);
+      assert_eq!(roundtrip(&mut session, &data), data);
+    }
+  }
+
+  #[test]
+  fn stream_detects_truncation_reordering_and_wrong_password() {
+    let (info, mut session) = session("pw");
+    let data = vec![7u8; CHUNK_SIZE * 20];
+    let mut encrypted = Vec::new();
+    encrypt(&mut session, &mut data.as_slice(), data.len() as u64, &mut encrypted).unwrap();
+    let header = STREAM_MAGIC.len() + SALT_LENGTH + NONCE_PREFIX_LENGTH + 4;
+    let sealed = CHUNK_SIZE + TAG_LENGTH;
+    let mut sink = Vec::new();
+
+    // Обрезка по границе чанка
+    let truncated = &encrypted[..header + sealed * 19];
+    assert!(decrypt(&mut session, &mut &truncated[..], &mut sink).is_err());
+
+    // Перестановка двух чанков
+    let mut swapped = encrypted.clone();
+    let (a, b) = (header, header + sealed);
+    let first = swapped[a..a + sealed].to_vec();
+    swapped.copy_within(b..b + sealed, a);
+    swapped[b..b + sealed].copy_from_slice(&first);
+    assert!(decrypt(&mut session, &mut swapped.as_slice(), &mut sink).is_err());
+
+    assert!(Session::unlock(&info, "wrong").is_err());
+    let other = EncryptionInfo { password_hash: hash_password("other"), ..info };
+    let mut other = Session::unlock(&other, "other").unwrap();
+    assert!(decrypt(&mut other, &mut encrypted.as_slice(), &mut sink).is_err());
+  }
+
+  #[test]
+  fn sessions_lock_and_unlock() {
+    let info = EncryptionInfo::new("pw").unwrap();
+    assert!(unlock("c", &info, "nope").is_err());
+    assert!(!is_unlocked("c"));
+    unlock("c", &info, "pw").unwrap();
+    assert!(with_session("c", |_| Ok(())).is_ok());
+    lock("c");
+    assert!(with_session("c", |_| Ok(())).is_err());
+  }
+
+  #[test]
+  fn concurrent_saves_get_distinct_names_without_holding_sessions() {
+    let tmp = tempfile::tempdir().unwrap();
+    let info = EncryptionInfo::new("pw").unwrap();
+    unlock("saves", &info, "pw").unwrap();
+    // Пока идёт шифрование, общая блокировка свободна
+    assert_eq!(with_session("saves", |_| Ok(is_unlocked("saves"))), Ok(true));
+
+    let data = vec![3u8; STREAM_THRESHOLD as usize + 5];
+    let names: Vec<String> = std::thread::scope(|scope| {
+      let saves: Vec<_> = (0..4)
+        .map(|_| {
+          let (dir, data) = (tmp.path(), &data);
+          scope.spawn(move || save_to_collection(dir, "saves", "a.webp", &mut data.as_slice(), data.len() as u64).unwrap())
+        })
+        .collect();
+      saves.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    let unique: std::collections::HashSet<_> = names.iter().collect();
+    assert_eq!(unique.len(), 4);
+    for name in &names {
+      let mut plain = Vec::new();
+      let file = fs::File::open(tmp.path().join(name)).unwrap();
+      with_session("saves", |s| decrypt(s, &mut std::io::BufReader::new(file), &mut plain)).unwrap();
+      assert_eq!(plain, data);
+    }
+    lock("saves");
+  }
+}
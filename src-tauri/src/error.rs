@@ -0,0 +1,128 @@
+//! Ошибки команд. Интерфейс получает объект с устойчивым `code` и полями контекста и сам выбирает
+//! перевод, а не разбирает текст. Модули, которым классифицировать нечего, по-прежнему возвращают
+//! `String` — такая ошибка доходит до интерфейса как `Invalid` с исходным текстом.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code")]
+pub enum AppError {
+  /// `kind` — что искали: collection, item, playlist, trash_entry, blob, file, rotation, upload.
+  NotFound { kind: &'static str, id: String },
+  /// Путь вне base, с `..`, через симлинк наружу или не один компонент там, где нужен один.
+  PathNotAllowed { path: String },
+  /// Действие недоступно на этой платформе или в этом окружении; `feature` — что именно.
+  Unsupported { feature: &'static str },
+  /// Действие только для зашифрованной коллекции, а коллекция `id` не зашифрована.
+  NotEncrypted { id: String },
+  /// Имя уже занято; `kind` — collection, playlist или file.
+  DuplicateName { kind: &'static str, name: String },
+  /// Файл не удалось прочитать как изображение.
+  DecodeFailed { path: String, reason: String },
+  Io { path: Option<String>, reason: String },
+  /// Вызов Android API через JNI не удался.
+  Jni { reason: String },
+  /// Прочее: неверные данные, пароль, формат архива. `reason` — текст для журнала.
+  Invalid { reason: String },
+}
+
+impl AppError {
+  pub fn not_found(kind: &'static str, id: impl Into<String>) -> Self {
+    Self::NotFound { kind, id: id.into() }
+  }
+
+  pub fn path_not_allowed(path: impl Into<String>) -> Self {
+    Self::PathNotAllowed { path: path.into() }
+  }
+
+  pub fn duplicate_name(kind: &'static str, name: impl Into<String>) -> Self {
+    Self::DuplicateName { kind, name: name.into() }
+  }
+
+  pub fn decode_failed(path: impl Into<String>, reason: impl fmt::Display) -> Self {
+    Self::DecodeFailed {
+      path: path.into(),
+      reason: reason.to_string(),
+    }
+  }
+
+  pub fn io(path: &Path, e: std::io::Error) -> Self {
+    Self::Io {
+      path: Some(path.display().to_string()),
+      reason: e.to_string(),
+    }
+  }
+
+  pub fn jni(reason: impl fmt::Display) -> Self {
+    Self::Jni {
+      reason: reason.to_string(),
+    }
+  }
+}
+
+impl fmt::Display for AppError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotFound { kind, id } => write!(f, "{} '{}' not found", kind, id),
+      Self::PathNotAllowed { path } => write!(f, "Path not allowed: '{}'", path),
+      Self::Unsupported { feature } => write!(f, "{} is not supported on this platform", feature),
+      Self::NotEncrypted { id } => write!(f, "Collection '{}' is not encrypted", id),
+      Self::DuplicateName { kind, name } => write!(f, "{} named '{}' already exists", kind, name),
+      Self::DecodeFailed { path, reason } => write!(f, "Failed to decode '{}': {}", path, reason),
+      Self::Io { path: Some(path), reason } => write!(f, "{}: {}", path, reason),
+      Self::Io { path: None, reason } | Self::Jni { reason } | Self::Invalid { reason } => f.write_str(reason),
+    }
+  }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+  fn from(reason: String) -> Self {
+    Self::Invalid { reason }
+  }
+}
+
+impl From<std::io::Error> for AppError {
+  fn from(e: std::io::Error) -> Self {
+    Self::Io {
+      path: None,
+      reason: e.to_string(),
+    }
+  }
+}
+
+/// Для модулей на `Result<_, String>`: `?` над AppError сохраняет текст ошибки.
+impl From<AppError> for String {
+  fn from(e: AppError) -> Self {
+    e.to_string()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serializes_with_stable_code_and_context() {
+    let json = |e: &AppError| serde_json::to_value(e).unwrap();
+    assert_eq!(
+      json(&AppError::not_found("collection", "c1")),
+      serde_json::json!({ "code": "NotFound", "kind": "collection", "id": "c1" })
+    );
+    assert_eq!(
+      json(&AppError::duplicate_name("playlist", "Mix")),
+      serde_json::json!({ "code": "DuplicateName", "kind": "playlist", "name": "Mix" })
+    );
+    assert_eq!(json(&AppError::from("bad".to_string()))["code"], "Invalid");
+
+    // Через String-модули ошибка доходит текстом, обратно — как Invalid
+    let text: String = AppError::path_not_allowed("../x").into();
+    assert_eq!(text, "Path not allowed: '../x'");
+    assert_eq!(AppError::from(text.clone()), AppError::Invalid { reason: text });
+    let io = AppError::io(Path::new("a.json"), std::io::Error::new(std::io::ErrorKind::NotFound, "gone"));
+    assert_eq!(io.to_string(), "a.json: gone");
+  }
+}
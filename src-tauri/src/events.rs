@@ -0,0 +1,148 @@
+//! События для интерфейса. Rust сообщает о каждой смене обоев и о запуске, изменении, остановке
+//! и ошибках ротации, кто бы их ни вызвал: кнопка в приложении, цикл на ПК или WallpaperRotationService
+//! на Android. Интерфейс обновляется по ним, а не опрашивает состояние по таймеру.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::history::{HistoryEntry, HistorySource};
+use crate::rotation::RotationState;
+use crate::rotation_loop::RotationSlot;
+
+pub const WALLPAPER_CHANGED: &str = "wallpaper-changed";
+pub const ROTATION_STARTED: &str = "rotation-started";
+pub const ROTATION_UPDATED: &str = "rotation-updated";
+pub const ROTATION_STOPPED: &str = "rotation-stopped";
+pub const ROTATION_ERROR: &str = "rotation-error";
+
+/// Обои поставлены. Поля записи истории плюс слот и его состояние, если обои сменила ротация.
+#[derive(Debug, Clone, Serialize)]
+pub struct WallpaperChanged {
+  #[serde(flatten)]
+  pub entry: HistoryEntry,
+  /// None — обои поставлены вручную, вне ротации.
+  pub slot: Option<RotationSlot>,
+  /// Состояние слота после смены; None, если его нет (ручная установка).
+  pub state: Option<RotationState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationStarted {
+  pub slot: RotationSlot,
+  pub state: Option<RotationState>,
+}
+
+/// Состояние идущей ротации изменилось без смены обоев: расписание, интервал, закрепление,
+/// новый состав коллекции или шаг, обои для которого ставит вызывающий.
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationUpdated {
+  pub slot: RotationSlot,
+  pub state: RotationState,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationStopped {
+  pub slot: RotationSlot,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RotationError {
+  /// None — ошибка не относится к конкретному слоту.
+  pub slot: Option<RotationSlot>,
+  pub source: HistorySource,
+  pub message: String,
+}
+
+pub fn wallpaper_changed(app: &AppHandle, payload: WallpaperChanged) {
+  emit(app, WALLPAPER_CHANGED, payload);
+}
+
+pub fn rotation_started(app: &AppHandle, slot: RotationSlot, state: Option<RotationState>) {
+  emit(app, ROTATION_STARTED, RotationStarted { slot, state });
+}
+
+pub fn rotation_updated(app: &AppHandle, slot: RotationSlot, state: RotationState) {
+  emit(app, ROTATION_UPDATED, RotationUpdated { slot, state });
+}
+
+pub fn rotation_stopped(app: &AppHandle, slot: RotationSlot) {
+  emit(app, ROTATION_STOPPED, RotationStopped { slot });
+}
+
+pub fn rotation_error(app: &AppHandle, slot: Option<RotationSlot>, source: HistorySource, message: String) {
+  emit(app, ROTATION_ERROR, RotationError { slot, source, message });
+}
+
+/// Событие не доставлено — интерфейс закрыт или ещё не загружен; смене обоев это не мешает.
+fn emit(app: &AppHandle, event: &str, payload: impl Serialize + Clone) {
+  if let Err(e) = app.emit(event, payload) {
+    log::warn!("event {}: {}", event, e);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rotation::{RotationEngine, RotationMode};
+  use serde_json::json;
+
+  fn state() -> RotationState {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 15, vec!["a.webp".into(), "b.webp".into()], 0);
+    engine.start(1_000);
+    engine.state(1_000)
+  }
+
+  /// Поля совпадают с интерфейсами `*Event` в app/helpers/tauri/file.ts.
+  #[test]
+  fn payloads_match_frontend_events() {
+    let entry = HistoryEntry {
+      at: 1_000,
+      path: "a.webp".into(),
+      target: "lock".into(),
+      source: HistorySource::Service,
+      collection_id: Some("c1".into()),
+    };
+    let changed = serde_json::to_value(WallpaperChanged {
+      entry: entry.clone(),
+      slot: Some(RotationSlot::Lock),
+      state: Some(state()),
+    })
+    .unwrap();
+    assert_eq!(changed["path"], "a.webp");
+    assert_eq!(changed["source"], "service");
+    assert_eq!(changed["collection_id"], "c1");
+    assert_eq!(changed["slot"], "lock");
+    assert_eq!(changed["state"]["current"], "a.webp");
+
+    let manual = serde_json::to_value(WallpaperChanged {
+      entry,
+      slot: None,
+      state: None,
+    })
+    .unwrap();
+    assert_eq!((&manual["slot"], &manual["state"]), (&json!(null), &json!(null)));
+
+    let updated = serde_json::to_value(RotationUpdated {
+      slot: RotationSlot::Home,
+      state: state(),
+    })
+    .unwrap();
+    assert_eq!(updated["slot"], "home");
+    assert_eq!(updated["state"]["collection_id"], "c1");
+    assert_eq!(updated["state"]["interval_minutes"], 15);
+
+    assert_eq!(
+      serde_json::to_value(RotationStopped { slot: RotationSlot::Lock }).unwrap(),
+      json!({ "slot": "lock" })
+    );
+    assert_eq!(
+      serde_json::to_value(RotationError {
+        slot: None,
+        source: HistorySource::Desktop,
+        message: "gone".into(),
+      })
+      .unwrap(),
+      json!({ "slot": null, "source": "desktop", "message": "gone" })
+    );
+  }
+}
@@ -0,0 +1,237 @@
+//! Журнал смен обоев: какое изображение, когда, куда и кем поставлено. Записи только дописываются
+//! строками JSON в history/log.jsonl внутри base; файл переписывается целиком лишь при очистке
+//! по сроку хранения.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::atomic_file;
+
+/// Журнал внутри base: history/log.jsonl + _settings.json.
+pub const HISTORY_DIR: &str = "history";
+const LOG_FILE_NAME: &str = "log.jsonl";
+const SETTINGS_FILE_NAME: &str = "_settings.json";
+/// Сколько дней хранить записи по умолчанию.
+pub const DEFAULT_RETENTION_DAYS: u32 = 90;
+/// Сколько записей хранить не больше, независимо от срока (частая ротация не раздувает файл).
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// Дозапись и очистка из разных потоков (команды, цикл ротации) не должны перемешиваться.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Кто сменил обои.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySource {
+  /// Из интерфейса приложения (кнопка или таймер ротации во фронтенде).
+  App,
+  /// WallpaperRotationService на Android.
+  Service,
+  /// Цикл ротации на ПК (rotation_loop).
+  Desktop,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+  /// Время смены, мс с эпохи Unix.
+  pub at: u64,
+  /// Путь относительно base.
+  pub path: String,
+  /// both / home / lock.
+  pub target: String,
+  pub source: HistorySource,
+  /// Коллекция, из которой взято изображение, если известна.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub collection_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct HistorySettings {
+  /// 0 — не очищать по сроку (остаётся только ограничение MAX_ENTRIES).
+  retention_days: u32,
+}
+
+pub struct History {
+  root: PathBuf,
+}
+
+impl History {
+  pub fn new(base: &Path) -> Self {
+    Self {
+      root: base.join(HISTORY_DIR),
+    }
+  }
+
+  /// Дописать запись в конец журнала.
+  pub fn append(&self, entry: &HistoryEntry) -> Result<(), String> {
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+    let path = self.log_file();
+    let mut file = OpenOptions::new()
+      .read(true)
+      .create(true)
+      .append(true)
+      .open(&path)
+      .map_err(|e| format!("open {:?}: {}", path, e))?;
+    // Строка, оборванная при сбое, не должна склеиться с новой записью
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    if len > 0 {
+      let mut last = [0u8];
+      file.seek(SeekFrom::Start(len - 1)).map_err(|e| e.to_string())?;
+      file.read_exact(&mut last).map_err(|e| e.to_string())?;
+      if last[0] != b'\n' {
+        line.insert(0, '\n');
+      }
+    }
+    file.write_all(line.as_bytes()).map_err(|e| format!("write {:?}: {}", path, e))
+  }
+
+  /// Записи не старше `since` (мс), новые сверху, не больше `limit`.
+  pub fn list(&self, limit: Option<usize>, since: Option<u64>) -> Result<Vec<HistoryEntry>, String> {
+    let since = since.unwrap_or(0);
+    let mut entries: Vec<HistoryEntry> = self.read()?.into_iter().filter(|e| e.at >= since).collect();
+    // Часы устройства могут переводиться назад — порядок задаёт время, а не позиция в файле
+    entries.reverse();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.at));
+    if let Some(limit) = limit {
+      entries.truncate(limit);
+    }
+    Ok(entries)
+  }
+
+  /// Удалить записи старше срока хранения и сверх MAX_ENTRIES. Возвращает число удалённых.
+  pub fn purge_expired(&self, now_ms: u64) -> Result<usize, String> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let entries = self.read()?;
+    let total = entries.len();
+    let days = self.retention_days();
+    let max_age = u64::from(days) * 24 * 60 * 60 * 1000;
+    let mut kept: Vec<HistoryEntry> = entries
+      .into_iter()
+      .filter(|e| days == 0 || now_ms.saturating_sub(e.at) < max_age)
+      .collect();
+    if kept.len() > MAX_ENTRIES {
+      kept.drain(..kept.len() - MAX_ENTRIES);
+    }
+    let purged = total - kept.len();
+    if purged == 0 {
+      return Ok(0);
+    }
+    let mut data = String::new();
+    for entry in &kept {
+      data.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+      data.push('\n');
+    }
+    atomic_file::write(&self.log_file(), data.as_bytes())?;
+    Ok(purged)
+  }
+
+  pub fn retention_days(&self) -> u32 {
+    fs::read_to_string(self.root.join(SETTINGS_FILE_NAME))
+      .ok()
+      .and_then(|s| serde_json::from_str::<HistorySettings>(&s).ok())
+      .map(|s| s.retention_days)
+      .unwrap_or(DEFAULT_RETENTION_DAYS)
+  }
+
+  pub fn set_retention_days(&self, retention_days: u32) -> Result<(), String> {
+    fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&HistorySettings { retention_days }).map_err(|e| e.to_string())?;
+    atomic_file::write(&self.root.join(SETTINGS_FILE_NAME), json.as_bytes())
+  }
+
+  fn log_file(&self) -> PathBuf {
+    self.root.join(LOG_FILE_NAME)
+  }
+
+  /// Записи в порядке дозаписи. Оборванная при сбое строка пропускается.
+  fn read(&self) -> Result<Vec<HistoryEntry>, String> {
+    let path = self.log_file();
+    let content = match fs::read_to_string(&path) {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(format!("read {:?}: {}", path, e)),
+    };
+    Ok(
+      content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+          Ok(entry) => Some(entry),
+          Err(e) => {
+            log::warn!("history: skipping bad line in {:?}: {}", path, e);
+            None
+          }
+        })
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DAY: u64 = 24 * 60 * 60 * 1000;
+
+  fn entry(at: u64, path: &str, source: HistorySource) -> HistoryEntry {
+    HistoryEntry {
+      at,
+      path: path.into(),
+      target: "both".into(),
+      source,
+      collection_id: Some("c1".into()),
+    }
+  }
+
+  #[test]
+  fn lists_newest_first_with_limit_and_since() {
+    let tmp = tempfile::tempdir().unwrap();
+    let history = History::new(tmp.path());
+    assert!(history.list(None, None).unwrap().is_empty());
+
+    history.append(&entry(1_000, "a", HistorySource::App)).unwrap();
+    history.append(&entry(3_000, "c", HistorySource::Desktop)).unwrap();
+    history.append(&entry(2_000, "b", HistorySource::Service)).unwrap();
+    // Оборванная последняя строка не ломает чтение
+    let mut file = OpenOptions::new().append(true).open(history.log_file()).unwrap();
+    file.write_all(b"{\"at\":4000,\"pa").unwrap();
+    history.append(&entry(500, "z", HistorySource::App)).unwrap();
+
+    let paths = |v: Vec<HistoryEntry>| v.into_iter().map(|e| e.path).collect::<Vec<_>>();
+    assert_eq!(paths(history.list(None, None).unwrap()), ["c", "b", "a", "z"]);
+    assert_eq!(paths(history.list(Some(2), None).unwrap()), ["c", "b"]);
+    assert_eq!(paths(history.list(None, Some(2_000)).unwrap()), ["c", "b"]);
+    assert_eq!(history.list(Some(1), None).unwrap()[0], entry(3_000, "c", HistorySource::Desktop));
+  }
+
+  #[test]
+  fn purge_applies_retention_days_and_entry_cap() {
+    let tmp = tempfile::tempdir().unwrap();
+    let history = History::new(tmp.path());
+    assert_eq!(history.retention_days(), DEFAULT_RETENTION_DAYS);
+    let now = 100 * DAY;
+    history.append(&entry(now - 91 * DAY, "old", HistorySource::App)).unwrap();
+    history.append(&entry(now - DAY, "new", HistorySource::App)).unwrap();
+    assert_eq!(history.purge_expired(now).unwrap(), 1);
+    assert_eq!(history.list(None, None).unwrap().len(), 1);
+    assert_eq!(history.purge_expired(now).unwrap(), 0);
+
+    history.set_retention_days(0).unwrap();
+    assert_eq!(history.retention_days(), 0);
+    for i in 0..MAX_ENTRIES as u64 {
+      history.append(&entry(now + i, "x", HistorySource::Service)).unwrap();
+    }
+    // Без срока хранения остаётся только лимит: уходят самые старые записи
+    assert_eq!(history.purge_expired(now).unwrap(), 1);
+    let entries = history.list(None, None).unwrap();
+    assert_eq!(entries.len(), MAX_ENTRIES);
+    assert!(entries.iter().all(|e| e.path == "x"));
+    assert_eq!(entries.last().unwrap().at, now);
+  }
+}
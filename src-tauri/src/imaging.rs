@@ -0,0 +1,464 @@
+//! Импорт изображений в коллекцию: декодирование, обрезка, масштабирование и кодирование в WebP
+//! выполняются в Rust, без canvas во WebView. Модуль не зависит от Tauri и проверяется обычными тестами.
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::blobs::BlobStore;
+use crate::collection::{self, CollectionItem, CropRect, NewCollectionItem, Size};
+use crate::crypto;
+use crate::error::AppError;
+use crate::paths;
+use crate::thumbnails;
+
+/// Качество WebP 0–100: как у прежнего импорта через `canvas.toBlob(..., 'image/webp', 0.95)`.
+const WEBP_QUALITY: f32 = 95.0;
+
+/// Результат обработки: WebP-байты и геометрия для метаданных элемента.
+#[derive(Debug)]
+pub struct Processed {
+  pub webp: Vec<u8>,
+  /// Размер исходного изображения с учётом EXIF-ориентации.
+  pub image: Size,
+  /// Фактически применённая обрезка (после приведения к границам изображения).
+  pub crop: CropRect,
+  /// Размер сохранённого файла.
+  pub output: Size,
+}
+
+/// Декодировать файл, обрезать и уменьшить до `target` (без увеличения), закодировать в WebP.
+/// Без `crop` берётся центральная область с пропорциями `target`, без обоих — изображение целиком.
+pub fn process(source: &Path, crop: Option<CropRect>, target: Option<Size>) -> Result<Processed, AppError> {
+  let mut img = decode(source)?;
+  let image = Size {
+    width: img.width(),
+    height: img.height(),
+  };
+  let crop = match (crop, target) {
+    (Some(crop), _) => clamp_crop(crop, image)
+      .ok_or_else(|| format!("Crop {:?} is outside of image {}x{}", crop, image.width, image.height))?,
+    (None, Some(target)) => centered_crop(image, target)?,
+    (None, None) => CropRect {
+      x: 0,
+      y: 0,
+      width: image.width,
+      height: image.height,
+    },
+  };
+  if crop != (CropRect { x: 0, y: 0, width: image.width, height: image.height }) {
+    img = img.crop_imm(crop.x, crop.y, crop.width, crop.height);
+  }
+
+  let cropped = Size {
+    width: crop.width,
+    height: crop.height,
+  };
+  let output = match target {
+    Some(target) => fit_within(cropped, target)?,
+    None => cropped,
+  };
+  if output != cropped {
+    img = img.resize_exact(output.width, output.height, FilterType::Lanczos3);
+  }
+
+  Ok(Processed {
+    webp: encode_webp(img)?,
+    image,
+    crop,
+    output,
+  })
+}
+
+/// Обработать изображение и добавить его элементом коллекции. Обычные коллекции хранят файл
+/// в общем хранилище (blobs/), зашифрованные — зашифрованным в своей папке.
+pub fn import(
+  base: &Path,
+  collection_id: &str,
+  source: &Path,
+  crop: Option<CropRect>,
+  target: Option<Size>,
+) -> Result<CollectionItem, AppError> {
+  let dir = paths::collection_dir(base, collection_id)?;
+  if !dir.is_dir() {
+    return Err(AppError::not_found("collection", collection_id));
+  }
+  let encrypted = collection::read_meta(&dir)?.encryption.is_some();
+  let processed = process(source, crop, target)?;
+  let name = webp_file_name(source);
+  if cfg!(debug_assertions) {
+    log::info!(
+      "import_image: {:?} {}x{} -> {} ({}x{}, {} bytes)",
+      source,
+      processed.image.width,
+      processed.image.height,
+      name,
+      processed.output.width,
+      processed.output.height,
+      processed.webp.len()
+    );
+  }
+  let new_item = |file: String, blob: Option<String>| NewCollectionItem {
+    file,
+    blob,
+    screen: target,
+    image: Some(processed.image),
+    crop: Some(processed.crop),
+    saved_as_crop: true,
+    created_at: None,
+  };
+
+  let item = if encrypted {
+    let data = processed.webp.as_slice();
+    let file = crypto::save_to_collection(&dir, collection_id, &name, &mut &*data, data.len() as u64)?;
+    collection::update_meta(&dir, |meta| meta.push_item(new_item(file.clone(), None))).inspect_err(|_| {
+      let _ = fs::remove_file(dir.join(&file));
+    })?
+  } else {
+    let store = BlobStore::new(base);
+    let blob = store.put_bytes(&processed.webp, &name)?;
+    store.retain(&blob)?;
+    collection::update_meta(&dir, |meta| meta.push_item(new_item(name.clone(), Some(blob.clone())))).inspect_err(|_| {
+      let _ = store.release(&blob);
+    })?
+  };
+
+  // Миниатюру для сетки создаём сразу; если не вышло — её создаст get_thumbnail при показе
+  if let Err(e) = thumbnails::get(base, &item.relative_path(collection_id), thumbnails::DEFAULT_SIZE) {
+    log::warn!("thumbnail for {}: {}", item.file, e);
+  }
+  Ok(item)
+}
+
+/// Центральная область изображения с пропорциями `aspect`, максимально возможного размера.
+pub fn centered_crop(image: Size, aspect: Size) -> Result<CropRect, String> {
+  if aspect.width == 0 || aspect.height == 0 {
+    return Err(format!("Invalid target size {}x{}", aspect.width, aspect.height));
+  }
+  let (iw, ih) = (image.width as u64, image.height as u64);
+  let (aw, ah) = (aspect.width as u64, aspect.height as u64);
+  let (width, height) = if iw * ah <= ih * aw {
+    (iw, (iw * ah / aw).max(1))
+  } else {
+    ((ih * aw / ah).max(1), ih)
+  };
+  Ok(CropRect {
+    x: ((iw - width) / 2) as u32,
+    y: ((ih - height) / 2) as u32,
+    width: width as u32,
+    height: height as u32,
+  })
+}
+
+/// Размер, вписанный в `bounds` с сохранением пропорций. Меньшие изображения не увеличиваются.
+pub fn fit_within(size: Size, bounds: Size) -> Result<Size, String> {
+  if bounds.width == 0 || bounds.height == 0 {
+    return Err(format!("Invalid target size {}x{}", bounds.width, bounds.height));
+  }
+  if size.width <= bounds.width && size.height <= bounds.height {
+    return Ok(size);
+  }
+  let (w, h) = (size.width as u64, size.height as u64);
+  let (bw, bh) = (bounds.width as u64, bounds.height as u64);
+  let (width, height) = if w * bh >= h * bw {
+    (bw, (h * bw / w).max(1))
+  } else {
+    ((w * bh / h).max(1), bh)
+  };
+  Ok(Size {
+    width: width as u32,
+    height: height as u32,
+  })
+}
+
+/// Имя сохранённого файла: имя исходника с расширением .webp. `_`-префикс зарезервирован за служебными файлами.
+pub fn webp_file_name(source: &Path) -> String {
+  let stem = source
+    .file_stem()
+    .and_then(|s| s.to_str())
+    .map(|s| s.trim_start_matches(['_', '.']))
+    .unwrap_or_default();
+  if stem.is_empty() {
+    format!("image_{}.webp", collection::now_millis())
+  } else {
+    format!("{}.webp", stem)
+  }
+}
+
+/// Затемнить изображение: `brightness` — оставляемая яркость в процентах (100 — без изменений).
+pub fn dim(img: DynamicImage, brightness: u8) -> DynamicImage {
+  let factor = u16::from(brightness.min(100));
+  let scale = |c: &mut u8| *c = (u16::from(*c) * factor / 100) as u8;
+  if img.color().has_alpha() {
+    let mut buf = img.into_rgba8();
+    for p in buf.pixels_mut() {
+      p.0[..3].iter_mut().for_each(scale);
+    }
+    DynamicImage::ImageRgba8(buf)
+  } else {
+    let mut buf = img.into_rgb8();
+    for p in buf.pixels_mut() {
+      p.0.iter_mut().for_each(scale);
+    }
+    DynamicImage::ImageRgb8(buf)
+  }
+}
+
+/// Скопировать изображения коллекции `source_id` в пустую коллекцию `dest_id` затемнёнными —
+/// ночная пара к дневной коллекции для расписания по Солнцу. Геометрия элементов сохраняется,
+/// порядок тоже. Нечитаемые изображения пропускаются. Возвращает число скопированных.
+pub fn dim_collection(base: &Path, source_id: &str, dest_id: &str, brightness: u8) -> Result<usize, String> {
+  if brightness == 0 || brightness >= 100 {
+    return Err(format!("Brightness must be between 1 and 99, got {}", brightness));
+  }
+  let source = collection::read_meta(&paths::collection_dir(base, source_id)?)?;
+  if source.encryption.is_some() {
+    return Err(format!("Collection '{}' is encrypted", source_id));
+  }
+  let dest_dir = paths::collection_dir(base, dest_id)?;
+  if collection::read_meta(&dest_dir)?.encryption.is_some() {
+    return Err(format!("Collection '{}' is encrypted", dest_id));
+  }
+  let store = BlobStore::new(base);
+  let mut items = source.items;
+  items.sort_by_key(|item| item.order);
+  let mut copied = 0;
+  for item in items {
+    let webp = paths::resolve(base, &item.relative_path(source_id))
+      .map_err(String::from)
+      .and_then(|path| fs::read(&path).map_err(|e| format!("read {:?}: {}", path, e)))
+      .and_then(|data| decode_bytes(&data))
+      .and_then(|img| encode_webp(dim(img, brightness)));
+    let webp = match webp {
+      Ok(webp) => webp,
+      Err(e) => {
+        log::warn!("dim_collection: skipping {} in '{}': {}", item.file, source_id, e);
+        continue;
+      }
+    };
+    let name = webp_file_name(Path::new(&item.file));
+    let blob = store.put_bytes(&webp, &name)?;
+    store.retain(&blob)?;
+    let new_item = NewCollectionItem {
+      file: name,
+      blob: Some(blob.clone()),
+      screen: item.screen,
+      image: item.image,
+      crop: item.crop,
+      saved_as_crop: item.saved_as_crop,
+      created_at: None,
+    };
+    let pushed = collection::update_meta(&dest_dir, |meta| meta.push_item(new_item)).inspect_err(|_| {
+      let _ = store.release(&blob);
+    })?;
+    if let Err(e) = thumbnails::get(base, &pushed.relative_path(dest_id), thumbnails::DEFAULT_SIZE) {
+      log::warn!("thumbnail for {}: {}", pushed.file, e);
+    }
+    copied += 1;
+  }
+  Ok(copied)
+}
+
+/// Декодировать файл с определением формата по содержимому и поворотом по EXIF — так же,
+/// как изображение показывает WebView, в координатах которого задан crop.
+fn decode(source: &Path) -> Result<DynamicImage, AppError> {
+  let data = fs::read(source).map_err(|e| AppError::io(source, e))?;
+  decode_bytes(&data).map_err(|e| AppError::decode_failed(source.to_string_lossy(), e))
+}
+
+/// Декодировать изображение из памяти (например, расшифрованный файл) с поворотом по EXIF.
+pub fn decode_bytes(data: &[u8]) -> Result<DynamicImage, String> {
+  let mut decoder = ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .map_err(|e| format!("read image: {}", e))?
+    .into_decoder()
+    .map_err(|e| format!("decode image: {}", e))?;
+  let orientation = decoder.orientation().map_err(|e| format!("decode image: {}", e))?;
+  let mut img = DynamicImage::from_decoder(decoder).map_err(|e| format!("decode image: {}", e))?;
+  img.apply_orientation(orientation);
+  Ok(img)
+}
+
+/// Обрезка, приведённая к границам изображения; `None`, если пересечения нет.
+fn clamp_crop(crop: CropRect, image: Size) -> Option<CropRect> {
+  let x = crop.x.min(image.width);
+  let y = crop.y.min(image.height);
+  let width = crop.width.min(image.width - x);
+  let height = crop.height.min(image.height - y);
+  (width > 0 && height > 0).then_some(CropRect { x, y, width, height })
+}
+
+/// WebP с потерями (libwebp): lossless-кодировщик крейта image раздувает фото в разы.
+fn encode_webp(img: DynamicImage) -> Result<Vec<u8>, String> {
+  let (width, height) = (img.width(), img.height());
+  let encoded = if img.color().has_alpha() {
+    webp::Encoder::from_rgba(img.into_rgba8().as_raw(), width, height).encode_simple(false, WEBP_QUALITY)
+  } else {
+    webp::Encoder::from_rgb(img.into_rgb8().as_raw(), width, height).encode_simple(false, WEBP_QUALITY)
+  };
+  let out = encoded.map_err(|e| format!("encode webp: {:?}", e))?;
+  Ok(out.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::{ImageFormat, Rgb, RgbImage};
+  use std::path::PathBuf;
+
+  fn size(width: u32, height: u32) -> Size {
+    Size { width, height }
+  }
+
+  /// PNG 400x200: левая половина красная, правая синяя.
+  fn write_png(dir: &Path, name: &str) -> PathBuf {
+    let img = RgbImage::from_fn(400, 200, |x, _| if x < 200 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+    let path = dir.join(name);
+    img.save_with_format(&path, ImageFormat::Png).unwrap();
+    path
+  }
+
+  /// WebP с потерями: цвет сплошной заливки сохраняется с точностью до нескольких единиц.
+  fn assert_near(pixel: &Rgb<u8>, expected: [u8; 3]) {
+    let close = pixel.0.iter().zip(expected).all(|(a, b)| a.abs_diff(b) <= 6);
+    assert!(close, "{:?} != {:?}", pixel.0, expected);
+  }
+
+  /// «Фотография»: плавные градиенты с шумом сенсора, JPEG качества 95 — как снимок с камеры.
+  fn write_photo(dir: &Path, name: &str) -> PathBuf {
+    let mut noise = 0x2545_f491_u32;
+    let img = RgbImage::from_fn(1200, 800, |x, y| {
+      noise ^= noise << 13;
+      noise ^= noise >> 17;
+      noise ^= noise << 5;
+      let grain = (noise % 24) as i32 - 12;
+      let channel = |base: i32| (base + grain).clamp(0, 255) as u8;
+      Rgb([channel((x / 5) as i32), channel((y / 4) as i32 + 20), channel(((x + y) / 9) as i32)])
+    });
+    let path = dir.join(name);
+    let mut out = fs::File::create(&path).unwrap();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 95).encode_image(&img).unwrap();
+    path
+  }
+
+  #[test]
+  fn geometry_helpers() {
+    assert_eq!(
+      centered_crop(size(400, 200), size(1080, 1920)).unwrap(),
+      CropRect { x: 144, y: 0, width: 112, height: 200 }
+    );
+    assert_eq!(
+      centered_crop(size(400, 200), size(4, 1)).unwrap(),
+      CropRect { x: 0, y: 50, width: 400, height: 100 }
+    );
+    assert_eq!(fit_within(size(4000, 3000), size(1920, 1080)).unwrap(), size(1440, 1080));
+    assert_eq!(fit_within(size(100, 50), size(1920, 1080)).unwrap(), size(100, 50));
+    assert!(fit_within(size(100, 50), size(0, 1080)).is_err());
+    assert_eq!(
+      clamp_crop(CropRect { x: 350, y: 150, width: 100, height: 100 }, size(400, 200)),
+      Some(CropRect { x: 350, y: 150, width: 50, height: 50 })
+    );
+    assert_eq!(clamp_crop(CropRect { x: 400, y: 0, width: 10, height: 10 }, size(400, 200)), None);
+    assert_eq!(webp_file_name(Path::new("/tmp/Photo.JPG")), "Photo.webp");
+    assert_eq!(webp_file_name(Path::new("/tmp/__meta.png")), "meta.webp");
+  }
+
+  #[test]
+  fn process_crops_and_scales_down() {
+    let tmp = tempfile::tempdir().unwrap();
+    let src = write_png(tmp.path(), "src.png");
+
+    let crop = CropRect { x: 200, y: 0, width: 200, height: 200 };
+    let out = process(&src, Some(crop), Some(size(100, 100))).unwrap();
+    assert_eq!(out.image, size(400, 200));
+    assert_eq!(out.crop, crop);
+    assert_eq!(out.output, size(100, 100));
+    let decoded = image::load_from_memory_with_format(&out.webp, ImageFormat::WebP).unwrap().into_rgb8();
+    assert_eq!(decoded.dimensions(), (100, 100));
+    assert_near(decoded.get_pixel(50, 50), [0, 0, 255]);
+
+    // Без crop — центральная область по пропорциям экрана; маленькие изображения не растягиваются
+    let out = process(&src, None, Some(size(1000, 1000))).unwrap();
+    assert_eq!(out.crop, CropRect { x: 100, y: 0, width: 200, height: 200 });
+    assert_eq!(out.output, size(200, 200));
+
+    assert!(process(&src, Some(CropRect { x: 500, y: 0, width: 10, height: 10 }), None).is_err());
+    fs::write(tmp.path().join("bad.png"), b"not an image").unwrap();
+    let bad = process(&tmp.path().join("bad.png"), None, None).unwrap_err();
+    assert!(matches!(bad, AppError::DecodeFailed { .. }), "{:?}", bad);
+  }
+
+  #[test]
+  fn import_appends_item_to_meta() {
+    let (tmp, base) = collection::test_base("c1", None, &[]);
+    let src = write_png(tmp.path(), "photo.png");
+
+    let item = import(&base, "c1", &src, None, Some(size(100, 100))).unwrap();
+    assert_eq!(item.file, "photo.webp");
+    assert_eq!(item.screen, Some(size(100, 100)));
+    assert_eq!(item.image, Some(size(400, 200)));
+    assert!(item.saved_as_crop);
+    let store = BlobStore::new(&base);
+    let data = fs::read(store.path(item.blob.as_ref().unwrap()).unwrap()).unwrap();
+    let decoded = image::load_from_memory(&data).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (100, 100));
+    assert_eq!(collection::read_meta(&base.join("collections/c1")).unwrap().items, vec![item]);
+
+    assert_eq!(import(&base, "missing", &src, None, None).unwrap_err(), AppError::not_found("collection", "missing"));
+  }
+
+  #[test]
+  fn imported_photo_is_smaller_than_source() {
+    let (tmp, base) = collection::test_base("c1", None, &[]);
+    let src = write_photo(tmp.path(), "photo.jpg");
+
+    let item = import(&base, "c1", &src, None, Some(size(1200, 800))).unwrap();
+    assert_eq!(item.image, Some(size(1200, 800)));
+    let stored = fs::metadata(BlobStore::new(&base).path(item.blob.as_ref().unwrap()).unwrap()).unwrap().len();
+    let source = fs::metadata(&src).unwrap().len();
+    assert!(stored < source, "webp {} bytes, source {} bytes", stored, source);
+  }
+
+  #[test]
+  fn import_into_encrypted_collection_stores_ciphertext() {
+    let info = crypto::EncryptionInfo::new("secret").unwrap();
+    // Сессии глобальные — id не должен пересекаться с другими тестами
+    let id = format!("imaging-test-{}", std::process::id());
+    let (tmp, base) = collection::test_base(&id, Some(info.clone()), &[]);
+    let src = write_png(tmp.path(), "photo.png");
+
+    assert!(import(&base, &id, &src, None, None).unwrap_err().to_string().contains("locked"));
+    crypto::unlock(&id, &info, "secret").unwrap();
+    let item = import(&base, &id, &src, None, None).unwrap();
+    assert_eq!(item.blob, None);
+    let stored = fs::read(base.join("collections").join(&id).join(&item.file)).unwrap();
+    assert!(image::load_from_memory(&stored).is_err());
+    let mut plain = Vec::new();
+    crypto::with_session(&id, |session| crypto::decrypt(session, &mut stored.as_slice(), &mut plain)).unwrap();
+    assert_eq!(image::load_from_memory(&plain).unwrap().width(), 400);
+    crypto::lock(&id);
+  }
+
+  #[test]
+  fn dim_collection_copies_darkened_items() {
+    let (tmp, base) = collection::test_base("day", None, &[]);
+    let night = base.join("collections/night");
+    fs::create_dir_all(&night).unwrap();
+    collection::write_meta(&night, &collection::CollectionMeta::new("night".into(), "Night".into(), 1)).unwrap();
+    let src = write_png(tmp.path(), "photo.png");
+    let item = import(&base, "day", &src, None, None).unwrap();
+
+    assert!(dim_collection(&base, "day", "night", 100).is_err());
+    assert_eq!(dim_collection(&base, "day", "night", 50).unwrap(), 1);
+    let copy = collection::read_meta(&night).unwrap().items.remove(0);
+    assert_eq!(copy.file, item.file);
+    assert_eq!((copy.image, copy.crop), (item.image, item.crop));
+    assert_ne!(copy.blob, item.blob);
+    let data = fs::read(BlobStore::new(&base).path(copy.blob.as_ref().unwrap()).unwrap()).unwrap();
+    let decoded = image::load_from_memory(&data).unwrap().into_rgb8();
+    assert_near(decoded.get_pixel(0, 0), [127, 0, 0]);
+    assert_near(decoded.get_pixel(399, 0), [0, 0, 127]);
+  }
+}
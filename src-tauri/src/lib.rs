@@ -1,63 +1,96 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use tauri::Manager;
 
+mod archive;
+mod atomic_file;
+mod blobs;
+mod collection;
+mod crypto;
+mod error;
+mod events;
+mod history;
+mod imaging;
+mod paths;
+mod playlist;
+mod rotation;
+mod rotation_loop;
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+mod rotation_prefs;
+mod rotation_store;
+mod schedule;
+mod solar;
+mod thumbnails;
+mod trash;
+mod upload;
+mod wallpaper;
+
+use error::AppError;
+use collection::{CollectionDetails, CollectionItem, CollectionMeta, NewCollectionItem};
+use rotation_loop::{RotationLoop, RotationSlot};
+use schedule::Schedule;
+
 #[cfg(target_os = "android")]
 use jni::objects::{JObject, JString};
 #[cfg(target_os = "android")]
 use ndk_context::android_context;
 
+/// Приложение для вызовов из WallpaperRotationService: через него сервис сообщает интерфейсу о сменах
+/// обоев. Пусто, если процесс запущен только ради сервиса и интерфейса нет.
+#[cfg(target_os = "android")]
+static APP_HANDLE: std::sync::OnceLock<tauri::AppHandle> = std::sync::OnceLock::new();
+
 /// Базовая папка для файлов приложения. Относительные пути хранятся от неё.
-fn files_base_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn files_base_dir(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
   #[cfg(target_os = "android")]
   {
     app
       .path()
       .picture_dir()
-      .map_err(|e: tauri::Error| e.to_string())
+      .map_err(|e: tauri::Error| AppError::from(e.to_string()))
   }
   #[cfg(not(target_os = "android"))]
   {
     app
       .path()
       .app_data_dir()
-      .map_err(|e: tauri::Error| e.to_string())
+      .map_err(|e: tauri::Error| AppError::from(e.to_string()))
       .map(|p| p.join("files"))
   }
 }
 
-#[cfg(target_os = "android")]
-fn absolute_path_from_relative(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
-  let base = files_base_dir(app)?;
-  let p = PathBuf::from(path);
-  Ok(if p.is_absolute() { p } else { base.join(path) })
+/// Путь от фронтенда (относительный от base или абсолютный внутри неё) → проверенный полный путь.
+fn resolve_app_path(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, AppError> {
+  paths::resolve(&files_base_dir(app)?, path)
 }
 
 #[cfg(target_os = "android")]
-fn set_wallpaper_android(app: &tauri::AppHandle, path: String) -> Result<(), String> {
+fn set_wallpaper_android(app: &tauri::AppHandle, path: String) -> Result<(), AppError> {
   let ctx = android_context();
   let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM error: {}", e))?
+    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM error: {}", e)))?
   };
   let mut env = vm
     .attach_current_thread()
-    .map_err(|e| format!("JNI attach thread: {}", e))?;
+    .map_err(|e| AppError::jni(format!("JNI attach thread: {}", e)))?;
   let context = unsafe { JObject::from_raw(ctx.context() as *mut _) };
 
-  let full = absolute_path_from_relative(app, &path)?;
+  let full = resolve_app_path(app, &path)?;
   let full_str = full
     .to_str()
-    .ok_or_else(|| "Invalid path".to_string())?
+    .ok_or_else(|| AppError::path_not_allowed(&path))?
     .to_string();
 
   let path_j: JString = env
     .new_string(full_str)
-    .map_err(|e| format!("JNI new_string: {}", e))?;
+    .map_err(|e| AppError::jni(format!("JNI new_string: {}", e)))?;
 
   // Bitmap bitmap = BitmapFactory.decodeFile(path)
   let bitmap_factory = env
     .find_class("android/graphics/BitmapFactory")
-    .map_err(|e| format!("Find BitmapFactory: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find BitmapFactory: {}", e)))?;
   let bitmap = env
     .call_static_method(
       bitmap_factory,
@@ -65,18 +98,18 @@ fn set_wallpaper_android(app: &tauri::AppHandle, path: String) -> Result<(), Str
       "(Ljava/lang/String;)Landroid/graphics/Bitmap;",
       &[jni::objects::JValue::Object(&path_j)],
     )
-    .map_err(|e| format!("BitmapFactory.decodeFile: {}", e))?
+    .map_err(|e| AppError::jni(format!("BitmapFactory.decodeFile: {}", e)))?
     .l()
-    .map_err(|e| format!("Get bitmap object: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get bitmap object: {}", e)))?;
 
-  if env.is_same_object(&bitmap, JObject::null()).map_err(|e| e.to_string())? {
-    return Err("Failed to decode image or file not found".to_string());
+  if env.is_same_object(&bitmap, JObject::null()).map_err(AppError::jni)? {
+    return Err(AppError::decode_failed(path, "BitmapFactory.decodeFile returned null"));
   }
 
   // WallpaperManager wm = WallpaperManager.getInstance(context)
   let wm_class = env
     .find_class("android/app/WallpaperManager")
-    .map_err(|e| format!("Find WallpaperManager: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find WallpaperManager: {}", e)))?;
   let wm_obj = env
     .call_static_method(
       wm_class,
@@ -84,9 +117,9 @@ fn set_wallpaper_android(app: &tauri::AppHandle, path: String) -> Result<(), Str
       "(Landroid/content/Context;)Landroid/app/WallpaperManager;",
       &[jni::objects::JValue::Object(&context)],
     )
-    .map_err(|e| format!("WallpaperManager.getInstance: {}", e))?
+    .map_err(|e| AppError::jni(format!("WallpaperManager.getInstance: {}", e)))?
     .l()
-    .map_err(|e| format!("Get WallpaperManager object: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get WallpaperManager object: {}", e)))?;
 
   // wm.setBitmap(bitmap)
   env
@@ -96,7 +129,7 @@ fn set_wallpaper_android(app: &tauri::AppHandle, path: String) -> Result<(), Str
       "(Landroid/graphics/Bitmap;)V",
       &[jni::objects::JValue::Object(&bitmap)],
     )
-    .map_err(|e| format!("WallpaperManager.setBitmap: {}", e))?;
+    .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap: {}", e)))?;
 
   Ok(())
 }
@@ -106,30 +139,30 @@ fn set_wallpaper_android_with_target(
   app: &tauri::AppHandle,
   path: String,
   target: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
   let ctx = android_context();
   let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM error: {}", e))?
+    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM error: {}", e)))?
   };
   let mut env = vm
     .attach_current_thread()
-    .map_err(|e| format!("JNI attach thread: {}", e))?;
+    .map_err(|e| AppError::jni(format!("JNI attach thread: {}", e)))?;
   let context = unsafe { JObject::from_raw(ctx.context() as *mut _) };
 
-  let full = absolute_path_from_relative(app, &path)?;
+  let full = resolve_app_path(app, &path)?;
   let full_str = full
     .to_str()
-    .ok_or_else(|| "Invalid path".to_string())?
+    .ok_or_else(|| AppError::path_not_allowed(&path))?
     .to_string();
 
   let path_j: JString = env
     .new_string(full_str)
-    .map_err(|e| format!("JNI new_string: {}", e))?;
+    .map_err(|e| AppError::jni(format!("JNI new_string: {}", e)))?;
 
   // Bitmap bitmap = BitmapFactory.decodeFile(path)
   let bitmap_factory = env
     .find_class("android/graphics/BitmapFactory")
-    .map_err(|e| format!("Find BitmapFactory: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find BitmapFactory: {}", e)))?;
   let bitmap = env
     .call_static_method(
       bitmap_factory,
@@ -137,18 +170,18 @@ fn set_wallpaper_android_with_target(
       "(Ljava/lang/String;)Landroid/graphics/Bitmap;",
       &[jni::objects::JValue::Object(&path_j)],
     )
-    .map_err(|e| format!("BitmapFactory.decodeFile: {}", e))?
+    .map_err(|e| AppError::jni(format!("BitmapFactory.decodeFile: {}", e)))?
     .l()
-    .map_err(|e| format!("Get bitmap object: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get bitmap object: {}", e)))?;
 
-  if env.is_same_object(&bitmap, JObject::null()).map_err(|e| e.to_string())? {
-    return Err("Failed to decode image or file not found".to_string());
+  if env.is_same_object(&bitmap, JObject::null()).map_err(AppError::jni)? {
+    return Err(AppError::decode_failed(path, "BitmapFactory.decodeFile returned null"));
   }
 
   // WallpaperManager wm = WallpaperManager.getInstance(context)
   let wm_class = env
     .find_class("android/app/WallpaperManager")
-    .map_err(|e| format!("Find WallpaperManager: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find WallpaperManager: {}", e)))?;
   let wm_obj = env
     .call_static_method(
       wm_class,
@@ -156,24 +189,24 @@ fn set_wallpaper_android_with_target(
       "(Landroid/content/Context;)Landroid/app/WallpaperManager;",
       &[jni::objects::JValue::Object(&context)],
     )
-    .map_err(|e| format!("WallpaperManager.getInstance: {}", e))?
+    .map_err(|e| AppError::jni(format!("WallpaperManager.getInstance: {}", e)))?
     .l()
-    .map_err(|e| format!("Get WallpaperManager object: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get WallpaperManager object: {}", e)))?;
 
   // int SDK_INT = android.os.Build.VERSION.SDK_INT
   let ver_class = env
     .find_class("android/os/Build$VERSION")
-    .map_err(|e| format!("Find Build.VERSION: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find Build.VERSION: {}", e)))?;
   let sdk_int = env
     .get_static_field(ver_class, "SDK_INT", "I")
-    .map_err(|e| format!("Get SDK_INT: {}", e))?
+    .map_err(|e| AppError::jni(format!("Get SDK_INT: {}", e)))?
     .i()
-    .map_err(|e| format!("Read SDK_INT: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Read SDK_INT: {}", e)))?;
 
   // Flags (API >= 24)
   let wm_class_for_flags = env
     .find_class("android/app/WallpaperManager")
-    .map_err(|e| format!("Find WallpaperManager for flags: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find WallpaperManager for flags: {}", e)))?;
   let flag_system = env
     .get_static_field(&wm_class_for_flags, "FLAG_SYSTEM", "I")
     .ok()
@@ -201,7 +234,7 @@ fn set_wallpaper_android_with_target(
             jni::objects::JValue::Int(flag_system),
           ],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap(system): {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap(system): {}", e)))?;
     } else {
       env
         .call_method(
@@ -210,7 +243,7 @@ fn set_wallpaper_android_with_target(
           "(Landroid/graphics/Bitmap;)V",
           &[jni::objects::JValue::Object(&bitmap)],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap: {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap: {}", e)))?;
     }
   } else if which == "lock" {
     if sdk_int >= 24 {
@@ -227,9 +260,10 @@ fn set_wallpaper_android_with_target(
             jni::objects::JValue::Int(flag_lock),
           ],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap(lock): {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap(lock): {}", e)))?;
     } else {
-      return Err("Lock screen wallpaper requires Android 7.0+".to_string());
+      // Отдельные обои экрана блокировки — с Android 7.0
+      return Err(AppError::Unsupported { feature: "lock_screen" });
     }
   } else {
     // both: set home, then lock if possible
@@ -247,7 +281,7 @@ fn set_wallpaper_android_with_target(
             jni::objects::JValue::Int(flag_system),
           ],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap(system): {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap(system): {}", e)))?;
       env
         .call_method(
           &wm_obj,
@@ -260,7 +294,7 @@ fn set_wallpaper_android_with_target(
             jni::objects::JValue::Int(flag_lock),
           ],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap(lock): {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap(lock): {}", e)))?;
     } else {
       env
         .call_method(
@@ -269,7 +303,7 @@ fn set_wallpaper_android_with_target(
           "(Landroid/graphics/Bitmap;)V",
           &[jni::objects::JValue::Object(&bitmap)],
         )
-        .map_err(|e| format!("WallpaperManager.setBitmap: {}", e))?;
+        .map_err(|e| AppError::jni(format!("WallpaperManager.setBitmap: {}", e)))?;
     }
   }
 
@@ -277,391 +311,813 @@ fn set_wallpaper_android_with_target(
 }
 
 #[tauri::command]
-fn set_device_wallpaper(app: tauri::AppHandle, path: String) -> Result<(), String> {
+fn set_device_wallpaper(app: tauri::AppHandle, path: String) -> Result<(), AppError> {
   #[cfg(target_os = "android")]
-  {
-    return set_wallpaper_android(&app, path);
-  }
+  set_wallpaper_android(&app, path.clone())?;
   #[cfg(not(target_os = "android"))]
-  {
-    Err("Only supported on Android".to_string())
-  }
+  set_desktop_wallpaper(&app, &path, "both")?;
+  record_history(&app, &path, "both", None, None);
+  Ok(())
 }
 
 #[tauri::command]
-fn set_device_wallpaper_target(app: tauri::AppHandle, path: String, target: String) -> Result<(), String> {
+fn set_device_wallpaper_target(
+  app: tauri::AppHandle,
+  path: String,
+  target: String,
+  collection_id: Option<String>,
+) -> Result<(), AppError> {
+  apply_wallpaper(&app, &path, &target)?;
+  record_history(&app, &path, &target, collection_id, None);
+  Ok(())
+}
+
+/// Поставить обои (путь относительно base) на both / home / lock.
+fn apply_wallpaper(app: &tauri::AppHandle, path: &str, target: &str) -> Result<(), AppError> {
   #[cfg(target_os = "android")]
   {
-    return set_wallpaper_android_with_target(&app, path, target);
+    set_wallpaper_android_with_target(app, path.to_string(), target.to_string())
   }
   #[cfg(not(target_os = "android"))]
   {
-    Err("Only supported on Android".to_string())
+    set_desktop_wallpaper(app, path, target)
   }
 }
 
-#[cfg(target_os = "android")]
-fn start_wallpaper_rotation_service_android(
-  interval_minutes: u32,
-  target: String,
-  rotation_index: u32,
-  last_change_at: i64,
-  sequence: Vec<String>,
-) -> Result<(), String> {
-  use jni::objects::JValue;
-  const PREFS_NAME: &str = "chrono_wall_rotation";
-  const DELIM: char = '\u{0000}';
-  let sequence_str = sequence.join(DELIM.to_string().as_str());
-
-  let ctx = ndk_context::android_context();
-  let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM: {}", e))?
+/// Записать смену обоев из приложения в историю и сообщить о ней интерфейсу. `rotation` — слот
+/// и его состояние, если обои сменила ротация. Ошибка записи не отменяет уже поставленные обои.
+fn record_history(
+  app: &tauri::AppHandle,
+  path: &str,
+  target: &str,
+  collection_id: Option<String>,
+  rotation: Option<(RotationSlot, rotation::RotationState)>,
+) {
+  let entry = history::HistoryEntry {
+    at: collection::now_millis(),
+    path: path.to_string(),
+    target: target.to_string(),
+    source: history::HistorySource::App,
+    collection_id,
   };
-  let mut env = vm.attach_current_thread().map_err(|e| format!("JNI attach: {}", e))?;
-  let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
-
-  let _prefs_class = env.find_class("android/content/Context").map_err(|e| format!("Find Context: {}", e))?;
-  let mode_private = 0i32;
-  let prefs_name_j = env.new_string(PREFS_NAME).map_err(|e| format!("new_string prefs: {}", e))?;
-  let prefs = env
-    .call_method(
-      &context,
-      "getSharedPreferences",
-      "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-      &[JValue::Object(&prefs_name_j).into(), JValue::Int(mode_private).into()],
-    )
-    .map_err(|e| format!("getSharedPreferences: {}", e))?
-    .l()
-    .map_err(|e| format!("SharedPreferences: {}", e))?;
-
-  let _editor_class = env.find_class("android/content/SharedPreferences$Editor").map_err(|e| format!("Find Editor: {}", e))?;
-  let editor = env
-    .call_method(&prefs, "edit", "()Landroid/content/SharedPreferences$Editor;", &[])
-    .map_err(|e| format!("edit: {}", e))?
-    .l()
-    .map_err(|e| format!("Editor: {}", e))?;
-
-  let running_j = env.new_string("running").map_err(|e| format!("key running: {}", e))?;
-  env.call_method(&editor, "putBoolean", "(Ljava/lang/String;Z)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&running_j).into(),
-    JValue::Bool(1).into(),
-  ])
-  .map_err(|e| format!("putBoolean: {}", e))?;
+  if let Err(e) = files_base_dir(app).and_then(|base| Ok(history::History::new(&base).append(&entry)?)) {
+    log::warn!("history: {}", e);
+  }
+  let (slot, state) = rotation.unzip();
+  events::wallpaper_changed(app, events::WallpaperChanged { entry, slot, state });
+}
 
-  let key_interval = env.new_string("interval_minutes").map_err(|e| format!("key interval: {}", e))?;
-  env.call_method(&editor, "putInt", "(Ljava/lang/String;I)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_interval).into(),
-    JValue::Int(interval_minutes as i32).into(),
-  ])
-  .map_err(|e| format!("putInt interval: {}", e))?;
+/// Настройки обоев на ПК: окружение рабочего стола, выбранное вручную.
+fn wallpaper_settings_file(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+  app
+    .path()
+    .app_data_dir()
+    .map_err(|e: tauri::Error| AppError::from(e.to_string()))
+    .map(|p| p.join(wallpaper::SETTINGS_FILE_NAME))
+}
 
-  let key_target = env.new_string("target").map_err(|e| format!("key target: {}", e))?;
-  let target_j = env.new_string(&target).map_err(|e| format!("target str: {}", e))?;
-  env.call_method(&editor, "putString", "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_target).into(),
-    JValue::Object(&target_j).into(),
-  ])
-  .map_err(|e| format!("putString target: {}", e))?;
+#[cfg(not(target_os = "android"))]
+fn set_desktop_wallpaper(app: &tauri::AppHandle, path: &str, target: &str) -> Result<(), AppError> {
+  let backend = wallpaper::backend_override(&wallpaper_settings_file(app)?);
+  wallpaper::set(&resolve_app_path(app, path)?, target, backend.as_deref(), wallpaper::Tools::from_env())
+}
 
-  let key_idx = env.new_string("rotation_index").map_err(|e| format!("key idx: {}", e))?;
-  env.call_method(&editor, "putInt", "(Ljava/lang/String;I)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_idx).into(),
-    JValue::Int(rotation_index as i32).into(),
-  ])
-  .map_err(|e| format!("putInt idx: {}", e))?;
+#[tauri::command]
+fn get_wallpaper_backend(app: tauri::AppHandle) -> Result<wallpaper::BackendInfo, AppError> {
+  if cfg!(target_os = "android") {
+    return Ok(wallpaper::BackendInfo {
+      supports_lock: true,
+      ..Default::default()
+    });
+  }
+  Ok(wallpaper::info(&wallpaper_settings_file(&app)?))
+}
 
-  let key_last = env.new_string("last_change_at").map_err(|e| format!("key last: {}", e))?;
-  env.call_method(&editor, "putLong", "(Ljava/lang/String;J)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_last).into(),
-    JValue::Long(last_change_at).into(),
-  ])
-  .map_err(|e| format!("putLong: {}", e))?;
+/// Выбрать окружение вручную; None — вернуть автоопределение.
+#[tauri::command]
+fn set_wallpaper_backend(app: tauri::AppHandle, backend: Option<String>) -> Result<(), AppError> {
+  if cfg!(target_os = "android") {
+    return Err(AppError::Unsupported { feature: "wallpaper_backend" });
+  }
+  Ok(wallpaper::set_backend_override(&wallpaper_settings_file(&app)?, backend)?)
+}
 
-  let key_seq = env.new_string("sequence").map_err(|e| format!("key sequence: {}", e))?;
-  let seq_j = env.new_string(&sequence_str).map_err(|e| format!("sequence str: {}", e))?;
-  env.call_method(&editor, "putString", "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_seq).into(),
-    JValue::Object(&seq_j).into(),
-  ])
-  .map_err(|e| format!("putString sequence: {}", e))?;
+/// Запустить WallpaperRotationService для слота: он заведёт будильник по сроку из файла состояния.
+#[cfg(target_os = "android")]
+fn start_wallpaper_rotation_service_android(slot: RotationSlot) -> Result<(), AppError> {
+  use jni::objects::JValue;
 
-  env.call_method(&editor, "apply", "()V", &[]).map_err(|e| format!("apply: {}", e))?;
+  let ctx = ndk_context::android_context();
+  let vm = unsafe {
+    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM: {}", e)))?
+  };
+  let mut env = vm.attach_current_thread().map_err(|e| AppError::jni(format!("JNI attach: {}", e)))?;
+  let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
 
-  let intent_class = env.find_class("android/content/Intent").map_err(|e| format!("Find Intent: {}", e))?;
-  let service_class = env.find_class("ru/qugor/chronowall/WallpaperRotationService").map_err(|e| format!("Find Service: {}", e))?;
+  let intent_class = env.find_class("android/content/Intent").map_err(|e| AppError::jni(format!("Find Intent: {}", e)))?;
+  let service_class = env.find_class("ru/qugor/chronowall/WallpaperRotationService").map_err(|e| AppError::jni(format!("Find Service: {}", e)))?;
   let intent = env
     .new_object(intent_class, "(Landroid/content/Context;Ljava/lang/Class;)V", &[
       JValue::Object(&context).into(),
       JValue::Object(&service_class).into(),
     ])
-    .map_err(|e| format!("New Intent: {}", e))?;
+    .map_err(|e| AppError::jni(format!("New Intent: {}", e)))?;
 
-  let extra_key = env.new_string("schedule_only").map_err(|e| format!("extra key: {}", e))?;
+  let extra_key = env.new_string("schedule_only").map_err(|e| AppError::jni(format!("extra key: {}", e)))?;
   env.call_method(&intent, "putExtra", "(Ljava/lang/String;Z)Landroid/content/Intent;", &[
     JValue::Object(&extra_key).into(),
     JValue::Bool(1).into(),
   ])
-  .map_err(|e| format!("putExtra: {}", e))?;
+  .map_err(|e| AppError::jni(format!("putExtra: {}", e)))?;
+  let slot_key = env.new_string("slot").map_err(|e| AppError::jni(format!("extra key: {}", e)))?;
+  let slot_j = env.new_string(slot_name(slot)).map_err(|e| AppError::jni(format!("slot str: {}", e)))?;
+  env.call_method(&intent, "putExtra", "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/Intent;", &[
+    JValue::Object(&slot_key).into(),
+    JValue::Object(&slot_j).into(),
+  ])
+  .map_err(|e| AppError::jni(format!("putExtra slot: {}", e)))?;
 
-  let context_class = env.get_object_class(&context).map_err(|e| format!("get_object_class: {}", e))?;
+  let context_class = env.get_object_class(&context).map_err(|e| AppError::jni(format!("get_object_class: {}", e)))?;
   let start_foreground = env.get_method_id(&context_class, "startForegroundService", "(Landroid/content/Intent;)Landroid/content/ComponentName;");
   if start_foreground.is_ok() {
     env.call_method(&context, "startForegroundService", "(Landroid/content/Intent;)Landroid/content/ComponentName;", &[JValue::Object(&intent).into()])
-      .map_err(|e| format!("startForegroundService: {}", e))?;
+      .map_err(|e| AppError::jni(format!("startForegroundService: {}", e)))?;
   } else {
     env.call_method(&context, "startService", "(Landroid/content/Intent;)Landroid/content/ComponentName;", &[JValue::Object(&intent).into()])
-      .map_err(|e| format!("startService: {}", e))?;
+      .map_err(|e| AppError::jni(format!("startService: {}", e)))?;
   }
   Ok(())
 }
 
+/// Остановить WallpaperRotationService; вызывается, когда не идёт ни один слот. Будильник,
+/// если уже заведён, сработает впустую: сервис прочитает в файле, что слот остановлен.
 #[cfg(target_os = "android")]
-fn stop_wallpaper_rotation_service_android() -> Result<(), String> {
+fn stop_wallpaper_rotation_service_android() -> Result<(), AppError> {
   use jni::objects::JValue;
-  const PREFS_NAME: &str = "chrono_wall_rotation";
 
   let ctx = ndk_context::android_context();
   let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM: {}", e))?
+    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM: {}", e)))?
   };
-  let mut env = vm.attach_current_thread().map_err(|e| format!("JNI attach: {}", e))?;
+  let mut env = vm.attach_current_thread().map_err(|e| AppError::jni(format!("JNI attach: {}", e)))?;
   let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
 
-  let prefs_name_j = env.new_string(PREFS_NAME).map_err(|e| format!("new_string: {}", e))?;
-  let prefs = env
-    .call_method(
-      &context,
-      "getSharedPreferences",
-      "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-      &[JValue::Object(&prefs_name_j).into(), JValue::Int(0i32).into()],
-    )
-    .map_err(|e| format!("getSharedPreferences: {}", e))?
-    .l()
-    .map_err(|e| format!("SharedPreferences: {}", e))?;
-
-  let editor = env
-    .call_method(&prefs, "edit", "()Landroid/content/SharedPreferences$Editor;", &[])
-    .map_err(|e| format!("edit: {}", e))?
-    .l()
-    .map_err(|e| format!("Editor: {}", e))?;
-
-  let running_j = env.new_string("running").map_err(|e| format!("key: {}", e))?;
-  env.call_method(&editor, "putBoolean", "(Ljava/lang/String;Z)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&running_j).into(),
-    JValue::Bool(0).into(),
-  ])
-  .map_err(|e| format!("putBoolean: {}", e))?;
-  env.call_method(&editor, "apply", "()V", &[]).map_err(|e| format!("apply: {}", e))?;
-
   // Пытаемся остановить сервис, но не падаем если он уже остановлен или не существует
-  let intent_class = env.find_class("android/content/Intent").map_err(|e| format!("Find Intent: {}", e))?;
-  let service_class = env.find_class("ru/qugor/chronowall/WallpaperRotationService").map_err(|e| format!("Find Service: {}", e))?;
+  let intent_class = env.find_class("android/content/Intent").map_err(|e| AppError::jni(format!("Find Intent: {}", e)))?;
+  let service_class = env.find_class("ru/qugor/chronowall/WallpaperRotationService").map_err(|e| AppError::jni(format!("Find Service: {}", e)))?;
   let intent = env
     .new_object(intent_class, "(Landroid/content/Context;Ljava/lang/Class;)V", &[
       JValue::Object(&context).into(),
       JValue::Object(&service_class).into(),
     ])
-    .map_err(|e| format!("New Intent: {}", e))?;
+    .map_err(|e| AppError::jni(format!("New Intent: {}", e)))?;
   // stopService возвращает boolean - игнорируем результат, чтобы не падать если сервис не запущен
   let _stopped = env.call_method(&context, "stopService", "(Landroid/content/Intent;)Z", &[JValue::Object(&intent).into()])
     .ok();
   Ok(())
 }
 
+/// Состояние ротации процесса. На Android его делят команды приложения и WallpaperRotationService:
+/// если процесс поднят ради будильника, без интерфейса, состояние создаёт первый вызов сервиса.
 #[cfg(target_os = "android")]
-fn get_wallpaper_rotation_state_android() -> Result<(i32, i64), String> {
-  use jni::objects::JValue;
-  const PREFS_NAME: &str = "chrono_wall_rotation";
+static SHARED_ROTATION: std::sync::OnceLock<Arc<RotationLoop>> = std::sync::OnceLock::new();
 
-  let ctx = ndk_context::android_context();
-  let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM: {}", e))?
-  };
-  let mut env = vm.attach_current_thread().map_err(|e| format!("JNI attach: {}", e))?;
-  let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
+/// Состояние из `state_file`; при первой загрузке в процессе в него переносятся prefs прежних версий.
+#[cfg(target_os = "android")]
+fn shared_rotation_android(env: &mut jni::JNIEnv, context: &JObject, state_file: PathBuf) -> Arc<RotationLoop> {
+  SHARED_ROTATION
+    .get_or_init(|| {
+      let rotation = Arc::new(RotationLoop::load(state_file));
+      let migrated = AndroidPrefs::new(env, context)
+        .map_err(String::from)
+        .and_then(|prefs| rotation_store::RotationStore::modify(&*rotation, &mut |state| {
+          if let Err(e) = rotation_prefs::migrate(&rotation_prefs::PrefsStore::new(&prefs), state) {
+            log::warn!("rotation: service prefs not migrated: {}", e);
+          }
+        }));
+      if let Err(e) = migrated {
+        log::warn!("rotation: service prefs not migrated: {}", e);
+      }
+      rotation
+    })
+    .clone()
+}
+
+/// SharedPreferences приложения через JNI — для переноса состояния из prefs прежних версий
+/// WallpaperRotationService (`rotation_prefs`). Держит JavaVM и контекст, а не JNIEnv: хранилище
+/// может понадобиться в другом потоке.
+#[cfg(target_os = "android")]
+struct AndroidPrefs {
+  vm: jni::JavaVM,
+  context: jni::objects::GlobalRef,
+}
+
+#[cfg(target_os = "android")]
+impl AndroidPrefs {
+  fn new(env: &mut jni::JNIEnv, context: &JObject) -> Result<Self, AppError> {
+    Ok(Self {
+      vm: env.get_java_vm().map_err(|e| AppError::jni(format!("JavaVM: {}", e)))?,
+      context: env.new_global_ref(context).map_err(|e| AppError::jni(format!("global ref: {}", e)))?,
+    })
+  }
 
-  let prefs_name_j = env.new_string(PREFS_NAME).map_err(|e| format!("new_string: {}", e))?;
-  let prefs = env
+  fn with_prefs<T>(
+    &self,
+    file: &str,
+    f: impl FnOnce(&mut jni::JNIEnv, &JObject) -> Result<T, AppError>,
+  ) -> Result<T, String> {
+    let mut env = self.vm.attach_current_thread().map_err(|e| AppError::jni(format!("JNI attach: {}", e)))?;
+    let prefs = shared_prefs(&mut *env, self.context.as_obj(), file)?;
+    f(&mut *env, &prefs).map_err(String::from)
+  }
+}
+
+#[cfg(target_os = "android")]
+impl rotation_prefs::Preferences for &AndroidPrefs {
+  fn get_string(&self, file: &str, key: &str) -> Result<String, String> {
+    self.with_prefs(file, |env, prefs| prefs_string(env, prefs, key))
+  }
+
+  fn get_long(&self, file: &str, key: &str) -> Result<i64, String> {
+    self.with_prefs(file, |env, prefs| prefs_long(env, prefs, key))
+  }
+
+  fn get_int(&self, file: &str, key: &str) -> Result<i32, String> {
+    self.with_prefs(file, |env, prefs| prefs_int(env, prefs, key))
+  }
+
+  fn get_bool(&self, file: &str, key: &str) -> Result<bool, String> {
+    self.with_prefs(file, |env, prefs| prefs_bool(env, prefs, key))
+  }
+
+  fn replace(&self, file: &str, values: &[(&str, rotation_prefs::PrefValue)]) -> Result<(), String> {
+    use jni::objects::JValue;
+    use rotation_prefs::PrefValue;
+    const EDITOR: &str = "Landroid/content/SharedPreferences$Editor;";
+    self.with_prefs(file, |env, prefs| {
+      let editor = env
+        .call_method(prefs, "edit", format!("(){}", EDITOR), &[])
+        .map_err(|e| AppError::jni(format!("edit: {}", e)))?
+        .l()
+        .map_err(|e| AppError::jni(format!("Editor: {}", e)))?;
+      env.call_method(&editor, "clear", format!("(){}", EDITOR), &[]).map_err(|e| AppError::jni(format!("clear: {}", e)))?;
+      for (key, value) in values {
+        let key_j = env.new_string(key).map_err(|e| AppError::jni(format!("key {}: {}", key, e)))?;
+        let put = match value {
+          PrefValue::Str(text) => {
+            let text = env.new_string(text).map_err(|e| AppError::jni(format!("new_string: {}", e)))?;
+            env.call_method(&editor, "putString", format!("(Ljava/lang/String;Ljava/lang/String;){}", EDITOR), &[JValue::Object(&key_j).into(), JValue::Object(&text).into()])
+          }
+          PrefValue::Long(n) => env.call_method(&editor, "putLong", format!("(Ljava/lang/String;J){}", EDITOR), &[JValue::Object(&key_j).into(), JValue::Long(*n).into()]),
+          PrefValue::Int(n) => env.call_method(&editor, "putInt", format!("(Ljava/lang/String;I){}", EDITOR), &[JValue::Object(&key_j).into(), JValue::Int(*n).into()]),
+          PrefValue::Bool(b) => env.call_method(&editor, "putBoolean", format!("(Ljava/lang/String;Z){}", EDITOR), &[JValue::Object(&key_j).into(), JValue::Bool(u8::from(*b)).into()]),
+        };
+        put.map_err(|e| AppError::jni(format!("put {}: {}", key, e)))?;
+      }
+      env.call_method(&editor, "apply", "()V", &[]).map_err(|e| AppError::jni(format!("apply: {}", e)))?;
+      Ok(())
+    })
+  }
+}
+
+/// Имя слота: в extra интента сервиса и в ошибках команд.
+fn slot_name(slot: RotationSlot) -> &'static str {
+  match slot {
+    RotationSlot::Home => "home",
+    RotationSlot::Lock => "lock",
+  }
+}
+
+#[cfg(target_os = "android")]
+fn shared_prefs<'local>(
+  env: &mut jni::JNIEnv<'local>,
+  context: &JObject,
+  file: &str,
+) -> Result<JObject<'local>, AppError> {
+  use jni::objects::JValue;
+  let prefs_name_j = env.new_string(file).map_err(|e| AppError::jni(format!("new_string: {}", e)))?;
+  env
     .call_method(
-      &context,
+      context,
       "getSharedPreferences",
       "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
       &[JValue::Object(&prefs_name_j).into(), JValue::Int(0i32).into()],
     )
-    .map_err(|e| format!("getSharedPreferences: {}", e))?
+    .map_err(|e| AppError::jni(format!("getSharedPreferences: {}", e)))?
     .l()
-    .map_err(|e| format!("SharedPreferences: {}", e))?;
-
-  let key_idx = env.new_string("rotation_index").map_err(|e| format!("key: {}", e))?;
-  let idx = env
-    .call_method(&prefs, "getInt", "(Ljava/lang/String;I)I", &[JValue::Object(&key_idx).into(), JValue::Int(0).into()])
-    .map_err(|e| format!("getInt: {}", e))?
-    .i()
-    .map_err(|e| format!("rotation_index: {}", e))?;
+    .map_err(|e| AppError::jni(format!("SharedPreferences: {}", e)))
+}
 
-  let key_last = env.new_string("last_change_at").map_err(|e| format!("key: {}", e))?;
-  let last = env
-    .call_method(&prefs, "getLong", "(Ljava/lang/String;J)J", &[JValue::Object(&key_last).into(), JValue::Long(0).into()])
-    .map_err(|e| format!("getLong: {}", e))?
+#[cfg(target_os = "android")]
+fn prefs_long(env: &mut jni::JNIEnv, prefs: &JObject, key: &str) -> Result<i64, AppError> {
+  use jni::objects::JValue;
+  let key_j = env.new_string(key).map_err(|e| AppError::jni(format!("key {}: {}", key, e)))?;
+  env
+    .call_method(prefs, "getLong", "(Ljava/lang/String;J)J", &[JValue::Object(&key_j).into(), JValue::Long(0).into()])
+    .map_err(|e| AppError::jni(format!("getLong {}: {}", key, e)))?
     .j()
-    .map_err(|e| format!("last_change_at: {}", e))?;
-
-  Ok((idx, last))
+    .map_err(|e| AppError::jni(format!("{}: {}", key, e)))
 }
 
 #[cfg(target_os = "android")]
-fn update_rotation_prefs_android(
-  interval_minutes: u32,
-  target: String,
-  rotation_index: u32,
-  last_change_at: i64,
-  sequence: Vec<String>,
-) -> Result<(), String> {
+fn prefs_bool(env: &mut jni::JNIEnv, prefs: &JObject, key: &str) -> Result<bool, AppError> {
   use jni::objects::JValue;
-  const PREFS_NAME: &str = "chrono_wall_rotation";
-  const DELIM: char = '\u{0000}';
-  let sequence_str = sequence.join(DELIM.to_string().as_str());
+  let key_j = env.new_string(key).map_err(|e| AppError::jni(format!("key {}: {}", key, e)))?;
+  env
+    .call_method(prefs, "getBoolean", "(Ljava/lang/String;Z)Z", &[JValue::Object(&key_j).into(), JValue::Bool(0).into()])
+    .map_err(|e| AppError::jni(format!("getBoolean {}: {}", key, e)))?
+    .z()
+    .map_err(|e| AppError::jni(format!("{}: {}", key, e)))
+}
 
-  let ctx = ndk_context::android_context();
-  let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM: {}", e))?
-  };
-  let mut env = vm.attach_current_thread().map_err(|e| format!("JNI attach: {}", e))?;
-  let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
+#[cfg(target_os = "android")]
+fn prefs_int(env: &mut jni::JNIEnv, prefs: &JObject, key: &str) -> Result<i32, AppError> {
+  use jni::objects::JValue;
+  let key_j = env.new_string(key).map_err(|e| AppError::jni(format!("key {}: {}", key, e)))?;
+  env
+    .call_method(prefs, "getInt", "(Ljava/lang/String;I)I", &[JValue::Object(&key_j).into(), JValue::Int(0).into()])
+    .map_err(|e| AppError::jni(format!("getInt {}: {}", key, e)))?
+    .i()
+    .map_err(|e| AppError::jni(format!("{}: {}", key, e)))
+}
 
-  let prefs_name_j = env.new_string(PREFS_NAME).map_err(|e| format!("new_string: {}", e))?;
-  let prefs = env
+#[cfg(target_os = "android")]
+fn prefs_string(env: &mut jni::JNIEnv, prefs: &JObject, key: &str) -> Result<String, AppError> {
+  use jni::objects::JValue;
+  let key_j = env.new_string(key).map_err(|e| AppError::jni(format!("key {}: {}", key, e)))?;
+  let empty = env.new_string("").map_err(|e| AppError::jni(format!("new_string: {}", e)))?;
+  let value = env
     .call_method(
-      &context,
-      "getSharedPreferences",
-      "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-      &[JValue::Object(&prefs_name_j).into(), JValue::Int(0i32).into()],
+      prefs,
+      "getString",
+      "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+      &[JValue::Object(&key_j).into(), JValue::Object(&empty).into()],
     )
-    .map_err(|e| format!("getSharedPreferences: {}", e))?
+    .map_err(|e| AppError::jni(format!("getString {}: {}", key, e)))?
     .l()
-    .map_err(|e| format!("SharedPreferences: {}", e))?;
+    .map_err(|e| AppError::jni(format!("{}: {}", key, e)))?;
+  if value.is_null() {
+    return Ok(String::new());
+  }
+  env
+    .get_string(&JString::from(value))
+    .map(String::from)
+    .map_err(|e| AppError::jni(format!("{}: {}", key, e)))
+}
 
-  let editor = env
-    .call_method(&prefs, "edit", "()Landroid/content/SharedPreferences$Editor;", &[])
-    .map_err(|e| format!("edit: {}", e))?
-    .l()
-    .map_err(|e| format!("Editor: {}", e))?;
+/// Запустить ротацию слота на движке из `rotation_start` (`LoopState::start`): интерфейс задаёт
+/// только интервал и цель, порядок и позицию ведёт движок. На ПК обои меняет RotationLoop,
+/// на Android — WallpaperRotationService по тому же файлу состояния. `target` относится
+/// к главному слоту; слот блокировки всегда ставит обои на экран блокировки.
+#[tauri::command]
+fn start_wallpaper_rotation_service(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  interval_minutes: u32,
+  target: String,
+  slot: Option<RotationSlot>,
+) -> Result<(), AppError> {
+  let slot = slot.unwrap_or_default();
+  let now = collection::now_millis();
+  let (state, started) = rotation_loop::start_slot(&**rotation, slot, target, interval_minutes, now)?
+    .ok_or_else(|| AppError::not_found("rotation", slot_name(slot)))?;
+  #[cfg(target_os = "android")]
+  start_wallpaper_rotation_service_android(slot)?;
+  if started {
+    events::rotation_started(&app, slot, Some(state));
+  } else {
+    events::rotation_updated(&app, slot, state);
+  }
+  Ok(())
+}
 
-  let key_interval = env.new_string("interval_minutes").map_err(|e| format!("key: {}", e))?;
-  env.call_method(&editor, "putInt", "(Ljava/lang/String;I)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_interval).into(),
-    JValue::Int(interval_minutes as i32).into(),
-  ])
-  .map_err(|e| format!("putInt: {}", e))?;
+#[tauri::command]
+fn stop_wallpaper_rotation_service(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<(), AppError> {
+  let slot = slot.unwrap_or_default();
+  let (was_running, any_running) = rotation_loop::stop_slot(&**rotation, slot)?;
+  // Будильники другого слота обслуживает тот же сервис — пока он идёт, сервис не останавливаем
+  #[cfg(target_os = "android")]
+  if !any_running {
+    stop_wallpaper_rotation_service_android()?;
+  }
+  #[cfg(not(target_os = "android"))]
+  let _ = any_running;
+  if was_running {
+    events::rotation_stopped(&app, slot);
+  }
+  Ok(())
+}
 
-  let key_target = env.new_string("target").map_err(|e| format!("key: {}", e))?;
-  let target_j = env.new_string(&target).map_err(|e| format!("target: {}", e))?;
-  env.call_method(&editor, "putString", "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_target).into(),
-    JValue::Object(&target_j).into(),
-  ])
-  .map_err(|e| format!("putString: {}", e))?;
+/// Индекс текущих обоев слота, время последней смены и срок закрепления (0 — не закреплены), мс.
+#[tauri::command]
+fn get_wallpaper_rotation_state(
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<(i32, i64, i64), AppError> {
+  Ok(rotation_loop::slot_position(&**rotation, slot.unwrap_or_default(), collection::now_millis())?)
+}
 
-  let key_idx = env.new_string("rotation_index").map_err(|e| format!("key: {}", e))?;
-  env.call_method(&editor, "putInt", "(Ljava/lang/String;I)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_idx).into(),
-    JValue::Int(rotation_index as i32).into(),
-  ])
-  .map_err(|e| format!("putInt: {}", e))?;
+/// Последовательность ротации по id плейлиста или коллекции.
+fn source_sequence(base: &Path, id: &str) -> Result<Vec<String>, AppError> {
+  if let Some(playlist) = playlist::Playlists::new(base).get(id)? {
+    return Ok(playlist.sequence(base));
+  }
+  let meta = collection::read_meta(&paths::collection_dir(base, id)?)?;
+  Ok(rotation::build_sequence(&meta))
+}
 
-  let key_last = env.new_string("last_change_at").map_err(|e| format!("key: {}", e))?;
-  env.call_method(&editor, "putLong", "(Ljava/lang/String;J)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_last).into(),
-    JValue::Long(last_change_at).into(),
-  ])
-  .map_err(|e| format!("putLong: {}", e))?;
+/// Последовательности коллекций (и плейлистов), которые может выбрать расписание.
+fn schedule_sequences(app: &tauri::AppHandle, schedule: &Schedule) -> Result<BTreeMap<String, Vec<String>>, AppError> {
+  schedule.validate()?;
+  let base = files_base_dir(app)?;
+  schedule
+    .collection_ids()
+    .into_iter()
+    .map(|id| Ok((id.to_string(), source_sequence(&base, id)?)))
+    .collect()
+}
 
-  let key_seq = env.new_string("sequence").map_err(|e| format!("key: {}", e))?;
-  let seq_j = env.new_string(&sequence_str).map_err(|e| format!("sequence: {}", e))?;
-  env.call_method(&editor, "putString", "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;", &[
-    JValue::Object(&key_seq).into(),
-    JValue::Object(&seq_j).into(),
-  ])
-  .map_err(|e| format!("putString: {}", e))?;
+/// Начать ротацию коллекции или плейлиста (`collection_id` — id любого из них) в слоте `slot`
+/// (по умолчанию главный). Текущие обои (`current`) вызывающий ставит сразу — смена отмечается
+/// сейчас. С расписанием начинается с коллекции, которую оно выбирает на текущий момент.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+fn rotation_start(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  collection_id: String,
+  mode: rotation::RotationMode,
+  interval_minutes: u32,
+  schedule: Option<Schedule>,
+  no_repeat: Option<usize>,
+  slot: Option<RotationSlot>,
+) -> Result<rotation::RotationState, AppError> {
+  let sequence = source_sequence(&files_base_dir(&app)?, &collection_id)?;
+  let mut engine = rotation::RotationEngine::new(&collection_id, mode, interval_minutes, sequence, 0);
+  engine.set_no_repeat(no_repeat.unwrap_or(rotation::DEFAULT_NO_REPEAT));
+  if let Some(schedule) = schedule {
+    let sequences = schedule_sequences(&app, &schedule)?;
+    engine.set_schedule(Some(schedule), sequences);
+  }
+  let now = collection::now_millis();
+  engine.start(now);
+  let state = engine.state(now);
+  let slot = slot.unwrap_or_default();
+  rotation.update(|loop_state| loop_state.set_engine(slot, Some(engine)))?;
+  // Слот ещё не запущен: rotation-started придёт из start_wallpaper_rotation_service
+  events::rotation_updated(&app, slot, state.clone());
+  Ok(state)
+}
 
-  env.call_method(&editor, "apply", "()V", &[]).map_err(|e| format!("apply: {}", e))?;
-  Ok(())
+/// Перейти к следующим обоям; `current` в ответе — что поставить.
+#[tauri::command]
+fn rotation_advance(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<rotation::RotationState, AppError> {
+  let slot = slot.unwrap_or_default();
+  let now = collection::now_millis();
+  let state = rotation.update(|state| {
+    let engine = state.engine_mut(slot).ok_or_else(|| AppError::not_found("rotation", slot_name(slot)))?;
+    engine.advance(now);
+    Ok::<_, AppError>(engine.state(now))
+  })??;
+  events::rotation_updated(&app, slot, state.clone());
+  Ok(state)
 }
 
+/// Шаг по таймеру фронтенда: обои меняются, только если срок (интервал, cron или граница окна
+/// расписания) наступил.
 #[tauri::command]
-fn start_wallpaper_rotation_service(
-  interval_minutes: u32,
-  target: String,
-  rotation_index: u32,
-  last_change_at: i64,
-  sequence: Vec<String>,
-) -> Result<(), String> {
-  #[cfg(target_os = "android")]
-  {
-    start_wallpaper_rotation_service_android(interval_minutes, target, rotation_index, last_change_at, sequence)
+fn rotation_tick(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<Option<rotation::Tick>, AppError> {
+  let slot = slot.unwrap_or_default();
+  let now = collection::now_millis();
+  let tick = rotation.update(|state| {
+    let engine = state.engine_mut(slot)?;
+    let due = engine.is_due(now);
+    if due {
+      engine.advance(now);
+    }
+    Some(rotation::Tick {
+      due,
+      state: engine.state(now),
+    })
+  })?;
+  if let Some(tick) = tick.as_ref().filter(|tick| tick.due) {
+    events::rotation_updated(&app, slot, tick.state.clone());
   }
-  #[cfg(not(target_os = "android"))]
-  {
-    let _ = (interval_minutes, target, rotation_index, last_change_at, sequence);
-    Err("Only supported on Android".to_string())
+  Ok(tick)
+}
+
+/// Задать или снять расписание запущенной ротации.
+#[tauri::command]
+fn rotation_set_schedule(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  schedule: Option<Schedule>,
+  slot: Option<RotationSlot>,
+) -> Result<Option<rotation::RotationState>, AppError> {
+  let slot = slot.unwrap_or_default();
+  let sequences = match &schedule {
+    Some(schedule) => schedule_sequences(&app, schedule)?,
+    None => BTreeMap::new(),
+  };
+  let state = rotation.update(|state| {
+    let engine = state.engine_mut(slot)?;
+    engine.set_schedule(schedule, sequences);
+    Some(engine.state(collection::now_millis()))
+  })?;
+  if let Some(state) = &state {
+    events::rotation_updated(&app, slot, state.clone());
   }
+  Ok(state)
 }
 
+/// Окно без повторов для случайного режима.
 #[tauri::command]
-fn stop_wallpaper_rotation_service() -> Result<(), String> {
-  #[cfg(target_os = "android")]
-  {
-    stop_wallpaper_rotation_service_android()
+fn rotation_set_no_repeat(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  no_repeat: usize,
+  slot: Option<RotationSlot>,
+) -> Result<Option<rotation::RotationState>, AppError> {
+  let slot = slot.unwrap_or_default();
+  let state = rotation.update(|state| {
+    let engine = state.engine_mut(slot)?;
+    engine.set_no_repeat(no_repeat);
+    Some(engine.state(collection::now_millis()))
+  })?;
+  if let Some(state) = &state {
+    events::rotation_updated(&app, slot, state.clone());
   }
-  #[cfg(not(target_os = "android"))]
-  {
-    Err("Only supported on Android".to_string())
+  Ok(state)
+}
+
+#[tauri::command]
+fn rotation_set_interval(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  interval_minutes: u32,
+  slot: Option<RotationSlot>,
+) -> Result<Option<rotation::RotationState>, AppError> {
+  let slot = slot.unwrap_or_default();
+  let state = rotation_loop::set_slot_interval(&**rotation, slot, interval_minutes, collection::now_millis())?;
+  if let Some(state) = &state {
+    events::rotation_updated(&app, slot, state.clone());
   }
+  Ok(state)
 }
 
+/// Следующие обои идущей ротации — сразу, следующая смена через интервал от этой.
 #[tauri::command]
-fn get_wallpaper_rotation_state() -> Result<(i32, i64), String> {
-  #[cfg(target_os = "android")]
-  {
-    get_wallpaper_rotation_state_android()
+fn rotate_next(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<rotation::RotationState, AppError> {
+  manual_rotation(&app, &rotation, slot.unwrap_or_default(), |engine, now| engine.advance(now).is_some())
+}
+
+/// Предыдущие обои идущей ротации.
+#[tauri::command]
+fn rotate_previous(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<rotation::RotationState, AppError> {
+  manual_rotation(&app, &rotation, slot.unwrap_or_default(), |engine, now| engine.step_back(now).is_some())
+}
+
+/// Закрепить текущие обои на `duration_minutes` минут; 0 снимает закрепление.
+#[tauri::command]
+fn pin_current(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  duration_minutes: u32,
+  slot: Option<RotationSlot>,
+) -> Result<rotation::RotationState, AppError> {
+  manual_rotation(&app, &rotation, slot.unwrap_or_default(), |engine, now| {
+    engine.pin(now, duration_minutes);
+    false
+  })
+}
+
+/// Ручное действие над идущей ротацией слота: `f` меняет движок и сообщает, сменились ли обои. Новые
+/// обои ставятся сразу; цикл на ПК и сервис на Android берут следующий срок из того же состояния.
+fn manual_rotation(
+  app: &tauri::AppHandle,
+  rotation: &RotationLoop,
+  slot: RotationSlot,
+  f: impl FnOnce(&mut rotation::RotationEngine, u64) -> bool,
+) -> Result<rotation::RotationState, AppError> {
+  let now = collection::now_millis();
+  let (state, target, changed) = rotation.update(|state| {
+    let target = state.target(slot).to_string();
+    let engine = state.engine_mut(slot).ok_or_else(|| AppError::not_found("rotation", slot_name(slot)))?;
+    let changed = f(engine, now);
+    Ok::<_, AppError>((engine.state(now), target, changed))
+  })??;
+  match (changed, &state.current) {
+    (true, Some(path)) => {
+      apply_wallpaper(app, path, &target)?;
+      record_history(app, path, &target, Some(state.collection_id.clone()), Some((slot, state.clone())));
+    }
+    _ => events::rotation_updated(app, slot, state.clone()),
   }
-  #[cfg(not(target_os = "android"))]
-  {
-    Err("Only supported on Android".to_string())
+  Ok(state)
+}
+
+/// Изображения коллекции изменились: идущие ротации подхватывают их сразу, если идут по этой
+/// коллекции, по плейлисту с ней или могут переключиться на неё по расписанию. `source_id` —
+/// id коллекции или плейлиста (после изменения его состава).
+fn refresh_rotation(app: &tauri::AppHandle, source_id: &str) {
+  for slot in [RotationSlot::Home, RotationSlot::Lock] {
+    if let Err(e) = try_refresh_rotation(app, source_id, slot) {
+      log::warn!("rotation: sequence for '{}' not refreshed: {}", source_id, e);
+      events::rotation_error(app, Some(slot), history::HistorySource::App, e.to_string());
+    }
   }
 }
 
+fn try_refresh_rotation(app: &tauri::AppHandle, source_id: &str, slot: RotationSlot) -> Result<(), AppError> {
+  let rotation = app.state::<Arc<RotationLoop>>();
+  let Some(engine) = rotation.snapshot().engine(slot).cloned() else {
+    return Ok(());
+  };
+  let base = files_base_dir(app)?;
+  let playlists = playlist::Playlists::new(&base);
+  let mut fresh = BTreeMap::new();
+  for id in engine.collections.keys().chain([&engine.collection_id]) {
+    let depends = id == source_id || playlists.get(id)?.is_some_and(|p| p.contains(source_id));
+    if depends && !fresh.contains_key(id) {
+      fresh.insert(id.clone(), source_sequence(&base, id)?);
+    }
+  }
+  if fresh.is_empty() {
+    return Ok(());
+  }
+  let state = rotation.update(|state| {
+    let engine = state.engine_mut(slot)?;
+    for (id, sequence) in fresh {
+      engine.update_collection(&id, sequence);
+    }
+    Some(engine.state(collection::now_millis()))
+  })?;
+  if let Some(state) = state {
+    events::rotation_updated(app, slot, state);
+  }
+  Ok(())
+}
+
 #[tauri::command]
-fn update_rotation_prefs(
-  interval_minutes: u32,
-  target: String,
-  rotation_index: u32,
-  last_change_at: i64,
-  sequence: Vec<String>,
-) -> Result<(), String> {
-  #[cfg(target_os = "android")]
-  {
-    update_rotation_prefs_android(interval_minutes, target, rotation_index, last_change_at, sequence)
+fn rotation_state(
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Option<rotation::RotationState> {
+  let now = collection::now_millis();
+  rotation.snapshot().engine(slot.unwrap_or_default()).map(|engine| engine.state(now))
+}
+
+#[tauri::command]
+fn rotation_stop(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  slot: Option<RotationSlot>,
+) -> Result<(), AppError> {
+  let slot = slot.unwrap_or_default();
+  let was_active = rotation_loop::clear_slot(&**rotation, slot)?;
+  if was_active {
+    events::rotation_stopped(&app, slot);
   }
-  #[cfg(not(target_os = "android"))]
-  {
-    let _ = (interval_minutes, target, rotation_index, last_change_at, sequence);
-    Ok(())
+  Ok(())
+}
+
+/// Вызов из WallpaperRotationService (`RotationNative.tick`): шаг слота по файлу состояния
+/// `state_file` (`LoopState::tick_slot`). Решение о смене обоев принимает RotationEngine и сразу
+/// сохраняет его; сервис только ставит выбранный файл и заводит будильник на `next_due_at`.
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_ru_qugor_chronowall_RotationNative_tick<'local>(
+  mut env: jni::JNIEnv<'local>,
+  _class: jni::objects::JClass<'local>,
+  context: JObject<'local>,
+  state_file: JString<'local>,
+  slot: JString<'local>,
+  now: jni::sys::jlong,
+  advance: jni::sys::jboolean,
+) -> jni::sys::jstring {
+  let result = (|| {
+    let state_file = env.get_string(&state_file).map(String::from).map_err(|e| format!("state file: {}", e))?;
+    let slot = service_slot(&env.get_string(&slot).map(String::from).map_err(|e| format!("slot: {}", e))?);
+    let rotation = shared_rotation_android(&mut env, &context, PathBuf::from(state_file));
+    let tick = rotation.update(|state| state.tick_slot(slot, now.max(0) as u64, advance != 0))?;
+    serde_json::to_string(&tick).map_err(|e| e.to_string())
+  })();
+  match result.and_then(|json| env.new_string(json).map_err(|e| AppError::jni(format!("new_string: {}", e)))) {
+    Ok(out) => out.into_raw(),
+    Err(e) => {
+      let _ = env.throw_new("java/lang/IllegalStateException", e);
+      std::ptr::null_mut()
+    }
+  }
+}
+
+/// Вызов из WallpaperRotationService (`RotationNative.recordHistory`) после смены обоев сервисом.
+/// Если интерфейс открыт, он получает `wallpaper-changed` с состоянием слота после шага.
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_ru_qugor_chronowall_RotationNative_recordHistory<'local>(
+  mut env: jni::JNIEnv<'local>,
+  _class: jni::objects::JClass<'local>,
+  base_dir: JString<'local>,
+  path: JString<'local>,
+  target: JString<'local>,
+  collection_id: JString<'local>,
+  at: jni::sys::jlong,
+  slot: JString<'local>,
+) {
+  let mut get = |s: &JString<'local>| env.get_string(s).map(String::from).map_err(|e| e.to_string());
+  let result = (|| {
+    let base = get(&base_dir)?;
+    let collection_id = get(&collection_id)?;
+    let slot = service_slot(&get(&slot)?);
+    let entry = history::HistoryEntry {
+      at: at.max(0) as u64,
+      path: get(&path)?,
+      target: get(&target)?,
+      source: history::HistorySource::Service,
+      collection_id: Some(collection_id).filter(|id| !id.is_empty()),
+    };
+    history::History::new(Path::new(&base)).append(&entry)?;
+    Ok::<_, String>((entry, slot))
+  })();
+  match result {
+    Ok((entry, slot)) => {
+      if let Some(app) = APP_HANDLE.get() {
+        let now = collection::now_millis();
+        let state = app.state::<Arc<RotationLoop>>().snapshot().engine(slot).map(|engine| engine.state(now));
+        events::wallpaper_changed(app, events::WallpaperChanged {
+          entry,
+          slot: Some(slot),
+          state,
+        });
+      }
+    }
+    Err(e) => {
+      let _ = env.throw_new("java/lang/IllegalStateException", e);
+    }
   }
 }
+
+/// Вызов из WallpaperRotationService (`RotationNative.reportError`): сервис не смог сменить обои.
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_ru_qugor_chronowall_RotationNative_reportError<'local>(
+  mut env: jni::JNIEnv<'local>,
+  _class: jni::objects::JClass<'local>,
+  slot: JString<'local>,
+  message: JString<'local>,
+) {
+  let slot = env.get_string(&slot).map(String::from).unwrap_or_default();
+  let message = env.get_string(&message).map(String::from).unwrap_or_default();
+  log::warn!("rotation service ({}): {}", slot, message);
+  if let Some(app) = APP_HANDLE.get() {
+    events::rotation_error(app, Some(service_slot(&slot)), history::HistorySource::Service, message);
+  }
+}
+
+/// Слот из extra сервиса ("home" / "lock").
+#[cfg(target_os = "android")]
+fn service_slot(name: &str) -> RotationSlot {
+  match name {
+    "lock" => RotationSlot::Lock,
+    _ => RotationSlot::Home,
+  }
+}
+
 /// Папка конкретной коллекции: base/collections/{collection_id}
-fn collection_dir(app: &tauri::AppHandle, collection_id: &str) -> Result<PathBuf, String> {
-  Ok(files_base_dir(app)?.join("collections").join(collection_id))
+fn collection_dir(app: &tauri::AppHandle, collection_id: &str) -> Result<PathBuf, AppError> {
+  paths::collection_dir(&files_base_dir(app)?, collection_id)
 }
 
 /// Единая структура: на ПК app_data/files/{save_type}, на Android — picture_dir/{save_type}. Оставлено для обратной совместимости.
-fn files_dir_for_type(app: &tauri::AppHandle, save_type: &str) -> Result<PathBuf, String> {
+fn files_dir_for_type(app: &tauri::AppHandle, save_type: &str) -> Result<PathBuf, AppError> {
+  paths::validate_segment(save_type)?;
   #[cfg(target_os = "android")]
   {
     let base = app
       .path()
       .picture_dir()
-      .map_err(|e: tauri::Error| e.to_string())?;
+      .map_err(|e: tauri::Error| AppError::from(e.to_string()))?;
     Ok(base.join(save_type))
   }
   #[cfg(not(target_os = "android"))]
@@ -669,18 +1125,18 @@ fn files_dir_for_type(app: &tauri::AppHandle, save_type: &str) -> Result<PathBuf
     let base = app
       .path()
       .app_data_dir()
-      .map_err(|e: tauri::Error| e.to_string())?;
+      .map_err(|e: tauri::Error| AppError::from(e.to_string()))?;
     Ok(base.join("files").join(save_type))
   }
 }
 
 #[tauri::command]
-fn get_files_base_path(app: tauri::AppHandle) -> Result<String, String> {
+fn get_files_base_path(app: tauri::AppHandle) -> Result<String, AppError> {
   let base = files_base_dir(&app)?;
   base
     .to_str()
     .map(String::from)
-    .ok_or_else(|| "Invalid base path".to_string())
+    .ok_or_else(|| AppError::path_not_allowed(base.to_string_lossy()))
 }
 
 #[tauri::command]
@@ -688,22 +1144,23 @@ fn get_file_name_from_path(app: tauri::AppHandle, path: String) -> Option<String
   app.path().file_name(&path)
 }
 
+/// Скопировать файл в папку типа `save_type`: с диска (`source_path`) или принятый через
+/// upload_chunk (`upload_id` + `file_name`).
 #[tauri::command]
 fn save_file_to_app(
   app: tauri::AppHandle,
   save_type: String,
   source_path: Option<String>,
-  contents: Option<Vec<u8>>,
+  upload_id: Option<String>,
   file_name: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
   let dir = files_dir_for_type(&app, &save_type)?;
   if cfg!(debug_assertions) {
     log::info!("save_file_to_app: dir = {:?}", dir);
   }
   fs::create_dir_all(&dir).map_err(|e| {
-    let msg = format!("create_dir_all {:?}: {}", dir, e);
-    log::error!("{}", msg);
-    msg
+    log::error!("create_dir_all {:?}: {}", dir, e);
+    AppError::io(&dir, e)
   })?;
 
   if let Some(ref path) = source_path {
@@ -713,121 +1170,214 @@ fn save_file_to_app(
       .ok_or_else(|| "Invalid file name".to_string())?;
     let dest = dir.join(name);
     fs::copy(path, &dest).map_err(|e| {
-      let msg = format!("copy {} -> {:?}: {}", path, dest, e);
-      log::error!("{}", msg);
-      msg
+      log::error!("copy {} -> {:?}: {}", path, dest, e);
+      AppError::io(&dest, e)
     })?;
     return dest
       .to_str()
       .map(String::from)
-      .ok_or_else(|| "Invalid path".to_string());
+      .ok_or_else(|| AppError::path_not_allowed(dest.to_string_lossy()));
   }
 
-  if let (Some(data), Some(name)) = (contents, file_name) {
+  if let (Some(id), Some(name)) = (upload_id, file_name) {
+    paths::validate_segment(&name)?;
+    let uploads = uploads(&app)?;
     let dest = dir.join(&name);
-    fs::write(&dest, &data).map_err(|e| {
-      let msg = format!("write {:?} ({} bytes): {}", dest, data.len(), e);
-      log::error!("{}", msg);
-      msg
+    fs::copy(uploads.path(&id)?, &dest).map_err(|e| {
+      log::error!("copy upload {} -> {:?}: {}", id, dest, e);
+      AppError::io(&dest, e)
     })?;
+    uploads.discard(&id)?;
     return dest
       .to_str()
       .map(String::from)
-      .ok_or_else(|| "Invalid path".to_string());
+      .ok_or_else(|| AppError::path_not_allowed(dest.to_string_lossy()));
   }
 
-  Err("Need either sourcePath or (contents + file_name)".to_string())
+  Err("Need either sourcePath or (uploadId + fileName)".to_string().into())
 }
 
-/// Сохранить файл в папку коллекции. Возвращает относительный путь: collections/{collection_id}/{file_name}
+/// Сохранить файл для коллекции в общее хранилище (blobs/). Возвращает относительный путь
+/// blobs/{xx}/{blob_id}; элемент в коллекцию добавляется отдельно через add_collection_item.
+/// Одинаковое содержимое хранится один раз. Источник — файл на диске (`source_path`) или
+/// принятый через upload_chunk (`upload_id`); принятый файл после сохранения удаляется.
 #[tauri::command]
 fn save_file_to_collection(
   app: tauri::AppHandle,
   collection_id: String,
   file_name: String,
   source_path: Option<String>,
-  contents: Option<Vec<u8>>,
-) -> Result<String, String> {
+  upload_id: Option<String>,
+) -> Result<String, AppError> {
   let dir = collection_dir(&app, &collection_id)?;
-  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  if !dir.is_dir() {
+    return Err(AppError::not_found("collection", collection_id));
+  }
 
   let name = Path::new(&file_name)
     .file_name()
     .and_then(|n| n.to_str())
     .ok_or_else(|| "Invalid file name".to_string())?
     .to_string();
-
-  if let Some(ref path) = source_path {
-    let dest = dir.join(&name);
-    fs::copy(path, &dest).map_err(|e| e.to_string())?;
-    let relative = format!("collections/{}/{}", collection_id, name);
-    return Ok(relative);
+  // Служебные файлы (_meta.json и т.п.) пишет только Rust
+  collection::validate_item_file_name(&name)?;
+
+  let uploads = uploads(&app)?;
+  let source = match (source_path, &upload_id) {
+    (Some(path), _) => PathBuf::from(path),
+    (None, Some(id)) => uploads.path(id)?,
+    (None, None) => return Err("Need either source_path or upload_id".to_string().into()),
+  };
+  let saved = if collection::read_meta(&dir)?.encryption.is_some() {
+    save_encrypted_file(&dir, &collection_id, &name, &source)
+  } else {
+    let store = blobs::BlobStore::new(&files_base_dir(&app)?);
+    store
+      .put_file(&source, &name)
+      .map(|id| blobs::relative_path(&id))
+      .map_err(AppError::from)
+  };
+  if let Some(id) = upload_id {
+    if let Err(e) = uploads.discard(&id) {
+      log::warn!("upload {}: {}", id, e);
+    }
   }
+  saved
+}
 
-  if let Some(data) = contents {
-    let dest = dir.join(&name);
-    fs::write(&dest, &data).map_err(|e| e.to_string())?;
-    let relative = format!("collections/{}/{}", collection_id, name);
-    return Ok(relative);
-  }
+/// Зашифровать файл в папку зашифрованной коллекции. Возвращает путь collections/{id}/{file}.
+fn save_encrypted_file(dir: &Path, collection_id: &str, name: &str, source: &Path) -> Result<String, AppError> {
+  let file = fs::File::open(source).map_err(|e| AppError::io(source, e))?;
+  let len = file.metadata().map_err(|e| AppError::io(source, e))?.len();
+  let final_name = crypto::save_to_collection(dir, collection_id, name, &mut std::io::BufReader::new(file), len)?;
+  Ok(format!("collections/{}/{}", collection_id, final_name))
+}
 
-  Err("Need either source_path or contents".to_string())
+/// Незавершённые загрузки upload_chunk — в кеше приложения, не в base: на Android base — папка
+/// «Изображения», и временные файлы попали бы в галерею.
+fn uploads(app: &tauri::AppHandle) -> Result<upload::Uploads, AppError> {
+  let cache = app
+    .path()
+    .app_cache_dir()
+    .map_err(|e: tauri::Error| AppError::from(e.to_string()))?;
+  Ok(upload::Uploads::new(cache.join("uploads")))
 }
 
-/// Read file content. path — относительный (collections/...) или полный (для совместимости).
+/// Начать передачу файла частями; возвращает id для upload_chunk и команд сохранения.
 #[tauri::command]
-fn read_file_from_app(app: tauri::AppHandle, path: String) -> Result<Vec<u8>, String> {
-  let base = files_base_dir(&app)?;
-  let base_str = base.to_string_lossy().to_string();
-  let path_buf = PathBuf::from(&path);
-  let full = if path_buf.is_absolute() {
-    path_buf
-  } else {
-    base.join(path)
+fn upload_begin(app: tauri::AppHandle) -> Result<String, AppError> {
+  uploads(&app)?.begin()
+}
+
+/// Принять часть файла (см. `upload`): сырое тело с заголовками на ПК, JSON с base64 на Android.
+/// Возвращает принятый размер.
+#[tauri::command]
+fn upload_chunk(app: tauri::AppHandle, request: tauri::ipc::Request<'_>) -> Result<u64, AppError> {
+  let chunk = match request.body() {
+    tauri::ipc::InvokeBody::Raw(data) => {
+      let header = |name: &str| request.headers().get(name).and_then(|v| v.to_str().ok());
+      upload::Chunk::from_raw(header("Upload-Id"), header("Upload-Offset"), data.clone())?
+    }
+    tauri::ipc::InvokeBody::Json(body) => upload::Chunk::from_json(body)?,
   };
-  let full_str = full.to_string_lossy().to_string();
-  if !full_str.starts_with(&base_str) {
-    return Err("Path not allowed".to_string());
+  uploads(&app)?.append(&chunk)
+}
+
+/// Отменить передачу: принятые части удаляются.
+#[tauri::command]
+fn upload_abort(app: tauri::AppHandle, upload_id: String) -> Result<(), AppError> {
+  uploads(&app)?.discard(&upload_id)
+}
+
+/// Зашифрованная коллекция, которой принадлежит файл (base/collections/{id}/{file}), если есть.
+fn encrypted_collection_of(base: &Path, full: &Path) -> Option<String> {
+  let base = paths::resolve(base, ".").ok()?;
+  let rel = full.strip_prefix(&base).ok()?;
+  let mut parts = rel.components().map(|c| c.as_os_str().to_str());
+  match (parts.next(), parts.next(), parts.next(), parts.next()) {
+    (Some(Some("collections")), Some(Some(id)), Some(Some(_)), None) => {
+      let meta = collection::read_meta(&base.join("collections").join(id)).ok()?;
+      meta.encryption.map(|_| id.to_string())
+    }
+    _ => None,
   }
-  fs::read(&full).map_err(|e| e.to_string())
 }
 
-/// Удалить файл по относительному или полному пути в пределах base.
+/// Read file content. path — относительный (collections/...) или полный (для совместимости).
+/// Файлы зашифрованных коллекций расшифровываются, если коллекция разблокирована: чанк за чанком
+/// из файла сразу в тело ответа, без копии шифротекста в памяти. Ответ — сырые байты
+/// (ArrayBuffer в интерфейсе), а не JSON-массив чисел.
 #[tauri::command]
-fn delete_app_file(app: tauri::AppHandle, path: String) -> Result<(), String> {
+fn read_file_from_app(app: tauri::AppHandle, path: String) -> Result<tauri::ipc::Response, AppError> {
   let base = files_base_dir(&app)?;
-  let base_str = base.to_string_lossy().to_string();
-  let path_buf = PathBuf::from(&path);
-  let full = if path_buf.is_absolute() {
-    path_buf
-  } else {
-    base.join(path)
+  let full = paths::resolve(&base, &path)?;
+  let Some(collection_id) = encrypted_collection_of(&base, &full) else {
+    return fs::read(&full).map(tauri::ipc::Response::new).map_err(|e| AppError::io(&full, e));
   };
-  let full_str = full.to_string_lossy().to_string();
-  if !full_str.starts_with(&base_str) {
-    return Err("Path not allowed".to_string());
-  }
-  if full.exists() {
-    fs::remove_file(&full).map_err(|e| e.to_string())?;
+  let file = fs::File::open(&full).map_err(|e| AppError::io(&full, e))?;
+  // Открытый текст не длиннее шифротекста: буфер ответа выделяется один раз
+  let len = file.metadata().map_err(|e| AppError::io(&full, e))?.len();
+  let mut plain = Vec::with_capacity(len as usize);
+  crypto::with_session(&collection_id, |session| {
+    crypto::decrypt(session, &mut std::io::BufReader::new(file), &mut plain)
+  })?;
+  Ok(tauri::ipc::Response::new(plain))
+}
+
+/// Миниатюра (JPEG) для сетки: path — как у read_file_from_app, size — длинная сторона в px
+/// (округляется вверх до 128/256/512/1024). Создаётся при первом запросе и кешируется в `_thumbs`.
+#[tauri::command(async)]
+fn get_thumbnail(app: tauri::AppHandle, path: String, size: u32) -> Result<tauri::ipc::Response, AppError> {
+  thumbnails::get(&files_base_dir(&app)?, &path, size).map(tauri::ipc::Response::new)
+}
+
+/// Разблокировать зашифрованную коллекцию: ключ хранится в памяти до lock_collection или выхода.
+#[tauri::command]
+fn unlock_collection(app: tauri::AppHandle, collection_id: String, password: String) -> Result<(), AppError> {
+  let meta = collection::read_meta(&collection_dir(&app, &collection_id)?)?;
+  let encryption = meta
+    .encryption
+    .ok_or_else(|| AppError::NotEncrypted { id: collection_id.clone() })?;
+  Ok(crypto::unlock(&collection_id, &encryption, &password)?)
+}
+
+/// Забыть ключ коллекции.
+#[tauri::command]
+fn lock_collection(collection_id: String) {
+  crypto::lock(&collection_id);
+}
+
+#[tauri::command]
+fn is_collection_unlocked(collection_id: String) -> bool {
+  crypto::is_unlocked(&collection_id)
+}
+
+/// Переместить файл (относительный или полный путь в пределах base) в корзину.
+#[tauri::command]
+fn delete_app_file(app: tauri::AppHandle, path: String) -> Result<(), AppError> {
+  let full = resolve_app_path(&app, &path)?;
+  if full.is_file() {
+    let base = paths::resolve(&files_base_dir(&app)?, ".")?;
+    trash::Trash::new(&base).trash_file(&full)?;
   }
   Ok(())
 }
 
 /// Получить размер экрана Android: ширина/высота в пикселях и xdpi/ydpi для расчёта физических дюймов.
 #[cfg(target_os = "android")]
-fn get_screen_size_android() -> Result<(i32, i32, f32, f32), String> {
+fn get_screen_size_android() -> Result<(i32, i32, f32, f32), AppError> {
   let ctx = android_context();
   let vm = unsafe {
-    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| format!("JavaVM error: {}", e))?
+    jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM error: {}", e)))?
   };
   let mut env = vm
     .attach_current_thread()
-    .map_err(|e| format!("JNI attach thread: {}", e))?;
+    .map_err(|e| AppError::jni(format!("JNI attach thread: {}", e)))?;
   let context = unsafe { JObject::from_raw(ctx.context() as *mut _) };
 
   let _resources_class = env
     .find_class("android/content/Context")
-    .map_err(|e| format!("Find Context: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find Context: {}", e)))?;
   let resources = env
     .call_method(
       &context,
@@ -835,16 +1385,16 @@ fn get_screen_size_android() -> Result<(i32, i32, f32, f32), String> {
       "()Landroid/content/res/Resources;",
       &[],
     )
-    .map_err(|e| format!("getResources: {}", e))?
+    .map_err(|e| AppError::jni(format!("getResources: {}", e)))?
     .l()
-    .map_err(|e| format!("Get Resources object: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get Resources object: {}", e)))?;
 
   let display_metrics_class = env
     .find_class("android/util/DisplayMetrics")
-    .map_err(|e| format!("Find DisplayMetrics: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Find DisplayMetrics: {}", e)))?;
   let metrics = env
     .new_object(display_metrics_class, "()V", &[])
-    .map_err(|e| format!("New DisplayMetrics: {}", e))?;
+    .map_err(|e| AppError::jni(format!("New DisplayMetrics: {}", e)))?;
 
   env
     .call_method(
@@ -853,65 +1403,113 @@ fn get_screen_size_android() -> Result<(i32, i32, f32, f32), String> {
       "(Landroid/util/DisplayMetrics;)V",
       &[jni::objects::JValue::Object(&metrics)],
     )
-    .map_err(|e| format!("getDisplayMetrics: {}", e))?;
+    .map_err(|e| AppError::jni(format!("getDisplayMetrics: {}", e)))?;
 
   let width = env
     .get_field(&metrics, "widthPixels", "I")
-    .map_err(|e| format!("get widthPixels: {}", e))?
+    .map_err(|e| AppError::jni(format!("get widthPixels: {}", e)))?
     .i()
-    .map_err(|e| format!("Get width: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get width: {}", e)))?;
 
   let height = env
     .get_field(&metrics, "heightPixels", "I")
-    .map_err(|e| format!("get heightPixels: {}", e))?
+    .map_err(|e| AppError::jni(format!("get heightPixels: {}", e)))?
     .i()
-    .map_err(|e| format!("Get height: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get height: {}", e)))?;
 
   // Физические дюймы: xdpi, ydpi (пикселей на дюйм по осям) — для точного соотношения сторон экрана
   let xdpi = env
     .get_field(&metrics, "xdpi", "F")
-    .map_err(|e| format!("get xdpi: {}", e))?
+    .map_err(|e| AppError::jni(format!("get xdpi: {}", e)))?
     .f()
-    .map_err(|e| format!("Get xdpi: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get xdpi: {}", e)))?;
 
   let ydpi = env
     .get_field(&metrics, "ydpi", "F")
-    .map_err(|e| format!("get ydpi: {}", e))?
+    .map_err(|e| AppError::jni(format!("get ydpi: {}", e)))?
     .f()
-    .map_err(|e| format!("Get ydpi: {}", e))?;
+    .map_err(|e| AppError::jni(format!("Get ydpi: {}", e)))?;
 
   Ok((width, height, xdpi, ydpi))
 }
 
 #[tauri::command]
-fn get_screen_size() -> Result<(i32, i32, f32, f32), String> {
+fn get_screen_size() -> Result<(i32, i32, f32, f32), AppError> {
   #[cfg(target_os = "android")]
   {
     return get_screen_size_android();
   }
   #[cfg(not(target_os = "android"))]
   {
-    Err("Only supported on Android".to_string())
+    Err(AppError::Unsupported { feature: "screen_size" })
   }
 }
 
 /// Создать коллекцию. Возвращает уникальный ID коллекции.
 #[tauri::command]
-fn create_collection(app: tauri::AppHandle, name: String) -> Result<String, String> {
-  let collections_dir = files_base_dir(&app)?.join("collections");
-  fs::create_dir_all(&collections_dir).map_err(|e| e.to_string())?;
+fn create_collection(app: tauri::AppHandle, name: String) -> Result<String, AppError> {
+  create_collection_with(&app, name, None)
+}
 
-  // Проверяем уникальность названия
-  let existing = list_collections(app.clone())?;
-  if existing.iter().any(|c| c["name"].as_str() == Some(&name)) {
-    return Err(format!("Коллекция с названием '{}' уже существует", name));
+/// Создать зашифрованную коллекцию и сразу разблокировать её. Пароль не сохраняется — только хеш для проверки.
+#[tauri::command]
+fn create_encrypted_collection(app: tauri::AppHandle, name: String, password: String) -> Result<String, AppError> {
+  let encryption = crypto::EncryptionInfo::new(&password)?;
+  let id = create_collection_with(&app, name, Some(encryption.clone()))?;
+  crypto::unlock(&id, &encryption, &password)?;
+  Ok(id)
+}
+
+/// Создать затемнённую копию коллекции — ночную пару для расписания по Солнцу.
+/// `brightness` — оставляемая яркость в процентах (1–99).
+#[tauri::command(async)]
+fn create_dimmed_collection(
+  app: tauri::AppHandle,
+  collection_id: String,
+  name: String,
+  brightness: u8,
+) -> Result<CollectionMeta, AppError> {
+  let base = files_base_dir(&app)?;
+  let source = collection::read_meta(&collection_dir(&app, &collection_id)?)?;
+  if source.encryption.is_some() {
+    return Err(AppError::Unsupported { feature: "encrypted_collection" });
   }
+  let id = create_collection_with(&app, name, None)?;
+  let dir = collection_dir(&app, &id)?;
+  if let Err(e) = imaging::dim_collection(&base, &collection_id, &id, brightness) {
+    // Недоделанную копию убираем целиком вместе со ссылками на blobs
+    let store = blobs::BlobStore::new(&base);
+    if let Ok(meta) = collection::read_meta(&dir) {
+      for blob in meta.items.iter().filter_map(|item| item.blob.as_deref()) {
+        let _ = store.release(blob);
+      }
+    }
+    let _ = fs::remove_dir_all(&dir);
+    return Err(e.into());
+  }
+  collection::read_meta(&dir)
+}
+
+fn create_collection_with(
+  app: &tauri::AppHandle,
+  name: String,
+  encryption: Option<crypto::EncryptionInfo>,
+) -> Result<String, AppError> {
+  let collections_dir = files_base_dir(app)?.join("collections");
+  fs::create_dir_all(&collections_dir).map_err(|e| AppError::io(&collections_dir, e))?;
+  collection::with_names_lock(|| create_collection_locked(app, name, encryption))
+}
+
+fn create_collection_locked(
+  app: &tauri::AppHandle,
+  name: String,
+  encryption: Option<crypto::EncryptionInfo>,
+) -> Result<String, AppError> {
+  // Проверяем уникальность названия
+  ensure_unique_collection_name(app, &name, None)?;
 
   // Генерируем уникальный ID на основе имени и времени
-  let sanitized_name = name
-    .chars()
-    .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
-    .collect::<String>();
+  let sanitized_name = collection::sanitize_id(&name);
   let timestamp = std::time::SystemTime::now()
     .duration_since(std::time::UNIX_EPOCH)
     .unwrap()
@@ -921,31 +1519,26 @@ fn create_collection(app: tauri::AppHandle, name: String) -> Result<String, Stri
   // Проверяем уникальность ID папки
   let mut final_id = collection_id.clone();
   let mut counter = 0;
-  while collection_dir(&app, &final_id)?.exists() {
+  while collection_dir(app, &final_id)?.exists() {
     counter += 1;
     final_id = format!("{}_{}", collection_id, counter);
   }
 
   // Создаем папку коллекции
-  let dir = collection_dir(&app, &final_id)?;
-  fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+  let dir = collection_dir(app, &final_id)?;
+  fs::create_dir_all(&dir).map_err(|e| AppError::io(&dir, e))?;
 
   // Сохраняем метаданные (название) в файл
-  let meta_file = dir.join("_meta.json");
-  let meta = serde_json::json!({
-    "name": name,
-    "id": final_id,
-    "created_at": timestamp
-  });
-  fs::write(&meta_file, serde_json::to_string_pretty(&meta).unwrap())
-    .map_err(|e| e.to_string())?;
+  let mut meta = CollectionMeta::new(final_id.clone(), name, timestamp);
+  meta.encryption = encryption;
+  collection::write_meta(&dir, &meta)?;
 
   Ok(final_id)
 }
 
 /// Получить список всех коллекций
 #[tauri::command]
-fn list_collections(app: tauri::AppHandle) -> Result<Vec<serde_json::Value>, String> {
+fn list_collections(app: tauri::AppHandle) -> Result<Vec<CollectionMeta>, AppError> {
   let collections_dir = files_base_dir(&app)?.join("collections");
 
   if !collections_dir.exists() {
@@ -953,10 +1546,10 @@ fn list_collections(app: tauri::AppHandle) -> Result<Vec<serde_json::Value>, Str
   }
 
   let mut collections = Vec::new();
-  let entries = fs::read_dir(&collections_dir).map_err(|e| e.to_string())?;
+  let entries = fs::read_dir(&collections_dir).map_err(|e| AppError::io(&collections_dir, e))?;
 
   for entry in entries {
-    let entry = entry.map_err(|e| e.to_string())?;
+    let entry = entry.map_err(|e| AppError::io(&collections_dir, e))?;
     let path = entry.path();
 
     if path.is_dir() {
@@ -966,45 +1559,194 @@ fn list_collections(app: tauri::AppHandle) -> Result<Vec<serde_json::Value>, Str
         .map(String::from)
         .unwrap_or_default();
 
-      // Читаем метаданные
-      let meta_file = path.join("_meta.json");
-      if meta_file.exists() {
-        if let Ok(content) = fs::read_to_string(&meta_file) {
-          if let Ok(meta) = serde_json::from_str::<serde_json::Value>(&content) {
+      // Читаем метаданные (повреждённый файл восстанавливается из резервной копии)
+      if path.join(collection::META_FILE_NAME).exists() {
+        match collection::read_meta(&path) {
+          Ok(meta) => {
             collections.push(meta);
             continue;
           }
+          Err(e) => log::warn!("list_collections: {}", e),
         }
       }
 
       // Если метаданных нет, создаем базовую структуру
-      collections.push(serde_json::json!({
-        "id": collection_id,
-        "name": collection_id,
-        "created_at": 0
-      }));
+      collections.push(CollectionMeta::new(collection_id.clone(), collection_id, 0));
     }
   }
 
   // Сортируем по дате создания (новые сначала)
-  collections.sort_by(|a, b| {
-    let a_time = a["created_at"].as_u64().unwrap_or(0);
-    let b_time = b["created_at"].as_u64().unwrap_or(0);
-    b_time.cmp(&a_time)
-  });
+  collections.sort_by_key(|c| std::cmp::Reverse(c.created_at));
 
   Ok(collections)
 }
 
+/// Название не должно совпадать с другой коллекцией (`except_id` — переименовываемая).
+/// Вызывается под `collection::with_names_lock` вместе с записью названия.
+fn ensure_unique_collection_name(
+  app: &tauri::AppHandle,
+  name: &str,
+  except_id: Option<&str>,
+) -> Result<(), AppError> {
+  let existing = list_collections(app.clone())?;
+  if existing
+    .iter()
+    .any(|c| c.name == name && Some(c.id.as_str()) != except_id)
+  {
+    return Err(AppError::duplicate_name("collection", name));
+  }
+  Ok(())
+}
+
+/// Переименовать коллекцию. ID (папка) остаётся прежним.
 #[tauri::command]
-fn list_collection_files(app: tauri::AppHandle, collection_id: String) -> Result<Vec<String>, String> {
+fn rename_collection(app: tauri::AppHandle, collection_id: String, name: String) -> Result<CollectionMeta, AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  let name = name.trim().to_string();
+  collection::with_names_lock(|| {
+    ensure_unique_collection_name(&app, &name, Some(&collection_id))?;
+    collection::update_meta(&dir, |meta| {
+      meta.rename(&name)?;
+      Ok(meta.clone())
+    })
+  })
+}
+
+/// Изменить описание, обложку и акцентный цвет коллекции.
+#[tauri::command]
+fn update_collection_details(
+  app: tauri::AppHandle,
+  collection_id: String,
+  details: CollectionDetails,
+) -> Result<CollectionMeta, AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  collection::update_meta(&dir, |meta| {
+    meta.apply_details(details)?;
+    Ok(meta.clone())
+  })
+}
+
+/// Метаданные одной коллекции (с элементами).
+#[tauri::command]
+fn get_collection_meta(app: tauri::AppHandle, collection_id: String) -> Result<CollectionMeta, AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  if !dir.is_dir() {
+    return Err(AppError::not_found("collection", collection_id));
+  }
+  collection::read_meta(&dir)
+}
+
+/// Добавить элемент в `_meta.json`. Файл должен быть уже сохранён через save_file_to_collection.
+#[tauri::command]
+fn add_collection_item(
+  app: tauri::AppHandle,
+  collection_id: String,
+  item: NewCollectionItem,
+) -> Result<CollectionItem, AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  // Имя проверяется до обращения к диску: `../other/x.webp` не должен выйти из папки коллекции
+  paths::validate_segment(&item.file)?;
+  if item.blob.is_some() && collection::read_meta(&dir)?.encryption.is_some() {
+    return Err(format!("Encrypted collection '{}' cannot reference shared blobs", collection_id).into());
+  }
+  let added = match item.blob.clone() {
+    None => {
+      if !dir.join(&item.file).is_file() {
+        return Err(AppError::not_found("file", &item.file));
+      }
+      collection::update_meta(&dir, |meta| meta.push_item(item))?
+    }
+    Some(blob) => {
+      let store = blobs::BlobStore::new(&files_base_dir(&app)?);
+      store.retain(&blob)?;
+      collection::update_meta(&dir, |meta| meta.push_item(item)).inspect_err(|_| {
+        let _ = store.release(&blob);
+      })?
+    }
+  };
+  refresh_rotation(&app, &collection_id);
+  Ok(added)
+}
+
+/// Импортировать изображение с диска: декодирование, обрезка `crop`, уменьшение до `target_size`
+/// и WebP — в Rust; элемент сразу добавляется в `_meta.json`. Без crop берётся центр по пропорциям экрана.
+/// async — чтобы декодирование больших фото не блокировало главный поток.
+#[tauri::command(async)]
+fn import_image_to_collection(
+  app: tauri::AppHandle,
+  collection_id: String,
+  source_path: String,
+  crop: Option<collection::CropRect>,
+  target_size: Option<collection::Size>,
+) -> Result<CollectionItem, AppError> {
+  let item = imaging::import(&files_base_dir(&app)?, &collection_id, Path::new(&source_path), crop, target_size)?;
+  refresh_rotation(&app, &collection_id);
+  Ok(item)
+}
+
+/// Обновить элемент коллекции (поиск по id).
+#[tauri::command]
+fn update_collection_item(
+  app: tauri::AppHandle,
+  collection_id: String,
+  item: CollectionItem,
+) -> Result<CollectionItem, AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  let store = blobs::BlobStore::new(&files_base_dir(&app)?);
+  let new_blob = item.blob.clone();
+  if let Some(blob) = &new_blob {
+    store.retain(blob)?;
+  }
+  let old_blob = collection::update_meta(&dir, |meta| {
+    let old = meta.items.iter().find(|it| it.id == item.id).and_then(|it| it.blob.clone());
+    let updated = meta.replace_item(item)?;
+    Ok((old, updated))
+  });
+  match old_blob {
+    Ok((old, updated)) => {
+      if let Some(old) = old {
+        store.release(&old)?;
+      }
+      refresh_rotation(&app, &collection_id);
+      Ok(updated)
+    }
+    Err(e) => {
+      if let Some(blob) = &new_blob {
+        let _ = store.release(blob);
+      }
+      Err(e)
+    }
+  }
+}
+
+/// Удалить элемент из коллекции в корзину. Ссылка на блоб переходит к записи корзины.
+#[tauri::command]
+fn remove_collection_item(app: tauri::AppHandle, collection_id: String, item_id: u64) -> Result<(), AppError> {
+  let dir = collection_dir(&app, &collection_id)?;
+  let removed = collection::update_meta(&dir, |meta| meta.remove_item(item_id))?;
+  trash::Trash::new(&files_base_dir(&app)?).trash_item(&collection_id, removed)?;
+  refresh_rotation(&app, &collection_id);
+  Ok(())
+}
+
+#[tauri::command]
+fn list_collection_files(app: tauri::AppHandle, collection_id: String) -> Result<Vec<String>, AppError> {
   let dir = collection_dir(&app, &collection_id)?;
   if !dir.exists() {
     return Ok(vec![]);
   }
   let mut files = Vec::new();
-  for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
-    let entry = entry.map_err(|e| e.to_string())?;
+  if let Ok(meta) = collection::read_meta(&dir) {
+    files.extend(
+      meta
+        .items
+        .iter()
+        .filter(|it| it.blob.is_some())
+        .map(|it| it.relative_path(&collection_id)),
+    );
+  }
+  for entry in fs::read_dir(&dir).map_err(|e| AppError::io(&dir, e))? {
+    let entry = entry.map_err(|e| AppError::io(&dir, e))?;
     let path = entry.path();
     if path.is_file() {
       if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
@@ -1018,15 +1760,186 @@ fn list_collection_files(app: tauri::AppHandle, collection_id: String) -> Result
   Ok(files)
 }
 
+/// Переместить коллекцию в корзину. Блобы освобождаются только при окончательном удалении.
 #[tauri::command]
-fn delete_collection(app: tauri::AppHandle, collection_id: String) -> Result<(), String> {
+fn delete_collection(app: tauri::AppHandle, collection_id: String) -> Result<(), AppError> {
   let dir = collection_dir(&app, &collection_id)?;
   if dir.exists() {
-    fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    trash::Trash::new(&files_base_dir(&app)?).trash_collection(&collection_id)?;
+  }
+  crypto::lock(&collection_id);
+  Ok(())
+}
+
+/// Экспортировать коллекцию в ZIP (манифест + изображения). dest_path — путь, выбранный пользователем.
+#[tauri::command]
+fn export_collection_zip(app: tauri::AppHandle, collection_id: String, dest_path: String) -> Result<(), AppError> {
+  Ok(archive::export(&files_base_dir(&app)?, &collection_id, Path::new(&dest_path))?)
+}
+
+/// Импортировать коллекцию из ZIP. Совпадающие id и название получают суффикс.
+#[tauri::command]
+fn import_collection_zip(app: tauri::AppHandle, src_path: String) -> Result<CollectionMeta, AppError> {
+  collection::with_names_lock(|| {
+    let existing = list_collections(app.clone())?;
+    Ok(archive::import(&files_base_dir(&app)?, Path::new(&src_path), &existing)?)
+  })
+}
+
+/// Плейлисты — наборы коллекций с весами; запускаются как коллекция (rotation_start с id плейлиста).
+#[tauri::command]
+fn list_playlists(app: tauri::AppHandle) -> Result<Vec<playlist::Playlist>, AppError> {
+  playlist::Playlists::new(&files_base_dir(&app)?).list()
+}
+
+#[tauri::command]
+fn create_playlist(
+  app: tauri::AppHandle,
+  name: String,
+  members: Vec<playlist::PlaylistMember>,
+) -> Result<playlist::Playlist, AppError> {
+  let base = files_base_dir(&app)?;
+  let playlists = playlist::Playlists::new(&base);
+  let name = name.trim().to_string();
+  let created_at = collection::now_millis() / 1000;
+  // id не должен совпасть ни с плейлистом, ни с коллекцией: ротация принимает оба
+  let prefix = format!("playlist_{}_{}", collection::sanitize_id(&name), created_at);
+  let mut id = prefix.clone();
+  let mut counter = 0;
+  while playlists.get(&id)?.is_some() || paths::collection_dir(&base, &id)?.exists() {
+    counter += 1;
+    id = format!("{}_{}", prefix, counter);
+  }
+  let playlist = playlist::Playlist {
+    id,
+    name,
+    created_at,
+    members,
+  };
+  playlists.save(&playlist)?;
+  Ok(playlist)
+}
+
+/// Изменить название и состав плейлиста. Идущая по нему ротация продолжается с новым составом.
+#[tauri::command]
+fn update_playlist(
+  app: tauri::AppHandle,
+  playlist_id: String,
+  name: String,
+  members: Vec<playlist::PlaylistMember>,
+) -> Result<playlist::Playlist, AppError> {
+  let playlists = playlist::Playlists::new(&files_base_dir(&app)?);
+  let mut playlist = playlists
+    .get(&playlist_id)?
+    .ok_or_else(|| AppError::not_found("playlist", &playlist_id))?;
+  playlist.name = name.trim().to_string();
+  playlist.members = members;
+  playlists.save(&playlist)?;
+  refresh_rotation(&app, &playlist_id);
+  Ok(playlist)
+}
+
+/// Удалить плейлист. Коллекции не затрагиваются; ротация, идущая по плейлисту, останавливается.
+#[tauri::command]
+fn delete_playlist(
+  app: tauri::AppHandle,
+  rotation: tauri::State<'_, Arc<RotationLoop>>,
+  playlist_id: String,
+) -> Result<(), AppError> {
+  playlist::Playlists::new(&files_base_dir(&app)?).delete(&playlist_id)?;
+  let stopped = rotation.update(|state| {
+    let slots: Vec<_> = [RotationSlot::Home, RotationSlot::Lock]
+      .into_iter()
+      .filter(|&slot| state.engine(slot).is_some_and(|engine| engine.collection_id == playlist_id))
+      .collect();
+    for &slot in &slots {
+      state.clear(slot);
+    }
+    slots
+  })?;
+  for slot in stopped {
+    events::rotation_stopped(&app, slot);
+  }
+  Ok(())
+}
+
+/// Содержимое корзины (новые сверху). Заодно удаляет записи старше срока хранения.
+#[tauri::command]
+fn list_trash(app: tauri::AppHandle) -> Result<Vec<trash::TrashEntry>, AppError> {
+  let trash = trash::Trash::new(&files_base_dir(&app)?);
+  trash.purge_expired(collection::now_millis())?;
+  Ok(trash.list()?)
+}
+
+/// Вернуть запись из корзины на место. Возвращает восстановленную запись.
+#[tauri::command]
+fn restore_from_trash(app: tauri::AppHandle, entry_id: String) -> Result<trash::TrashEntry, AppError> {
+  let base = files_base_dir(&app)?;
+  let entry = collection::with_names_lock(|| trash::Trash::new(&base).restore(&entry_id))?;
+  if let trash::TrashedKind::Item { collection_id, .. } = &entry.kind {
+    refresh_rotation(&app, collection_id);
+  }
+  Ok(entry)
+}
+
+/// Окончательно удалить одну запись корзины или, без entry_id, всю корзину.
+#[tauri::command]
+fn empty_trash(app: tauri::AppHandle, entry_id: Option<String>) -> Result<(), AppError> {
+  let trash = trash::Trash::new(&files_base_dir(&app)?);
+  match entry_id {
+    Some(id) => trash.purge(&id),
+    None => Ok(trash.empty()?),
   }
+}
+
+/// Срок хранения в корзине, дней (0 — без автоочистки).
+#[tauri::command]
+fn get_trash_retention_days(app: tauri::AppHandle) -> Result<u32, AppError> {
+  Ok(trash::Trash::new(&files_base_dir(&app)?).retention_days())
+}
+
+#[tauri::command]
+fn set_trash_retention_days(app: tauri::AppHandle, days: u32) -> Result<(), AppError> {
+  let trash = trash::Trash::new(&files_base_dir(&app)?);
+  trash.set_retention_days(days)?;
+  trash.purge_expired(collection::now_millis())?;
   Ok(())
 }
 
+/// История смен обоев (новые сверху): не больше `limit` записей, не раньше `since` (мс).
+#[tauri::command]
+fn get_wallpaper_history(
+  app: tauri::AppHandle,
+  limit: Option<usize>,
+  since: Option<u64>,
+) -> Result<Vec<history::HistoryEntry>, AppError> {
+  Ok(history::History::new(&files_base_dir(&app)?).list(limit, since)?)
+}
+
+/// Срок хранения истории, дней (0 — только ограничение по числу записей).
+#[tauri::command]
+fn get_history_retention_days(app: tauri::AppHandle) -> Result<u32, AppError> {
+  Ok(history::History::new(&files_base_dir(&app)?).retention_days())
+}
+
+#[tauri::command]
+fn set_history_retention_days(app: tauri::AppHandle, days: u32) -> Result<(), AppError> {
+  let history = history::History::new(&files_base_dir(&app)?);
+  history.set_retention_days(days)?;
+  history.purge_expired(collection::now_millis())?;
+  Ok(())
+}
+
+/// Сверить счётчики ссылок хранилища с метаданными коллекций и корзины и убрать блобы без ссылок.
+fn reconcile_blob_store(app: &tauri::AppHandle) -> Result<(), AppError> {
+  let base = files_base_dir(app)?;
+  let mut refs = collection::collect_blob_refs(&base.join("collections"))?;
+  for (blob, n) in trash::Trash::new(&base).blob_refs()? {
+    *refs.entry(blob).or_insert(0) += n;
+  }
+  Ok(blobs::BlobStore::new(&base).reconcile(&refs)?)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
@@ -1037,20 +1950,62 @@ pub fn run() {
     get_files_base_path,
     save_file_to_app,
     save_file_to_collection,
+    upload_begin,
+    upload_chunk,
+    upload_abort,
     get_file_name_from_path,
     read_file_from_app,
+    get_thumbnail,
     delete_app_file,
     set_device_wallpaper,
     set_device_wallpaper_target,
+    get_wallpaper_backend,
+    set_wallpaper_backend,
     create_collection,
+    create_dimmed_collection,
+    create_encrypted_collection,
+    unlock_collection,
+    lock_collection,
+    is_collection_unlocked,
     list_collections,
+    get_collection_meta,
+    rename_collection,
+    update_collection_details,
+    add_collection_item,
+    import_image_to_collection,
+    update_collection_item,
+    remove_collection_item,
     get_screen_size,
     list_collection_files,
     delete_collection,
+    export_collection_zip,
+    import_collection_zip,
+    list_playlists,
+    create_playlist,
+    update_playlist,
+    delete_playlist,
+    list_trash,
+    restore_from_trash,
+    empty_trash,
+    get_trash_retention_days,
+    set_trash_retention_days,
+    get_wallpaper_history,
+    get_history_retention_days,
+    set_history_retention_days,
     start_wallpaper_rotation_service,
     stop_wallpaper_rotation_service,
-    update_rotation_prefs,
     get_wallpaper_rotation_state,
+    rotation_start,
+    rotation_advance,
+    rotation_tick,
+    rotation_set_schedule,
+    rotation_set_no_repeat,
+    rotation_set_interval,
+    rotation_state,
+    rotation_stop,
+    rotate_next,
+    rotate_previous,
+    pin_current,
   ])
     .setup(|app| {
       if cfg!(debug_assertions) {
@@ -1060,6 +2015,74 @@ pub fn run() {
             .build(),
         )?;
       }
+      match files_base_dir(app.handle()) {
+        Ok(base) => match trash::Trash::new(&base).purge_expired(collection::now_millis()) {
+          Ok(n) if n > 0 => log::info!("trash: purged {} expired entries", n),
+          Ok(_) => {}
+          Err(e) => log::warn!("trash purge skipped: {}", e),
+        },
+        Err(e) => log::warn!("trash purge skipped: {}", e),
+      }
+      if let Err(e) = files_base_dir(app.handle()).and_then(|base| Ok(history::History::new(&base).purge_expired(collection::now_millis())?)) {
+        log::warn!("history purge skipped: {}", e);
+      }
+      if let Err(e) = reconcile_blob_store(app.handle()) {
+        log::warn!("blob store reconcile skipped: {}", e);
+      }
+      if let Err(e) = files_base_dir(app.handle()).and_then(|base| Ok(thumbnails::prune(&base)?)) {
+        log::warn!("thumbnail prune skipped: {}", e);
+      }
+      if let Err(e) = uploads(app.handle()).and_then(|uploads| uploads.clear()) {
+        log::warn!("unfinished uploads not removed: {}", e);
+      }
+      let state_file = app.path().app_data_dir()?.join(rotation_loop::STATE_FILE_NAME);
+      // На Android обои меняет WallpaperRotationService по тому же состоянию; на ПК — цикл,
+      // продолжающий сохранённую ротацию
+      #[cfg(target_os = "android")]
+      let rotation = {
+        let ctx = ndk_context::android_context();
+        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm() as *mut _).map_err(|e| AppError::jni(format!("JavaVM: {}", e)))? };
+        let mut env = vm.attach_current_thread().map_err(|e| AppError::jni(format!("JNI attach: {}", e)))?;
+        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut _) };
+        shared_rotation_android(&mut env, &context, state_file)
+      };
+      #[cfg(not(target_os = "android"))]
+      let rotation = Arc::new(RotationLoop::load(state_file));
+      #[cfg(not(target_os = "android"))]
+      {
+        let base = files_base_dir(app.handle())?;
+        let settings_file = wallpaper_settings_file(app.handle())?;
+        let handle = app.handle().clone();
+        rotation.spawn(move |change| {
+          let backend = wallpaper::backend_override(&settings_file);
+          let applied = paths::resolve(&base, &change.path)
+            .and_then(|file| wallpaper::set(&file, &change.target, backend.as_deref(), wallpaper::Tools::from_env()));
+          if let Err(e) = applied {
+            let message = e.to_string();
+            events::rotation_error(&handle, Some(change.slot), history::HistorySource::Desktop, message.clone());
+            return Err(message);
+          }
+          let entry = history::HistoryEntry {
+            at: collection::now_millis(),
+            path: change.path.clone(),
+            target: change.target.clone(),
+            source: history::HistorySource::Desktop,
+            collection_id: Some(change.collection_id.clone()),
+          };
+          if let Err(e) = history::History::new(&base).append(&entry) {
+            log::warn!("history: {}", e);
+          }
+          events::wallpaper_changed(&handle, events::WallpaperChanged {
+            entry,
+            slot: Some(change.slot),
+            state: Some(change.state.clone()),
+          });
+          Ok(())
+        });
+      }
+      #[cfg(target_os = "android")]
+      let _ = APP_HANDLE.set(app.handle().clone());
+      app.manage(rotation);
       Ok(())
     })
     .run(tauri::generate_context!())
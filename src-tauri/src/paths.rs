@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::AppError;
+
+/// Разрешить путь от фронтенда внутри `base`. Принимает относительный путь (collections/...)
+/// или абсолютный внутри base (для совместимости). Отклоняет `..`, выход через симлинки
+/// и соседние папки вроде `files2`. Целевой файл может ещё не существовать (для записи).
+pub fn resolve(base: &Path, path: &str) -> Result<PathBuf, AppError> {
+  if path.is_empty() {
+    return Err(AppError::path_not_allowed(path));
+  }
+  let requested = Path::new(path);
+  if requested
+    .components()
+    .any(|c| matches!(c, Component::ParentDir))
+  {
+    return Err(AppError::path_not_allowed(path));
+  }
+
+  let base = canonicalize_existing_prefix(base)?;
+  let joined = if requested.is_absolute() {
+    requested.to_path_buf()
+  } else {
+    let mut p = base.clone();
+    for c in requested.components() {
+      match c {
+        Component::Normal(part) => p.push(part),
+        Component::CurDir => {}
+        _ => return Err(AppError::path_not_allowed(path)),
+      }
+    }
+    p
+  };
+
+  let full = canonicalize_existing_prefix(&joined)?;
+  if !full.starts_with(&base) {
+    return Err(AppError::path_not_allowed(path));
+  }
+  Ok(full)
+}
+
+/// Папка коллекции base/collections/{collection_id}. id должен быть одним обычным компонентом пути.
+pub fn collection_dir(base: &Path, collection_id: &str) -> Result<PathBuf, AppError> {
+  validate_segment(collection_id)?;
+  resolve(base, &format!("collections/{}", collection_id))
+}
+
+/// Один компонент пути без разделителей, `.`/`..` и префиксов диска.
+pub fn validate_segment(segment: &str) -> Result<(), AppError> {
+  let mut components = Path::new(segment).components();
+  match (components.next(), components.next()) {
+    (Some(Component::Normal(part)), None)
+      if part == segment && !segment.contains(['/', '\\']) =>
+    {
+      Ok(())
+    }
+    _ => Err(AppError::path_not_allowed(segment)),
+  }
+}
+
+/// canonicalize для пути, хвост которого может ещё не существовать: канонизируется
+/// самый длинный существующий предок (раскрывая симлинки), оставшиеся компоненты дописываются.
+fn canonicalize_existing_prefix(path: &Path) -> Result<PathBuf, AppError> {
+  let mut existing = path.to_path_buf();
+  let mut tail = Vec::new();
+  loop {
+    match fs::canonicalize(&existing) {
+      Ok(canonical) => {
+        let mut result = canonical;
+        for part in tail.iter().rev() {
+          result.push(part);
+        }
+        return Ok(result);
+      }
+      Err(e) => {
+        // Висячий симлинк: цель неизвестна, считаем выход за пределы
+        if fs::symlink_metadata(&existing).is_ok() {
+          return Err(AppError::path_not_allowed(existing.to_string_lossy()));
+        }
+        match (existing.file_name(), existing.parent()) {
+          (Some(name), Some(parent)) => {
+            tail.push(name.to_os_string());
+            existing = parent.to_path_buf();
+          }
+          _ => return Err(AppError::io(path, e)),
+        }
+      }
+    }
+  }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use std::os::unix::fs::symlink;
+
+  struct Sandbox {
+    _tmp: tempfile::TempDir,
+    root: PathBuf,
+    base: PathBuf,
+  }
+
+  fn sandbox() -> Sandbox {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = fs::canonicalize(tmp.path()).unwrap();
+    let base = root.join("files");
+    fs::create_dir_all(base.join("collections/c1")).unwrap();
+    fs::write(base.join("collections/c1/a.webp"), b"img").unwrap();
+    fs::create_dir_all(root.join("files2")).unwrap();
+    fs::write(root.join("files2/secret"), b"secret").unwrap();
+    fs::write(root.join("outside"), b"outside").unwrap();
+    Sandbox { _tmp: tmp, root, base }
+  }
+
+  #[test]
+  fn resolves_relative_path_inside_base() {
+    let sb = sandbox();
+    let p = resolve(&sb.base, "collections/c1/a.webp").unwrap();
+    assert_eq!(p, sb.base.join("collections/c1/a.webp"));
+    let p = resolve(&sb.base, "./collections/c1/a.webp").unwrap();
+    assert_eq!(p, sb.base.join("collections/c1/a.webp"));
+  }
+
+  #[test]
+  fn resolves_missing_target_for_writes() {
+    let sb = sandbox();
+    let p = resolve(&sb.base, "collections/new/b.webp").unwrap();
+    assert_eq!(p, sb.base.join("collections/new/b.webp"));
+  }
+
+  #[test]
+  fn resolves_when_base_does_not_exist_yet() {
+    let sb = sandbox();
+    let base = sb.root.join("fresh");
+    let p = resolve(&base, "collections/x/a.webp").unwrap();
+    assert_eq!(p, base.join("collections/x/a.webp"));
+  }
+
+  #[test]
+  fn rejects_parent_dir_components() {
+    let sb = sandbox();
+    assert!(resolve(&sb.base, "collections/../../..").is_err());
+    assert!(resolve(&sb.base, "../outside").is_err());
+    assert!(resolve(&sb.base, "collections/c1/../../../files2/secret").is_err());
+    // `..`, которое «осталось бы» внутри base, тоже запрещено
+    assert!(resolve(&sb.base, "collections/c1/../c1/a.webp").is_err());
+    let abs = format!("{}/collections/../../outside", sb.base.display());
+    assert!(resolve(&sb.base, &abs).is_err());
+  }
+
+  #[test]
+  fn rejects_sibling_directory_with_common_prefix() {
+    let sb = sandbox();
+    let sibling = sb.root.join("files2/secret");
+    assert!(resolve(&sb.base, sibling.to_str().unwrap()).is_err());
+  }
+
+  #[test]
+  fn rejects_absolute_path_outside_base() {
+    let sb = sandbox();
+    assert!(resolve(&sb.base, "/etc/passwd").is_err());
+    assert!(resolve(&sb.base, sb.root.join("outside").to_str().unwrap()).is_err());
+  }
+
+  #[test]
+  fn accepts_absolute_path_inside_base() {
+    let sb = sandbox();
+    let abs = sb.base.join("collections/c1/a.webp");
+    assert_eq!(resolve(&sb.base, abs.to_str().unwrap()).unwrap(), abs);
+  }
+
+  #[test]
+  fn rejects_symlink_escapes() {
+    let sb = sandbox();
+    symlink(sb.root.join("files2"), sb.base.join("collections/link_dir")).unwrap();
+    symlink(sb.root.join("outside"), sb.base.join("collections/c1/link_file")).unwrap();
+    symlink(sb.root.join("missing"), sb.base.join("collections/c1/dangling")).unwrap();
+    assert!(resolve(&sb.base, "collections/link_dir/secret").is_err());
+    assert!(resolve(&sb.base, "collections/link_dir/new_file").is_err());
+    assert!(resolve(&sb.base, "collections/c1/link_file").is_err());
+    assert!(resolve(&sb.base, "collections/c1/dangling").is_err());
+  }
+
+  #[test]
+  fn accepts_symlink_that_stays_inside_base() {
+    let sb = sandbox();
+    symlink(sb.base.join("collections/c1"), sb.base.join("collections/alias")).unwrap();
+    let p = resolve(&sb.base, "collections/alias/a.webp").unwrap();
+    assert_eq!(p, sb.base.join("collections/c1/a.webp"));
+  }
+
+  #[test]
+  fn accepts_absolute_path_through_link_to_base() {
+    let sb = sandbox();
+    let link_base = sb.root.join("link_base");
+    symlink(&sb.base, &link_base).unwrap();
+    // После канонизации путь оказывается внутри base
+    let through_link = link_base.join("collections/c1/a.webp");
+    assert!(resolve(&sb.base, through_link.to_str().unwrap()).is_ok());
+  }
+
+  #[test]
+  fn rejects_empty_path() {
+    let sb = sandbox();
+    assert_eq!(resolve(&sb.base, "").unwrap_err(), AppError::path_not_allowed(""));
+  }
+
+  #[test]
+  fn collection_dir_rejects_bad_ids() {
+    let sb = sandbox();
+    for id in ["", ".", "..", "a/b", "../files2", "/etc", "a\\b"] {
+      assert!(collection_dir(&sb.base, id).is_err(), "id {:?} accepted", id);
+    }
+    assert_eq!(
+      collection_dir(&sb.base, "c1").unwrap(),
+      sb.base.join("collections/c1")
+    );
+  }
+}
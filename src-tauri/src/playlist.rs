@@ -0,0 +1,312 @@
+//! Плейлисты: несколько коллекций с весами, которые запускаются так же, как одна коллекция.
+//! Хранятся файлами playlists/{id}.json внутри base. Последовательность ротации собирается из
+//! последовательностей коллекций-участников и пересобирается при их изменении.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_file;
+use crate::collection;
+use crate::error::AppError;
+use crate::paths;
+use crate::rotation;
+
+/// Папка плейлистов внутри base.
+pub const PLAYLISTS_DIR: &str = "playlists";
+/// Вес участника: от 1 до MAX_WEIGHT.
+pub const MAX_WEIGHT: u32 = 100;
+/// Круг плейлиста не длиннее этого (или суммы размеров коллекций, если она больше): при сильно
+/// разных весах большая коллекция с малым весом показывается за круг не целиком.
+const MAX_ROUND: usize = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistMember {
+  pub collection_id: String,
+  /// Доля показов коллекции относительно остальных участников.
+  #[serde(default = "default_weight")]
+  pub weight: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Playlist {
+  pub id: String,
+  pub name: String,
+  /// Время создания, секунды с эпохи Unix.
+  #[serde(default)]
+  pub created_at: u64,
+  pub members: Vec<PlaylistMember>,
+}
+
+fn default_weight() -> u32 {
+  1
+}
+
+impl Playlist {
+  pub fn validate(&self) -> Result<(), String> {
+    paths::validate_segment(&self.id)?;
+    if self.name.trim().is_empty() {
+      return Err(format!("Playlist '{}' has empty name", self.id));
+    }
+    if self.members.is_empty() {
+      return Err(format!("Playlist '{}' has no collections", self.id));
+    }
+    for (i, member) in self.members.iter().enumerate() {
+      paths::validate_segment(&member.collection_id)?;
+      if !(1..=MAX_WEIGHT).contains(&member.weight) {
+        return Err(format!(
+          "Weight of '{}' must be between 1 and {}, got {}",
+          member.collection_id, MAX_WEIGHT, member.weight
+        ));
+      }
+      if self.members[..i].iter().any(|m| m.collection_id == member.collection_id) {
+        return Err(format!("Collection '{}' is listed twice in playlist '{}'", member.collection_id, self.id));
+      }
+    }
+    Ok(())
+  }
+
+  pub fn contains(&self, collection_id: &str) -> bool {
+    self.members.iter().any(|m| m.collection_id == collection_id)
+  }
+
+  /// Последовательность ротации по текущему содержимому коллекций. Удалённые и зашифрованные
+  /// коллекции пропускаются — плейлист продолжает работать на остальных.
+  pub fn sequence(&self, base: &Path) -> Vec<String> {
+    let members: Vec<(Vec<String>, u32)> = self
+      .members
+      .iter()
+      .filter_map(|member| {
+        let meta = paths::collection_dir(base, &member.collection_id)
+          .and_then(|dir| collection::read_meta(&dir));
+        match meta {
+          Ok(meta) => Some((rotation::build_sequence(&meta), member.weight)),
+          Err(e) => {
+            log::warn!("playlist '{}': skipping '{}': {}", self.id, member.collection_id, e);
+            None
+          }
+        }
+      })
+      .collect();
+    weighted_sequence(&members)
+  }
+}
+
+pub struct Playlists {
+  base: PathBuf,
+  root: PathBuf,
+}
+
+impl Playlists {
+  pub fn new(base: &Path) -> Self {
+    Self {
+      base: base.to_path_buf(),
+      root: base.join(PLAYLISTS_DIR),
+    }
+  }
+
+  /// Все плейлисты, старые сверху. Нечитаемые файлы пропускаются.
+  pub fn list(&self) -> Result<Vec<Playlist>, AppError> {
+    let entries = match fs::read_dir(&self.root) {
+      Ok(entries) => entries,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(e) => return Err(AppError::io(&self.root, e)),
+    };
+    let mut playlists = Vec::new();
+    for entry in entries {
+      let path = entry.map_err(|e| AppError::io(&self.root, e))?.path();
+      if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+        continue;
+      }
+      match read(&path) {
+        Ok(playlist) => playlists.push(playlist),
+        Err(e) => log::warn!("playlists: skipping {:?}: {}", path, e),
+      }
+    }
+    playlists.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    Ok(playlists)
+  }
+
+  /// Плейлист с этим id; `None`, если такого нет (например, это id коллекции).
+  pub fn get(&self, id: &str) -> Result<Option<Playlist>, AppError> {
+    let path = self.file(id)?;
+    if !path.is_file() {
+      return Ok(None);
+    }
+    Ok(Some(read(&path)?))
+  }
+
+  /// Сохранить плейлист. Названия плейлистов уникальны, все участники — существующие коллекции.
+  pub fn save(&self, playlist: &Playlist) -> Result<(), AppError> {
+    playlist.validate()?;
+    for member in &playlist.members {
+      let dir = paths::collection_dir(&self.base, &member.collection_id)?;
+      if !dir.join(collection::META_FILE_NAME).is_file() {
+        return Err(AppError::not_found("collection", &member.collection_id));
+      }
+    }
+    let name = playlist.name.trim();
+    if self.list()?.iter().any(|p| p.id != playlist.id && p.name.trim() == name) {
+      return Err(AppError::duplicate_name("playlist", name));
+    }
+    fs::create_dir_all(&self.root).map_err(|e| AppError::io(&self.root, e))?;
+    let json = serde_json::to_string_pretty(playlist).map_err(|e| e.to_string())?;
+    Ok(atomic_file::write(&self.file(&playlist.id)?, json.as_bytes())?)
+  }
+
+  pub fn delete(&self, id: &str) -> Result<(), AppError> {
+    let path = self.file(id)?;
+    match fs::remove_file(&path) {
+      Ok(()) => Ok(()),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(AppError::not_found("playlist", id)),
+      Err(e) => Err(AppError::io(&path, e)),
+    }
+  }
+
+  fn file(&self, id: &str) -> Result<PathBuf, AppError> {
+    paths::validate_segment(id)?;
+    Ok(self.root.join(format!("{}.json", id)))
+  }
+}
+
+fn read(path: &Path) -> Result<Playlist, String> {
+  let content = fs::read_to_string(path).map_err(|e| format!("read {:?}: {}", path, e))?;
+  let playlist: Playlist = serde_json::from_str(&content).map_err(|e| format!("parse {:?}: {}", path, e))?;
+  playlist.validate()?;
+  Ok(playlist)
+}
+
+/// Сведение последовательностей коллекций в одну по весам. Каждая коллекция получает в круге
+/// число мест, пропорциональное весу, и не меньше своего размера (если круг не упёрся в предел);
+/// изображения внутри коллекции идут по кругу в её порядке. Места распределяются равномерно
+/// (плавный взвешенный round-robin), так что коллекции чередуются, а не идут блоками.
+pub fn weighted_sequence(members: &[(Vec<String>, u32)]) -> Vec<String> {
+  let members: Vec<_> = members.iter().filter(|(seq, weight)| !seq.is_empty() && *weight > 0).collect();
+  let total_weight: u64 = members.iter().map(|(_, weight)| u64::from(*weight)).sum();
+  let total_len: usize = members.iter().map(|(seq, _)| seq.len()).sum();
+  let needed = members
+    .iter()
+    .map(|(seq, weight)| (seq.len() as u64 * total_weight).div_ceil(u64::from(*weight)))
+    .max()
+    .unwrap_or(0);
+  let round = needed.min(MAX_ROUND.max(total_len) as u64);
+  let slots: Vec<u64> = members
+    .iter()
+    .map(|(_, weight)| (round * u64::from(*weight) / total_weight).max(1))
+    .collect();
+  let total_slots: u64 = slots.iter().sum();
+
+  let mut current = vec![0i64; members.len()];
+  let mut taken = vec![0usize; members.len()];
+  let mut sequence = Vec::with_capacity(total_slots as usize);
+  for _ in 0..total_slots {
+    for (c, s) in current.iter_mut().zip(&slots) {
+      *c += *s as i64;
+    }
+    // Из равных берётся первый по списку — порядок детерминирован
+    let mut pick = 0;
+    for i in 1..current.len() {
+      if current[i] > current[pick] {
+        pick = i;
+      }
+    }
+    current[pick] -= total_slots as i64;
+    let seq = &members[pick].0;
+    sequence.push(seq[taken[pick] % seq.len()].clone());
+    taken[pick] += 1;
+  }
+  sequence
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn seq(prefix: &str, n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("{}{}", prefix, i)).collect()
+  }
+
+  fn count(sequence: &[String], prefix: &str) -> usize {
+    sequence.iter().filter(|p| p.starts_with(prefix)).count()
+  }
+
+  #[test]
+  fn weighted_sequence_follows_weights_and_interleaves() {
+    // Одна коллекция — её последовательность без изменений
+    assert_eq!(weighted_sequence(&[(seq("a", 3), 5)]), seq("a", 3));
+    assert!(weighted_sequence(&[(Vec::new(), 1)]).is_empty());
+
+    // Равные веса, разные размеры: поровну мест, меньшая коллекция повторяется
+    let s = weighted_sequence(&[(seq("a", 4), 1), (seq("b", 2), 1)]);
+    assert_eq!(s.len(), 8);
+    assert_eq!((count(&s, "a"), count(&s, "b")), (4, 4));
+    assert_eq!(&s[..4], ["a0", "b0", "a1", "b1"]);
+    assert!(seq("a", 4).iter().all(|p| s.contains(p)));
+
+    // Вес 3:1 — втрое больше показов, и каждое изображение хотя бы раз за круг
+    let s = weighted_sequence(&[(seq("a", 2), 3), (seq("b", 2), 1), (Vec::new(), 10)]);
+    assert_eq!(count(&s, "a"), 3 * count(&s, "b"));
+    assert!(seq("a", 2).iter().chain(&seq("b", 2)).all(|p| s.contains(p)));
+    assert!(!s.windows(4).any(|w| w.iter().all(|p| p.starts_with('a'))));
+
+    // Длина круга ограничена
+    let s = weighted_sequence(&[(seq("a", 1500), 1), (seq("b", 10), 100)]);
+    assert!(s.len() <= MAX_ROUND + 2);
+    assert!(count(&s, "a") >= 1 && count(&s, "b") > 100 * (count(&s, "a") - 1));
+  }
+
+  #[test]
+  fn store_validates_and_round_trips() {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path();
+    let playlists = Playlists::new(base);
+    assert!(playlists.list().unwrap().is_empty());
+
+    let member = |id: &str, weight| PlaylistMember {
+      collection_id: id.into(),
+      weight,
+    };
+    for id in ["c1", "c2"] {
+      let dir = base.join("collections").join(id);
+      fs::create_dir_all(&dir).unwrap();
+      let mut meta = collection::CollectionMeta::new(id.into(), "One".into(), 1);
+      for file in ["a.webp", "b.webp"] {
+        meta.push_item(serde_json::from_value(serde_json::json!({ "file": file })).unwrap()).unwrap();
+      }
+      collection::write_meta(&dir, &meta).unwrap();
+    }
+    let mut playlist = Playlist {
+      id: "mix_1".into(),
+      name: "Mix".into(),
+      created_at: 1,
+      members: vec![member("c1", 2), member("c2", 1)],
+    };
+    playlists.save(&playlist).unwrap();
+    assert_eq!(playlists.get("mix_1").unwrap(), Some(playlist.clone()));
+    assert_eq!(playlists.get("c1").unwrap(), None);
+    assert!(playlists.get("../x").is_err());
+
+    let duplicate = Playlist { id: "mix_2".into(), ..playlist.clone() };
+    assert_eq!(playlists.save(&duplicate).unwrap_err(), AppError::duplicate_name("playlist", "Mix"));
+    for bad in [vec![], vec![member("c1", 0)], vec![member("c1", 1), member("c1", 2)], vec![member("a/b", 1)]] {
+      assert!(playlists.save(&Playlist { members: bad, ..playlist.clone() }).is_err());
+    }
+
+    // Неизвестная коллекция в составе не сохраняется — ни при создании, ни при изменении
+    let unknown = Playlist { members: vec![member("c1", 1), member("c3", 1)], ..playlist.clone() };
+    assert_eq!(playlists.save(&unknown).unwrap_err(), AppError::not_found("collection", "c3"));
+    assert_eq!(playlists.get("mix_1").unwrap(), Some(playlist.clone()));
+
+    // Удалённая потом коллекция пропускается, остальные собираются по весам
+    fs::remove_dir_all(base.join("collections/c2")).unwrap();
+    assert_eq!(playlist.sequence(base), ["collections/c1/b.webp", "collections/c1/a.webp"]);
+
+    playlist.name = "Renamed".into();
+    assert_eq!(playlists.save(&playlist).unwrap_err(), AppError::not_found("collection", "c2"));
+    playlist.members.truncate(1);
+    playlists.save(&playlist).unwrap();
+    assert_eq!(playlists.list().unwrap(), vec![playlist]);
+    playlists.delete("mix_1").unwrap();
+    assert_eq!(playlists.delete("mix_1").unwrap_err(), AppError::not_found("playlist", "mix_1"));
+  }
+}
@@ -0,0 +1,751 @@
+//! Логика ротации обоев, общая для фронтенда, десктопа и Android-сервиса: порядок
+//! последовательности (очередь/случайно), переход к следующему, перемешивание и время следующей смены.
+//! Модуль не зависит от Tauri и платформы — Android-сервис получает решения через `rotation_loop`.
+//! Расписание (`Schedule`) считается по местному времени; методы `*_in` принимают часовой пояс явно.
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::collection::CollectionMeta;
+use crate::schedule::Schedule;
+
+/// Минимальный интервал смены обоев (ограничение AlarmManager на Android).
+pub const MIN_INTERVAL_MINUTES: u32 = 15;
+/// Смена считается наступившей чуть раньше срока: будильники и таймеры срабатывают неточно.
+pub const DUE_TOLERANCE_MS: u64 = 15_000;
+/// Если по расписанию менять нечего, состояние перепроверяется раз в сутки.
+const IDLE_RECHECK_MS: u64 = 24 * 60 * 60 * 1000;
+/// Окно без повторов по умолчанию: новый круг не начинается с только что показанного.
+pub const DEFAULT_NO_REPEAT: usize = 1;
+
+/// Порядок показа изображений коллекции.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationMode {
+  /// От новых к старым, по кругу.
+  #[default]
+  Queue,
+  /// Случайный порядок «мешком»: каждое изображение по разу за круг, после круга мешок
+  /// перемешивается заново, и начало нового круга не повторяет последние `no_repeat` показанных.
+  Random,
+}
+
+/// Состояние ротации. Сериализуется целиком — так оно лежит в файле состояния (`rotation_loop`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotationEngine {
+  #[serde(default)]
+  pub collection_id: String,
+  #[serde(default)]
+  pub mode: RotationMode,
+  #[serde(default = "default_interval")]
+  pub interval_minutes: u32,
+  /// Пути изображений относительно base, в порядке показа.
+  pub sequence: Vec<String>,
+  /// Индекс текущих обоев в `sequence`.
+  #[serde(default)]
+  pub index: usize,
+  /// Время последней смены, мс с эпохи Unix.
+  #[serde(default)]
+  pub last_change_at: Option<u64>,
+  /// Состояние генератора для перемешивания; 0 — ещё не засеян. Читается и из строки:
+  /// так его хранили prefs Android-сервиса прежних версий.
+  #[serde(default, deserialize_with = "u64_from_number_or_string")]
+  pub rng: u64,
+  /// Сколько последних показанных изображений не повторяется на стыке кругов (режим `Random`).
+  #[serde(default = "default_no_repeat")]
+  pub no_repeat: usize,
+  /// Последние показанные (не больше `no_repeat`), от старых к новым.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub recent: Vec<String>,
+  /// Расписание: какая коллекция в какое время и когда менять обои.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub schedule: Option<Schedule>,
+  /// Последовательности коллекций из расписания (очередью) — переключение без чтения с диска,
+  /// так же и в Android-сервисе.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub collections: BTreeMap<String, Vec<String>>,
+  /// Текущие обои закреплены до этого времени (мс): ни интервал, ни расписание их не меняют.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pinned_until: Option<u64>,
+}
+
+/// Снимок для фронтенда: состояние плюс вычисленные текущий путь и срок следующей смены.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RotationState {
+  pub collection_id: String,
+  pub mode: RotationMode,
+  pub interval_minutes: u32,
+  pub sequence: Vec<String>,
+  pub index: usize,
+  pub current: Option<String>,
+  pub last_change_at: Option<u64>,
+  pub next_due_at: u64,
+  pub schedule: Option<Schedule>,
+  pub no_repeat: usize,
+  /// Состояние мешка для Android-сервиса: генератор (строкой) и последние показанные.
+  pub rng: String,
+  pub recent: Vec<String>,
+  /// До какого времени закреплены текущие обои; None — не закреплены (или срок прошёл).
+  pub pinned_until: Option<u64>,
+}
+
+fn default_interval() -> u32 {
+  MIN_INTERVAL_MINUTES
+}
+
+fn default_no_repeat() -> usize {
+  DEFAULT_NO_REPEAT
+}
+
+fn u64_from_number_or_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Raw {
+    Number(u64),
+    Text(String),
+  }
+  match Raw::deserialize(deserializer)? {
+    Raw::Number(n) => Ok(n),
+    Raw::Text(s) => s.parse().map_err(serde::de::Error::custom),
+  }
+}
+
+impl RotationEngine {
+  /// Ротация по готовой последовательности; в режиме `Random` она сразу перемешивается.
+  pub fn new(collection_id: &str, mode: RotationMode, interval_minutes: u32, sequence: Vec<String>, seed: u64) -> Self {
+    let mut engine = Self {
+      collection_id: collection_id.to_string(),
+      mode,
+      interval_minutes: interval_minutes.max(MIN_INTERVAL_MINUTES),
+      sequence,
+      index: 0,
+      last_change_at: None,
+      rng: seed,
+      no_repeat: DEFAULT_NO_REPEAT,
+      recent: Vec::new(),
+      schedule: None,
+      collections: BTreeMap::new(),
+      pinned_until: None,
+    };
+    if mode == RotationMode::Random {
+      engine.shuffle(false);
+    }
+    engine
+  }
+
+  pub fn current(&self) -> Option<&str> {
+    self.sequence.get(self.index).map(String::as_str)
+  }
+
+  /// Задать расписание и последовательности его коллекций (`build_sequence`).
+  pub fn set_schedule(&mut self, schedule: Option<Schedule>, collections: BTreeMap<String, Vec<String>>) {
+    self.collections = if schedule.is_some() { collections } else { BTreeMap::new() };
+    self.schedule = schedule;
+  }
+
+  /// Коллекция, на которую расписание требует переключиться сейчас.
+  fn scheduled_switch<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> Option<&str> {
+    let id = self.schedule.as_ref()?.active_collection(now + DUE_TOLERANCE_MS, tz)?;
+    let ready = id != self.collection_id && self.collections.get(id).is_some_and(|s| !s.is_empty());
+    ready.then_some(id)
+  }
+
+  /// Расписание сейчас не выбирает ни одной коллекции — обои не меняются.
+  fn scheduled_idle<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> bool {
+    self
+      .schedule
+      .as_ref()
+      .is_some_and(|s| s.active_collection(now + DUE_TOLERANCE_MS, tz).is_none())
+  }
+
+  /// Очередная смена внутри коллекции: по cron из расписания или через интервал.
+  fn regular_due_at<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> Option<u64> {
+    let Some(last) = self.last_change_at else {
+      return Some(now);
+    };
+    match self.schedule.as_ref().filter(|s| s.change_at.is_some()) {
+      // Смена отмечается чуть раньше срабатывания (допуск) — то же срабатывание не должно повториться
+      Some(schedule) => schedule.next_change(last + DUE_TOLERANCE_MS, tz),
+      None => Some(last.saturating_add(self.interval_minutes.max(MIN_INTERVAL_MINUTES) as u64 * 60_000)),
+    }
+  }
+
+  /// Перейти к следующему изображению и отметить смену. В режиме `Random` после
+  /// последнего элемента последовательность перемешивается и начинается заново.
+  pub fn advance(&mut self, now: u64) -> Option<&str> {
+    self.advance_in(now, &Local)
+  }
+
+  /// `advance` в заданном часовом поясе. Если расписание выбрало другую коллекцию,
+  /// ротация переключается на её первое изображение. Ручной переход снимает закрепление.
+  pub fn advance_in<Tz: TimeZone>(&mut self, now: u64, tz: &Tz) -> Option<&str> {
+    self.pinned_until = None;
+    if let Some(id) = self.scheduled_switch(now, tz).map(String::from) {
+      self.sequence = self.collections[&id].clone();
+      self.collection_id = id;
+      self.index = 0;
+      if self.mode == RotationMode::Random {
+        self.shuffle(false);
+      }
+      self.last_change_at = Some(now);
+      self.remember_current();
+      return self.current();
+    }
+    if self.sequence.is_empty() {
+      return None;
+    }
+    if self.mode == RotationMode::Random && self.index + 1 >= self.sequence.len() {
+      // Состояние из старых версий могло не записать текущее в `recent`
+      self.remember_current();
+      self.shuffle(true);
+      self.index = 0;
+    } else {
+      self.index = (self.index + 1) % self.sequence.len();
+    }
+    self.last_change_at = Some(now);
+    self.remember_current();
+    self.current()
+  }
+
+  /// Вернуться к предыдущему изображению последовательности и отметить смену. В режиме `Random`
+  /// с начала круга — к последнему элементу текущего мешка: прошлый круг уже перемешан.
+  pub fn step_back(&mut self, now: u64) -> Option<&str> {
+    if self.sequence.is_empty() {
+      return None;
+    }
+    self.pinned_until = None;
+    self.index = (self.index + self.sequence.len() - 1) % self.sequence.len();
+    self.last_change_at = Some(now);
+    self.remember_current();
+    self.current()
+  }
+
+  /// Закрепить текущие обои на `minutes` минут; 0 снимает закрепление. После срока следующая
+  /// смена наступает сразу, если интервал уже прошёл.
+  pub fn pin(&mut self, now: u64, minutes: u32) {
+    self.pinned_until = (minutes > 0).then(|| now + minutes as u64 * 60_000);
+  }
+
+  /// Срок закрепления, если он ещё не прошёл.
+  pub fn pinned_at(&self, now: u64) -> Option<u64> {
+    self.pinned_until.filter(|&until| until > now)
+  }
+
+  /// Изображения коллекции (или плейлиста) `id` изменились: обновляется её последовательность для
+  /// расписания и, если ротация идёт по ней сейчас, текущая — без перезапуска.
+  pub fn update_collection(&mut self, id: &str, sequence: Vec<String>) {
+    if let Some(entry) = self.collections.get_mut(id) {
+      *entry = sequence.clone();
+    }
+    if self.collection_id == id {
+      self.refresh_sequence(sequence);
+    }
+  }
+
+  /// Заменить последовательность идущей ротации. Текущие обои остаются текущими, если они есть
+  /// в новой. В режиме `Random` перемешанный порядок сохраняется, а новые изображения попадают
+  /// на случайные места в ещё не показанной части круга.
+  pub fn refresh_sequence(&mut self, sequence: Vec<String>) {
+    let current = self.current().map(String::from);
+    let old = std::mem::take(&mut self.sequence);
+    let index = match self.mode {
+      RotationMode::Queue => {
+        self.sequence = sequence;
+        current
+          .and_then(|c| self.sequence.iter().position(|p| *p == c))
+          .unwrap_or(self.index)
+      }
+      RotationMode::Random => {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        for path in sequence {
+          *counts.entry(path).or_default() += 1;
+        }
+        // Сколько оставшихся элементов уже показано в этом круге (до текущего включительно)
+        let mut shown = 0;
+        let mut kept_current = None;
+        let mut kept = Vec::with_capacity(old.len());
+        for (i, path) in old.into_iter().enumerate() {
+          let Some(n) = counts.get_mut(&path).filter(|n| **n > 0) else {
+            continue;
+          };
+          *n -= 1;
+          if i == self.index {
+            kept_current = Some(kept.len());
+          }
+          if i <= self.index {
+            shown += 1;
+          }
+          kept.push(path);
+        }
+        if self.rng == 0 {
+          self.rng = seed_from_time();
+        }
+        for (path, n) in counts {
+          for _ in 0..n {
+            let pos = shown + (self.next_random() % (kept.len() - shown + 1) as u64) as usize;
+            kept.insert(pos, path.clone());
+          }
+        }
+        self.sequence = kept;
+        kept_current.unwrap_or(shown.saturating_sub(1))
+      }
+    };
+    self.index = index.min(self.sequence.len().saturating_sub(1));
+    let sequence = &self.sequence;
+    self.recent.retain(|p| sequence.contains(p));
+  }
+
+  /// Окно без повторов; больше `len - 1` гарантировать нельзя — лишнее игнорируется.
+  pub fn set_no_repeat(&mut self, no_repeat: usize) {
+    self.no_repeat = no_repeat;
+    let excess = self.recent.len().saturating_sub(no_repeat);
+    self.recent.drain(..excess);
+  }
+
+  /// Запомнить показанное для окна без повторов.
+  fn remember_current(&mut self) {
+    if self.mode != RotationMode::Random || self.no_repeat == 0 {
+      return;
+    }
+    let Some(current) = self.current().map(String::from) else {
+      return;
+    };
+    if self.recent.last() != Some(&current) {
+      self.recent.retain(|p| *p != current);
+      self.recent.push(current);
+    }
+    let excess = self.recent.len().saturating_sub(self.no_repeat);
+    self.recent.drain(..excess);
+  }
+
+  /// Отметить, что текущие обои только что установлены (первая установка при запуске).
+  pub fn mark_changed(&mut self, now: u64) {
+    self.last_change_at = Some(now);
+  }
+
+  /// Начало ротации: текущие обои ставятся сразу. С расписанием — из коллекции, которую оно выбирает сейчас.
+  pub fn start(&mut self, now: u64) {
+    self.start_in(now, &Local)
+  }
+
+  pub fn start_in<Tz: TimeZone>(&mut self, now: u64, tz: &Tz) {
+    if self.scheduled_switch(now, tz).is_some() {
+      self.advance_in(now, tz);
+    } else if self.current().is_some() {
+      self.mark_changed(now);
+      self.remember_current();
+    }
+  }
+
+  pub fn set_interval(&mut self, interval_minutes: u32) {
+    self.interval_minutes = interval_minutes.max(MIN_INTERVAL_MINUTES);
+  }
+
+  /// Время следующей смены, мс. Если обои ещё не ставились — сразу.
+  pub fn next_due_at(&self, now: u64) -> u64 {
+    self.next_due_at_in(now, &Local)
+  }
+
+  /// Когда пересмотреть ротацию: очередная смена или ближайшая граница окна расписания,
+  /// но не раньше конца закрепления.
+  pub fn next_due_at_in<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> u64 {
+    match self.pinned_at(now) {
+      Some(until) => self.unpinned_due_at(until, tz).max(until),
+      None => self.unpinned_due_at(now, tz),
+    }
+  }
+
+  fn unpinned_due_at<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> u64 {
+    if self.scheduled_switch(now, tz).is_some() {
+      return now;
+    }
+    let regular = if self.sequence.is_empty() || self.scheduled_idle(now, tz) {
+      None
+    } else {
+      self.regular_due_at(now, tz)
+    };
+    let boundary = self.schedule.as_ref().and_then(|s| s.next_boundary(now + DUE_TOLERANCE_MS, tz));
+    [regular, boundary].into_iter().flatten().min().unwrap_or(now + IDLE_RECHECK_MS)
+  }
+
+  pub fn is_due(&self, now: u64) -> bool {
+    self.is_due_in(now, &Local)
+  }
+
+  /// Пора ли менять обои. Граница окна, не меняющая коллекцию, сменой не считается.
+  pub fn is_due_in<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> bool {
+    if self.pinned_at(now).is_some() {
+      return false;
+    }
+    if self.scheduled_switch(now, tz).is_some() {
+      return true;
+    }
+    if self.sequence.is_empty() || self.scheduled_idle(now, tz) {
+      return false;
+    }
+    self.regular_due_at(now, tz).is_some_and(|due| now + DUE_TOLERANCE_MS >= due)
+  }
+
+  pub fn state(&self, now: u64) -> RotationState {
+    self.state_in(now, &Local)
+  }
+
+  pub fn state_in<Tz: TimeZone>(&self, now: u64, tz: &Tz) -> RotationState {
+    RotationState {
+      collection_id: self.collection_id.clone(),
+      mode: self.mode,
+      interval_minutes: self.interval_minutes,
+      sequence: self.sequence.clone(),
+      index: self.index,
+      current: self.current().map(String::from),
+      last_change_at: self.last_change_at,
+      next_due_at: self.next_due_at_in(now, tz),
+      schedule: self.schedule.clone(),
+      no_repeat: self.no_repeat,
+      rng: self.rng.to_string(),
+      recent: self.recent.clone(),
+      pinned_until: self.pinned_at(now),
+    }
+  }
+
+  /// Перемешать мешок (Фишер — Йейтс). С `avoid_recent` позиция `p` нового круга не занимается
+  /// ни одним из последних `window - p` показанных: на их место выдвигается ближайшее
+  /// подходящее из перемешанного хвоста. Так между повторами всегда не меньше `window` смен.
+  fn shuffle(&mut self, avoid_recent: bool) {
+    if self.rng == 0 {
+      self.rng = seed_from_time();
+    }
+    for i in (1..self.sequence.len()).rev() {
+      let j = (self.next_random() % (i as u64 + 1)) as usize;
+      self.sequence.swap(i, j);
+    }
+    if !avoid_recent {
+      return;
+    }
+    let window = self.no_repeat.min(self.recent.len()).min(self.sequence.len().saturating_sub(1));
+    for p in 0..window {
+      let forbidden = &self.recent[self.recent.len() - (window - p)..];
+      if !forbidden.contains(&self.sequence[p]) {
+        continue;
+      }
+      // Подходящее найдётся всегда: запрещённых window - p, а это меньше оставшихся len - p
+      if let Some(j) = (p + 1..self.sequence.len()).find(|&j| !forbidden.contains(&self.sequence[j])) {
+        let item = self.sequence.remove(j);
+        self.sequence.insert(p, item);
+      }
+    }
+  }
+
+  /// splitmix64: быстрый и воспроизводимый по сохранённому состоянию.
+  fn next_random(&mut self) -> u64 {
+    self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = self.rng;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+}
+
+/// Пути изображений коллекции в порядке очереди: от новых к старым.
+/// Зашифрованные коллекции не ротируются — сервис обоев не может расшифровать файлы.
+pub fn build_sequence(meta: &CollectionMeta) -> Vec<String> {
+  if meta.encryption.is_some() {
+    return Vec::new();
+  }
+  let mut items: Vec<_> = meta.items.iter().collect();
+  items.sort_by_key(|it| std::cmp::Reverse(if it.order > 0 { it.order } else { it.id }));
+  items.iter().map(|it| it.relative_path(&meta.id)).collect()
+}
+
+/// Результат шага ротации (команда `rotation_tick`): сменились ли обои и новое состояние.
+#[derive(Debug, Serialize)]
+pub struct Tick {
+  pub due: bool,
+  #[serde(flatten)]
+  pub state: RotationState,
+}
+
+fn seed_from_time() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_nanos() as u64)
+    .unwrap_or(1)
+    | 1
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::collection::NewCollectionItem;
+
+  const MIN: u64 = 60_000;
+
+  fn paths(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("p{}", i)).collect()
+  }
+
+  #[test]
+  fn queue_sequence_is_newest_first() {
+    let mut meta = CollectionMeta::new("c1".into(), "One".into(), 1);
+    for file in ["a.webp", "b.webp", "c.webp"] {
+      let item: NewCollectionItem = serde_json::from_value(serde_json::json!({ "file": file })).unwrap();
+      meta.push_item(item).unwrap();
+    }
+    meta.items[1].blob = Some(format!("{}.webp", "ab".repeat(32)));
+    assert_eq!(
+      build_sequence(&meta),
+      vec![
+        "collections/c1/c.webp".to_string(),
+        format!("blobs/ab/{}.webp", "ab".repeat(32)),
+        "collections/c1/a.webp".to_string(),
+      ]
+    );
+
+    meta.encryption = Some(crate::crypto::EncryptionInfo::new("pw").unwrap());
+    assert!(build_sequence(&meta).is_empty());
+  }
+
+  #[test]
+  fn queue_advances_in_a_circle() {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 60, paths(3), 7);
+    assert_eq!(engine.current(), Some("p0"));
+    assert_eq!(engine.advance(1), Some("p1"));
+    assert_eq!(engine.advance(2), Some("p2"));
+    assert_eq!(engine.advance(3), Some("p0"));
+    assert_eq!(engine.last_change_at, Some(3));
+    assert_eq!(engine.sequence, paths(3));
+  }
+
+  #[test]
+  fn random_reshuffles_after_full_round() {
+    let mut engine = RotationEngine::new("c1", RotationMode::Random, 60, paths(10), 42);
+    let mut first_round: Vec<String> = engine.sequence.clone();
+    first_round.sort();
+    assert_eq!(first_round, paths(10));
+
+    // Тот же seed — та же последовательность
+    assert_eq!(RotationEngine::new("c1", RotationMode::Random, 60, paths(10), 42).sequence, engine.sequence);
+
+    let before = engine.sequence.clone();
+    let mut shown = vec![engine.current().unwrap().to_string()];
+    for t in 0..9 {
+      shown.push(engine.advance(t).unwrap().to_string());
+    }
+    assert_eq!(shown, before);
+    let last = engine.current().unwrap().to_string();
+    let next = engine.advance(100).unwrap().to_string();
+    assert_eq!(engine.index, 0);
+    assert_ne!(next, last, "new round must not repeat the last wallpaper");
+    assert_ne!(engine.sequence, before);
+  }
+
+  #[test]
+  fn shuffle_bag_respects_no_repeat_window() {
+    for (n, window) in [(10, 5), (6, 5), (4, 10), (2, 1)] {
+      let mut engine = RotationEngine::new("c1", RotationMode::Random, 60, paths(n), 99);
+      engine.set_no_repeat(window);
+      engine.start_in(0, &chrono::Utc);
+      let mut shown = vec![engine.current().unwrap().to_string()];
+      for t in 1..200 {
+        shown.push(engine.advance_in(t, &chrono::Utc).unwrap().to_string());
+      }
+      let effective = window.min(n - 1);
+      for (i, path) in shown.iter().enumerate() {
+        let from = i.saturating_sub(effective);
+        assert!(!shown[from..i].contains(path), "n={} window={}: {} repeats at {}", n, window, path, i);
+      }
+      // Каждый круг — все изображения по разу
+      for round in shown.chunks_exact(n) {
+        let mut round = round.to_vec();
+        round.sort();
+        assert_eq!(round, paths(n));
+      }
+      assert!(engine.recent.len() <= window);
+    }
+  }
+
+  #[test]
+  fn bag_state_round_trips_through_service() {
+    // Мешок продолжается после сохранения в файл состояния и чтения обратно (rng строкой — из prefs прежних версий)
+    let mut local = RotationEngine::new("c1", RotationMode::Random, 15, paths(5), 7);
+    local.set_no_repeat(3);
+    local.start_in(0, &chrono::Utc);
+    for t in 1..4 {
+      local.advance_in(t * 15 * MIN, &chrono::Utc);
+    }
+    let mut state = serde_json::to_value(&local).unwrap();
+    state["rng"] = serde_json::Value::String(local.rng.to_string());
+
+    let mut now = 3 * 15 * MIN;
+    for _ in 0..12 {
+      now += 15 * MIN;
+      let mut saved: RotationEngine = serde_json::from_value(state).unwrap();
+      assert!(saved.is_due(now));
+      assert_eq!(saved.advance(now), local.advance(now));
+      state = serde_json::to_value(&saved).unwrap();
+    }
+    assert_eq!(state["rng"], local.rng);
+  }
+
+  #[test]
+  fn next_due_and_interval_floor() {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 5, paths(2), 1);
+    assert_eq!(engine.interval_minutes, MIN_INTERVAL_MINUTES);
+    assert_eq!(engine.next_due_at(1_000), 1_000);
+    assert!(engine.is_due(1_000));
+
+    engine.mark_changed(0);
+    assert_eq!(engine.next_due_at(1_000), 15 * MIN);
+    assert!(!engine.is_due(14 * MIN));
+    assert!(engine.is_due(15 * MIN - DUE_TOLERANCE_MS));
+
+    engine.set_interval(60);
+    assert_eq!(engine.next_due_at(0), 60 * MIN);
+    assert!(!RotationEngine::new("c1", RotationMode::Queue, 60, vec![], 1).is_due(0));
+  }
+
+  #[test]
+  fn manual_steps_and_pin_reschedule() {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 15, paths(3), 1);
+    engine.start(0);
+    assert_eq!(engine.step_back(MIN), Some("p2"));
+    assert_eq!(engine.step_back(2 * MIN), Some("p1"));
+    assert_eq!(engine.advance(3 * MIN), Some("p2"));
+    // Ручная смена переносит следующую на интервал от неё
+    assert_eq!(engine.next_due_at(3 * MIN), 18 * MIN);
+
+    // Закрепление держит обои дольше интервала, а после срока смена наступает сразу
+    engine.pin(3 * MIN, 60);
+    assert_eq!(engine.state(4 * MIN).pinned_until, Some(63 * MIN));
+    assert!(!engine.is_due(30 * MIN));
+    assert_eq!(engine.next_due_at(30 * MIN), 63 * MIN);
+    assert!(engine.is_due(63 * MIN));
+    assert_eq!(engine.state(63 * MIN).pinned_until, None);
+
+    // Закрепление переживает сохранение в файл состояния, а переход вперёд его снимает
+    engine.pin(70 * MIN, 30);
+    let saved: RotationEngine = serde_json::from_str(&serde_json::to_string(&engine).unwrap()).unwrap();
+    assert!(!saved.is_due(80 * MIN));
+    assert_eq!(saved.state(80 * MIN).pinned_until, Some(100 * MIN));
+    assert_eq!(saved.next_due_at(80 * MIN), 100 * MIN);
+    engine.advance(81 * MIN);
+    assert_eq!(engine.pinned_until, None);
+    engine.pin(82 * MIN, 30);
+    engine.pin(83 * MIN, 0);
+    assert_eq!(engine.pinned_until, None);
+    assert_eq!(RotationEngine::new("c1", RotationMode::Queue, 15, vec![], 1).step_back(0), None);
+  }
+
+  #[test]
+  fn refresh_keeps_position_and_adds_to_unshown_part() {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 15, paths(3), 1);
+    engine.advance(0);
+    // Новое изображение в начале очереди (самое новое) — текущее остаётся текущим
+    engine.update_collection("c1", ["new", "p0", "p1", "p2"].map(String::from).to_vec());
+    assert_eq!((engine.index, engine.current()), (2, Some("p1")));
+    engine.update_collection("other", paths(1));
+    assert_eq!(engine.sequence.len(), 4);
+
+    let mut engine = RotationEngine::new("c1", RotationMode::Random, 15, paths(6), 5);
+    engine.set_no_repeat(3);
+    engine.start(0);
+    engine.advance(1);
+    engine.advance(2);
+    let shown = engine.sequence[..3].to_vec();
+    let removed = engine.sequence[4].clone();
+    let mut fresh: Vec<String> = paths(6).into_iter().filter(|p| *p != removed).collect();
+    fresh.extend(["n1".to_string(), "n2".to_string()]);
+    engine.refresh_sequence(fresh);
+    assert_eq!(engine.sequence[..3], shown);
+    assert_eq!(engine.current(), Some(shown[2].as_str()));
+    assert_eq!(engine.sequence.len(), 7);
+    assert!(!engine.sequence.contains(&removed));
+    assert!(engine.sequence[3..].contains(&"n1".to_string()) && engine.sequence[3..].contains(&"n2".to_string()));
+
+    // Удалили текущее и показанные: позиция — перед непоказанной частью
+    engine.refresh_sequence(engine.sequence[3..].to_vec());
+    assert_eq!(engine.index, 0);
+    assert!(engine.recent.is_empty());
+    engine.refresh_sequence(Vec::new());
+    assert_eq!((engine.index, engine.current()), (0, None));
+  }
+
+  #[test]
+  fn schedule_switches_collections_and_uses_cron() {
+    use crate::schedule::{Schedule, ScheduleRule};
+    use chrono_tz::Europe::Berlin;
+
+    let at = |d: u32, h: u32, mi: u32| Berlin.with_ymd_and_hms(2026, 10, d, h, mi, 0).unwrap().timestamp_millis() as u64;
+    let schedule = Schedule {
+      rules: vec![ScheduleRule {
+        collection_id: "A".into(),
+        days: vec![1, 2, 3, 4, 5],
+        start: "07:00".into(),
+        end: "18:00".into(),
+      }],
+      fallback: Some("B".into()),
+      solar: None,
+      change_at: None,
+    };
+    let collections = BTreeMap::from([("A".to_string(), paths(2)), ("B".to_string(), vec!["b0".to_string(), "b1".to_string()])]);
+    let mut engine = RotationEngine::new("B", RotationMode::Queue, 60, collections["B"].clone(), 1);
+    engine.set_schedule(Some(schedule.clone()), collections.clone());
+    engine.start_in(at(14, 6, 30), &Berlin);
+    assert_eq!((engine.collection_id.as_str(), engine.current()), ("B", Some("b0")));
+
+    // Среда 06:40: смена через интервал была бы в 07:30, но в 07:00 начинается окно коллекции A
+    assert!(!engine.is_due_in(at(14, 6, 40), &Berlin));
+    assert_eq!(engine.next_due_at_in(at(14, 6, 40), &Berlin), at(14, 7, 0));
+    assert!(engine.is_due_in(at(14, 7, 0), &Berlin));
+    assert_eq!(engine.advance_in(at(14, 7, 0), &Berlin), Some("p0"));
+    assert_eq!(engine.collection_id, "A");
+    assert_eq!(engine.next_due_at_in(at(14, 7, 0), &Berlin), at(14, 8, 0));
+    assert_eq!(engine.advance_in(at(14, 8, 0), &Berlin), Some("p1"));
+    // Конец окна: обратно на B
+    engine.mark_changed(at(14, 17, 10));
+    assert_eq!(engine.next_due_at_in(at(14, 17, 30), &Berlin), at(14, 18, 0));
+    assert_eq!(engine.advance_in(at(14, 18, 0), &Berlin), Some("b0"));
+
+    // Cron: смена в начале каждого часа; срабатывание в пределах допуска не повторяется
+    let cron = Schedule {
+      change_at: Some("0 * * * *".into()),
+      ..schedule.clone()
+    };
+    engine.set_schedule(Some(cron), collections.clone());
+    engine.mark_changed(at(14, 18, 59) + 50_000);
+    assert_eq!(engine.next_due_at_in(at(14, 19, 0), &Berlin), at(14, 20, 0));
+
+    // Без запасной коллекции в выходные обои не меняются до понедельника
+    let weekdays_only = Schedule { fallback: None, ..schedule };
+    let mut engine = RotationEngine::new("B", RotationMode::Queue, 15, collections["B"].clone(), 1);
+    engine.set_schedule(Some(weekdays_only), collections);
+    // Запуск в пятницу днём сразу переключает на A
+    engine.start_in(at(16, 12, 0), &Berlin);
+    assert_eq!((engine.collection_id.as_str(), engine.current()), ("A", Some("p0")));
+    assert!(!engine.is_due_in(at(17, 12, 0), &Berlin));
+    assert_eq!(engine.next_due_at_in(at(17, 12, 0), &Berlin), at(19, 7, 0));
+    assert_eq!(engine.state_in(at(17, 12, 0), &Berlin).next_due_at, at(19, 7, 0));
+  }
+
+  #[test]
+  fn minimal_state_parses() {
+    // Так состояние собиралось из prefs сервиса прежних версий: только обязательные поля
+    let state = serde_json::json!({
+      "mode": "queue",
+      "interval_minutes": 15,
+      "sequence": ["a", "b"],
+      "index": 1,
+      "last_change_at": 0
+    });
+    let mut engine: RotationEngine = serde_json::from_value(state).unwrap();
+    assert!(!engine.is_due(MIN));
+    assert_eq!(engine.current(), Some("b"));
+    assert_eq!(engine.next_due_at(MIN), 15 * MIN);
+
+    assert!(engine.is_due(15 * MIN));
+    assert_eq!(engine.advance(15 * MIN), Some("a"));
+    assert_eq!((engine.index, engine.last_change_at), (0, Some(15 * MIN)));
+    assert!(serde_json::from_str::<RotationEngine>("{").is_err());
+  }
+}
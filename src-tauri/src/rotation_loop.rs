@@ -0,0 +1,659 @@
+//! Ротация обоев на ПК: цикл в отдельном потоке процесса Tauri. Спит до срока следующей смены
+//! из RotationEngine, ставит обои и сохраняет состояние (`RotationStore`), чтобы после перезапуска
+//! приложения ротация продолжилась с того же места. Слотов два: главный экран (или оба экрана)
+//! и отдельная ротация экрана блокировки со своей коллекцией, интервалом и позицией.
+//!
+//! На Android обои меняет WallpaperRotationService: он читает тот же файл, а шаг делает через
+//! JNI (`tick_slot`), так что состояние пишет только Rust. Формат файла — `LoopState` в JSON:
+//!
+//! ```json
+//! { "version": 2, "running": true, "target": "both", "engine": { ... },
+//!   "lock": { "running": true, "engine": { ... } } }
+//! ```
+//!
+//! `version` растёт при несовместимых изменениях; файл без него — версия 1 (до общего файла
+//! с сервисом), он читается как есть. Новые поля добавляются с `#[serde(default)]` без смены
+//! версии.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::collection::now_millis;
+use crate::rotation::{RotationEngine, RotationState};
+use crate::rotation_store::{FileStore, RotationStore};
+
+/// Имя файла состояния в app_data_dir.
+pub const STATE_FILE_NAME: &str = "rotation.json";
+/// Версия формата файла состояния, которую пишет эта сборка.
+pub const STATE_VERSION: u32 = 2;
+
+/// Слот ротации: у каждого своя коллекция, интервал и позиция.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationSlot {
+  /// Главный экран; пока отдельная ротация блокировки не запущена — куда укажет `target`.
+  #[default]
+  Home,
+  /// Экран блокировки.
+  Lock,
+}
+
+/// Смена обоев циклом: что поставить и состояние слота после шага.
+#[derive(Debug, Clone)]
+pub struct LoopChange {
+  pub slot: RotationSlot,
+  /// Путь относительно base.
+  pub path: String,
+  pub target: String,
+  pub collection_id: String,
+  pub state: RotationState,
+}
+
+/// Отдельная ротация экрана блокировки.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SlotState {
+  #[serde(default)]
+  pub running: bool,
+  #[serde(default)]
+  pub engine: Option<RotationEngine>,
+}
+
+impl SlotState {
+  pub fn is_empty(&self) -> bool {
+    !self.running && self.engine.is_none()
+  }
+}
+
+/// Сохраняемое состояние ротации. Поля главного слота лежат на верхнем уровне — так файл
+/// остаётся совместимым с версиями без отдельной ротации блокировки.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoopState {
+  /// Версия формата, см. STATE_VERSION.
+  #[serde(default = "legacy_version")]
+  pub version: u32,
+  /// Цикл меняет обои только когда ротация запущена (start_wallpaper_rotation_service).
+  #[serde(default)]
+  pub running: bool,
+  /// both / home / lock.
+  #[serde(default = "default_target")]
+  pub target: String,
+  #[serde(default)]
+  pub engine: Option<RotationEngine>,
+  #[serde(default, skip_serializing_if = "SlotState::is_empty")]
+  pub lock: SlotState,
+}
+
+impl Default for LoopState {
+  fn default() -> Self {
+    Self {
+      version: STATE_VERSION,
+      running: false,
+      target: default_target(),
+      engine: None,
+      lock: SlotState::default(),
+    }
+  }
+}
+
+impl LoopState {
+  /// Ни один слот не идёт и не помнит движок — хранить нечего.
+  pub fn is_empty(&self) -> bool {
+    !self.running && self.engine.is_none() && self.lock.is_empty()
+  }
+
+  pub fn engine(&self, slot: RotationSlot) -> Option<&RotationEngine> {
+    match slot {
+      RotationSlot::Home => self.engine.as_ref(),
+      RotationSlot::Lock => self.lock.engine.as_ref(),
+    }
+  }
+
+  pub fn engine_mut(&mut self, slot: RotationSlot) -> Option<&mut RotationEngine> {
+    match slot {
+      RotationSlot::Home => self.engine.as_mut(),
+      RotationSlot::Lock => self.lock.engine.as_mut(),
+    }
+  }
+
+  pub fn set_engine(&mut self, slot: RotationSlot, engine: Option<RotationEngine>) {
+    match slot {
+      RotationSlot::Home => self.engine = engine,
+      RotationSlot::Lock => self.lock.engine = engine,
+    }
+  }
+
+  pub fn is_running(&self, slot: RotationSlot) -> bool {
+    match slot {
+      RotationSlot::Home => self.running,
+      RotationSlot::Lock => self.lock.running,
+    }
+  }
+
+  pub fn set_running(&mut self, slot: RotationSlot, running: bool) {
+    match slot {
+      RotationSlot::Home => self.running = running,
+      RotationSlot::Lock => self.lock.running = running,
+    }
+  }
+
+  pub fn any_running(&self) -> bool {
+    self.is_running(RotationSlot::Home) || self.is_running(RotationSlot::Lock)
+  }
+
+  /// Запустить слот на движке, который собрал `rotation_start`: движок не пересобирается, меняется
+  /// только интервал. `target` относится к главному слоту. None — у слота нет движка; иначе true,
+  /// если слот до этого не шёл.
+  pub fn start(&mut self, slot: RotationSlot, target: String, interval_minutes: u32) -> Option<bool> {
+    self.engine_mut(slot)?.set_interval(interval_minutes);
+    if slot == RotationSlot::Home {
+      self.target = target;
+    }
+    let started = !self.is_running(slot);
+    self.set_running(slot, true);
+    Some(started)
+  }
+
+  /// Остановить слот, сохранив движок для повторного запуска. true — слот шёл.
+  pub fn stop(&mut self, slot: RotationSlot) -> bool {
+    let was_running = self.is_running(slot);
+    self.set_running(slot, false);
+    was_running
+  }
+
+  /// Индекс текущих обоев слота, время последней смены и срок закрепления (0 — не закреплены), мс.
+  pub fn position(&self, slot: RotationSlot, now: u64) -> (i32, i64, i64) {
+    match self.engine(slot) {
+      Some(engine) => (
+        engine.index as i32,
+        engine.last_change_at.unwrap_or(0) as i64,
+        engine.pinned_at(now).unwrap_or(0) as i64,
+      ),
+      None => (0, 0, 0),
+    }
+  }
+
+  /// Остановить слот и забыть его движок.
+  pub fn clear(&mut self, slot: RotationSlot) {
+    self.set_running(slot, false);
+    self.set_engine(slot, None);
+  }
+
+  /// Куда ставить обои слота: пока идёт отдельная ротация блокировки, главный слот меняет
+  /// только главный экран.
+  pub fn target(&self, slot: RotationSlot) -> &str {
+    match slot {
+      RotationSlot::Lock => "lock",
+      RotationSlot::Home if self.lock.running => "home",
+      RotationSlot::Home => &self.target,
+    }
+  }
+
+  /// Следующий запущенный слот, которому пора сменить обои: движок переходит дальше.
+  fn advance_due(&mut self, now: u64) -> Option<LoopChange> {
+    for slot in [RotationSlot::Home, RotationSlot::Lock] {
+      let running = self.is_running(slot);
+      let Some(engine) = self.engine_mut(slot).filter(|e| running && e.is_due(now)) else {
+        continue;
+      };
+      engine.advance(now);
+      let state = engine.state(now);
+      if let Some(path) = state.current.clone() {
+        return Some(LoopChange {
+          slot,
+          path,
+          target: self.target(slot).to_string(),
+          collection_id: state.collection_id.clone(),
+          state,
+        });
+      }
+    }
+    None
+  }
+
+  /// Через сколько пересмотреть состояние; None — ни один слот не ждёт смены.
+  fn next_wake(&self, now: u64) -> Option<Duration> {
+    [RotationSlot::Home, RotationSlot::Lock]
+      .into_iter()
+      .filter(|&slot| self.is_running(slot))
+      .filter_map(|slot| self.engine(slot))
+      .filter(|e| !e.sequence.is_empty() || e.schedule.is_some())
+      .map(|e| Duration::from_millis(e.next_due_at(now).saturating_sub(now)))
+      .min()
+  }
+
+  /// Шаг слота для фонового сервиса Android: если пора — движок переходит дальше
+  /// (при `advance`), в ответе что ставить и когда будить сервис в следующий раз.
+  #[cfg_attr(not(target_os = "android"), allow(dead_code))]
+  pub fn tick_slot(&mut self, slot: RotationSlot, now: u64, advance: bool) -> SlotTick {
+    let running = self.is_running(slot);
+    let target = self.target(slot).to_string();
+    let Some(engine) = self.engine_mut(slot).filter(|_| running) else {
+      return SlotTick {
+        running: false,
+        due: false,
+        current: None,
+        target,
+        collection_id: String::new(),
+        next_due_at: 0,
+      };
+    };
+    let due = advance && engine.is_due(now);
+    if due {
+      engine.advance(now);
+    }
+    // Без state(): последовательность большой коллекции сервису не нужна
+    let waiting = !engine.sequence.is_empty() || engine.schedule.is_some();
+    let current = engine.current().map(String::from);
+    SlotTick {
+      running: true,
+      due: due && current.is_some(),
+      current,
+      target,
+      collection_id: engine.collection_id.clone(),
+      next_due_at: if waiting { engine.next_due_at(now) } else { 0 },
+    }
+  }
+}
+
+/// Результат `LoopState::tick_slot`, его разбирает WallpaperRotationService.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+pub struct SlotTick {
+  /// false — слот остановлен, сервису будить себя не нужно.
+  pub running: bool,
+  /// Движок перешёл к новым обоям, их нужно поставить.
+  pub due: bool,
+  /// Путь относительно base.
+  pub current: Option<String>,
+  pub target: String,
+  pub collection_id: String,
+  /// Когда вызвать шаг снова, мс с эпохи Unix; 0 — ждать нечего.
+  pub next_due_at: u64,
+}
+
+fn default_target() -> String {
+  "both".to_string()
+}
+
+fn legacy_version() -> u32 {
+  1
+}
+
+pub struct RotationLoop {
+  state: Mutex<LoopState>,
+  /// Будит цикл при любом изменении состояния (старт, стоп, новый интервал).
+  wake: Condvar,
+  store: Box<dyn RotationStore>,
+}
+
+impl RotationLoop {
+  /// Состояние из файла `state_file` (`FileStore`).
+  pub fn load(state_file: PathBuf) -> Self {
+    Self::new(FileStore::new(state_file))
+  }
+
+  /// Загрузить сохранённое состояние. Повреждённое или более новой версии не мешает запуску —
+  /// ротация просто не продолжится. Старые версии читаются и при сохранении переписываются в текущей.
+  pub fn new(store: impl RotationStore + 'static) -> Self {
+    let state = match store.load() {
+      Ok(Some(state)) if state.version > STATE_VERSION => {
+        log::warn!("rotation: ignoring state of unsupported version {}", state.version);
+        LoopState::default()
+      }
+      Ok(Some(state)) => LoopState {
+        version: STATE_VERSION,
+        ..state
+      },
+      Ok(None) => LoopState::default(),
+      Err(e) => {
+        log::warn!("rotation: ignoring unreadable state: {}", e);
+        LoopState::default()
+      }
+    };
+    Self {
+      state: Mutex::new(state),
+      wake: Condvar::new(),
+      store: Box::new(store),
+    }
+  }
+
+  /// Снимок состояния.
+  pub fn snapshot(&self) -> LoopState {
+    self.lock().clone()
+  }
+
+  /// Изменить состояние: после `f` оно сохраняется, а цикл пересчитывает срок.
+  pub fn update<T>(&self, f: impl FnOnce(&mut LoopState) -> T) -> Result<T, String> {
+    let mut state = self.lock();
+    let result = f(&mut state);
+    self.store.save(&state)?;
+    self.wake.notify_all();
+    Ok(result)
+  }
+
+  /// Запустить цикл в отдельном потоке. `apply` ставит обои и сообщает о смене; ошибка только пишется в лог.
+  pub fn spawn(self: &Arc<Self>, apply: impl Fn(&LoopChange) -> Result<(), String> + Send + 'static) {
+    let this = Arc::clone(self);
+    std::thread::Builder::new()
+      .name("wallpaper-rotation".into())
+      .spawn(move || this.run(apply))
+      .expect("spawn wallpaper rotation thread");
+  }
+
+  fn run(&self, apply: impl Fn(&LoopChange) -> Result<(), String>) {
+    let mut state = self.lock();
+    loop {
+      let now = now_millis();
+      if let Some(change) = state.advance_due(now) {
+        if let Err(e) = self.store.save(&state) {
+          log::warn!("rotation: {}", e);
+        }
+        // Обои ставим без блокировки: команды не должны ждать внешних программ
+        drop(state);
+        if let Err(e) = apply(&change) {
+          log::warn!("rotation: failed to set {}: {}", change.path, e);
+        }
+        state = self.lock();
+        continue;
+      }
+      state = match state.next_wake(now) {
+        Some(timeout) => self.wake.wait_timeout(state, timeout).unwrap_or_else(|e| e.into_inner()).0,
+        None => self.wake.wait(state).unwrap_or_else(|e| e.into_inner()),
+      };
+    }
+  }
+
+  fn lock(&self) -> MutexGuard<'_, LoopState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+/// Команды работают с RotationLoop как с хранилищем: изменение идёт под его блокировкой,
+/// сохраняется во внутреннее хранилище и будит цикл.
+impl RotationStore for RotationLoop {
+  fn load(&self) -> Result<Option<LoopState>, String> {
+    Ok(Some(self.snapshot()))
+  }
+
+  fn save(&self, state: &LoopState) -> Result<(), String> {
+    self.update(|current| *current = state.clone())
+  }
+
+  fn modify(&self, f: &mut dyn FnMut(&mut LoopState)) -> Result<(), String> {
+    self.update(f)
+  }
+}
+
+// Тела команд ротации над любым хранилищем: в приложении это RotationLoop, в тестах — MemoryStore.
+
+/// `start_wallpaper_rotation_service`: запустить слот. Возвращает состояние его движка и true,
+/// если слот до этого не шёл; None — `rotation_start` для слота не вызывался.
+pub fn start_slot(
+  store: &dyn RotationStore,
+  slot: RotationSlot,
+  target: String,
+  interval_minutes: u32,
+  now: u64,
+) -> Result<Option<(RotationState, bool)>, String> {
+  modify(store, |state| {
+    let started = state.start(slot, target, interval_minutes)?;
+    state.engine(slot).map(|engine| (engine.state(now), started))
+  })
+}
+
+/// `stop_wallpaper_rotation_service`: остановить слот, сохранив движок. Возвращает, шёл ли слот
+/// и идёт ли ещё какой-нибудь.
+pub fn stop_slot(store: &dyn RotationStore, slot: RotationSlot) -> Result<(bool, bool), String> {
+  modify(store, |state| (state.stop(slot), state.any_running()))
+}
+
+/// `rotation_stop`: остановить слот и забыть движок. true — слот шёл или помнил движок.
+pub fn clear_slot(store: &dyn RotationStore, slot: RotationSlot) -> Result<bool, String> {
+  modify(store, |state| {
+    let was_active = state.is_running(slot) || state.engine(slot).is_some();
+    state.clear(slot);
+    was_active
+  })
+}
+
+/// `rotation_set_interval`: новый интервал слота; None — у слота нет движка.
+pub fn set_slot_interval(
+  store: &dyn RotationStore,
+  slot: RotationSlot,
+  interval_minutes: u32,
+  now: u64,
+) -> Result<Option<RotationState>, String> {
+  modify(store, |state| {
+    let engine = state.engine_mut(slot)?;
+    engine.set_interval(interval_minutes);
+    Some(engine.state(now))
+  })
+}
+
+/// `get_wallpaper_rotation_state`: индекс, время последней смены и срок закрепления слота.
+pub fn slot_position(store: &dyn RotationStore, slot: RotationSlot, now: u64) -> Result<(i32, i64, i64), String> {
+  Ok(store.load()?.unwrap_or_default().position(slot, now))
+}
+
+/// `RotationStore::modify` с результатом `f`.
+fn modify<T>(store: &dyn RotationStore, f: impl FnOnce(&mut LoopState) -> T) -> Result<T, String> {
+  let mut f = Some(f);
+  let mut result = None;
+  store.modify(&mut |state| result = f.take().map(|f| f(state)))?;
+  result.ok_or_else(|| "rotation state was not modified".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rotation::RotationMode;
+  use crate::rotation_store::MemoryStore;
+  use std::fs;
+  use std::sync::mpsc;
+
+  fn engine(last_change_at: Option<u64>) -> RotationEngine {
+    let mut engine = RotationEngine::new("c1", RotationMode::Queue, 15, vec!["a".into(), "b".into()], 1);
+    engine.last_change_at = last_change_at;
+    engine
+  }
+
+  #[test]
+  fn state_survives_restart() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join(STATE_FILE_NAME);
+    let rotation = RotationLoop::load(file.clone());
+    assert_eq!(rotation.snapshot(), LoopState::default());
+
+    rotation
+      .update(|state| {
+        state.running = true;
+        state.target = "home".into();
+        state.engine = Some(engine(Some(5)));
+      })
+      .unwrap();
+    assert_eq!(RotationLoop::load(file.clone()).snapshot(), rotation.snapshot());
+
+    rotation.update(|state| *state = LoopState::default()).unwrap();
+    assert!(!file.exists());
+
+    fs::write(&file, "{broken").unwrap();
+    assert_eq!(RotationLoop::load(file).snapshot(), LoopState::default());
+  }
+
+  #[test]
+  fn state_file_is_versioned() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join(STATE_FILE_NAME);
+
+    // Файл первой версии (без поля version) читается и переписывается в текущей
+    let legacy = serde_json::json!({ "running": true, "target": "lock", "engine": engine(Some(5)) });
+    fs::write(&file, legacy.to_string()).unwrap();
+    let rotation = RotationLoop::load(file.clone());
+    let state = rotation.snapshot();
+    assert_eq!((state.version, state.running, state.target.as_str()), (STATE_VERSION, true, "lock"));
+    rotation.update(|_| ()).unwrap();
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(saved["version"], STATE_VERSION);
+
+    // Файл более новой версии не трогаем вслепую
+    fs::write(&file, serde_json::json!({ "version": STATE_VERSION + 1, "running": true }).to_string()).unwrap();
+    assert_eq!(RotationLoop::load(file).snapshot(), LoopState::default());
+  }
+
+  #[test]
+  fn tick_slot_advances_only_when_due() {
+    let mut state = LoopState::default();
+    let stopped = state.tick_slot(RotationSlot::Home, 0, true);
+    assert!(!stopped.running && stopped.next_due_at == 0);
+
+    state.engine = Some(engine(Some(0)));
+    state.running = true;
+    // Без advance — только срок, движок на месте
+    let tick = state.tick_slot(RotationSlot::Home, 1, false);
+    assert!(tick.running && !tick.due);
+    assert_eq!(tick.current.as_deref(), Some("a"));
+
+    let now = 20 * 60_000;
+    let tick = state.tick_slot(RotationSlot::Home, now, true);
+    assert!(tick.due);
+    assert_eq!((tick.current.as_deref(), tick.target.as_str()), (Some("b"), "both"));
+    assert_eq!(tick.next_due_at, now + 15 * 60_000);
+    assert!(!state.tick_slot(RotationSlot::Home, now + 1, true).due);
+
+    // Слот блокировки не запущен, главный при этом снова ставит на оба экрана
+    assert!(!state.tick_slot(RotationSlot::Lock, now, true).running);
+  }
+
+  #[test]
+  fn commands_start_stop_and_report_position() {
+    let store = MemoryStore::default();
+    let (home, lock) = (RotationSlot::Home, RotationSlot::Lock);
+    // Без rotation_start запускать нечего
+    assert_eq!(start_slot(&store, home, "home".into(), 30, 10).unwrap(), None);
+    assert_eq!(store.saved(), None);
+
+    // Движок rotation_start со всеми настройками остаётся тем же, меняется только интервал
+    let mut built = engine(Some(5));
+    built.set_no_repeat(1);
+    built.rng = 42;
+    built.index = 1;
+    store.modify(&mut |state| state.engine = Some(built.clone())).unwrap();
+    let (state, started) = start_slot(&store, home, "home".into(), 30, 10).unwrap().unwrap();
+    assert!(started);
+    assert_eq!((state.index, state.interval_minutes), (1, 30));
+    let saved = store.saved().unwrap();
+    assert!(saved.running && saved.any_running());
+    assert_eq!(saved.target(home), "home");
+    assert_eq!(saved.engine, Some(RotationEngine { interval_minutes: 30, ..built }));
+    assert_eq!(slot_position(&store, home, 10).unwrap(), (1, 5, 0));
+    assert_eq!(slot_position(&store, lock, 10).unwrap(), (0, 0, 0));
+
+    // Повторный запуск идущего слота — не новый старт
+    let (state, started) = start_slot(&store, home, "both".into(), 60, 10).unwrap().unwrap();
+    assert!(!started);
+    assert_eq!(state.interval_minutes, 60);
+    assert_eq!(set_slot_interval(&store, home, 45, 10).unwrap().unwrap().interval_minutes, 45);
+    assert_eq!(set_slot_interval(&store, lock, 45, 10).unwrap(), None);
+
+    // Слот блокировки не трогает цель главного и останавливается отдельно
+    store.modify(&mut |state| state.set_engine(lock, Some(engine(None)))).unwrap();
+    start_slot(&store, lock, "home".into(), 15, 10).unwrap().unwrap();
+    assert_eq!(store.saved().unwrap().target, "both");
+    assert_eq!(stop_slot(&store, home).unwrap(), (true, true));
+    assert_eq!(stop_slot(&store, home).unwrap(), (false, true));
+    assert_eq!(stop_slot(&store, lock).unwrap(), (true, false));
+
+    // Остановленные слоты помнят движки до rotation_stop; пустое состояние хранилище не держит
+    let saved = store.saved().unwrap();
+    assert!(!saved.any_running() && saved.engine(lock).is_some());
+    assert!(clear_slot(&store, home).unwrap());
+    assert!(clear_slot(&store, lock).unwrap());
+    assert!(!clear_slot(&store, lock).unwrap());
+    assert_eq!(store.saved(), None);
+  }
+
+  #[test]
+  fn commands_through_loop_update_shared_state() {
+    let store = Arc::new(MemoryStore::default());
+    let rotation = RotationLoop::new(Arc::clone(&store));
+    rotation.update(|state| state.engine = Some(engine(None))).unwrap();
+    start_slot(&rotation, RotationSlot::Home, "both".into(), 30, 10).unwrap();
+    assert!(rotation.snapshot().running);
+    assert_eq!(store.saved(), Some(rotation.snapshot()));
+    assert_eq!(RotationLoop::new(Arc::clone(&store)).snapshot(), rotation.snapshot());
+    assert_eq!(stop_slot(&rotation, RotationSlot::Home).unwrap(), (true, false));
+    assert!(!store.saved().unwrap().running);
+  }
+
+  #[test]
+  fn loop_applies_due_wallpaper_and_persists() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join(STATE_FILE_NAME);
+    let rotation = Arc::new(RotationLoop::load(file.clone()));
+    let (tx, rx) = mpsc::channel();
+    rotation.spawn(move |change| {
+      assert_eq!((change.slot, change.collection_id.as_str()), (RotationSlot::Home, "c1"));
+      assert_eq!(change.state.current.as_deref(), Some(change.path.as_str()));
+      tx.send((change.path.clone(), change.target.clone())).unwrap();
+      Ok(())
+    });
+
+    // Не запущена — обои не меняются, даже если срок прошёл
+    rotation.update(|state| state.engine = Some(engine(Some(0)))).unwrap();
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    rotation.update(|state| state.running = true).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ("b".to_string(), "both".to_string()));
+    // Следующая смена — через интервал, повторно сразу не срабатывает
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    let saved = RotationLoop::load(file).snapshot();
+    let engine = saved.engine.unwrap();
+    assert_eq!(engine.index, 1);
+    assert!(engine.last_change_at.unwrap() > 0);
+  }
+
+  #[test]
+  fn lock_slot_rotates_independently() {
+    let tmp = tempfile::tempdir().unwrap();
+    let file = tmp.path().join(STATE_FILE_NAME);
+    let rotation = Arc::new(RotationLoop::load(file.clone()));
+    let (tx, rx) = mpsc::channel();
+    rotation.spawn(move |change| {
+      tx.send((change.path.clone(), change.target.clone(), change.collection_id.clone())).unwrap();
+      Ok(())
+    });
+
+    let mut lock_engine = RotationEngine::new("c2", RotationMode::Queue, 15, vec!["x".into(), "y".into()], 1);
+    lock_engine.last_change_at = Some(0);
+    rotation
+      .update(|state| {
+        state.set_engine(RotationSlot::Lock, Some(lock_engine));
+        state.set_running(RotationSlot::Lock, true);
+      })
+      .unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ("y".into(), "lock".into(), "c2".into()));
+
+    // Главный слот при отдельной блокировке меняет только главный экран
+    rotation
+      .update(|state| {
+        state.engine = Some(engine(Some(0)));
+        state.running = true;
+      })
+      .unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), ("b".into(), "home".into(), "c1".into()));
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+    let saved = RotationLoop::load(file.clone()).snapshot();
+    assert_eq!(saved.engine(RotationSlot::Lock).unwrap().index, 1);
+    assert_eq!(saved.target(RotationSlot::Home), "home");
+
+    rotation.update(|state| state.clear(RotationSlot::Home)).unwrap();
+    assert!(file.exists());
+    rotation.update(|state| state.clear(RotationSlot::Lock)).unwrap();
+    assert_eq!(rotation.snapshot().target(RotationSlot::Home), "both");
+    assert!(!file.exists());
+  }
+}
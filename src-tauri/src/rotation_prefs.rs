@@ -0,0 +1,300 @@
+//! Состояние ротации в SharedPreferences — так его хранили прежние версии
+//! WallpaperRotationService: по файлу на слот, каждое поле движка под своим ключом. Сейчас общий
+//! с сервисом формат — файл (`FileStore`); `PrefsStore` читает и пишет прежний формат, а `migrate`
+//! один раз переносит из него состояние при обновлении приложения на Android.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::rotation::{RotationEngine, RotationMode, MIN_INTERVAL_MINUTES};
+use crate::rotation_loop::{LoopState, RotationSlot};
+use crate::rotation_store::RotationStore;
+use crate::schedule::Schedule;
+
+/// SharedPreferences, в которых прежние версии WallpaperRotationService хранили слот: у экрана
+/// блокировки свои.
+pub fn prefs_file(slot: RotationSlot) -> &'static str {
+  match slot {
+    RotationSlot::Home => "chrono_wall_rotation",
+    RotationSlot::Lock => "chrono_wall_rotation_lock",
+  }
+}
+
+/// Значение в SharedPreferences. Тип важен: getInt на ключе, записанном putLong, бросает исключение.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefValue {
+  Str(String),
+  Long(i64),
+  Int(i32),
+  Bool(bool),
+}
+
+/// Файлы настроек «ключ → значение»: SharedPreferences на Android (`AndroidPrefs` в lib.rs).
+/// Отсутствующий ключ читается как пустая строка, 0 или false.
+pub trait Preferences: Send + Sync {
+  fn get_string(&self, file: &str, key: &str) -> Result<String, String>;
+  fn get_long(&self, file: &str, key: &str) -> Result<i64, String>;
+  fn get_int(&self, file: &str, key: &str) -> Result<i32, String>;
+  fn get_bool(&self, file: &str, key: &str) -> Result<bool, String>;
+  /// Заменить всё содержимое файла на `values`; пустой список очищает файл.
+  fn replace(&self, file: &str, values: &[(&str, PrefValue)]) -> Result<(), String>;
+}
+
+/// Разделитель последовательности и недавних в одной строке prefs.
+const PREFS_SEPARATOR: &str = "\u{0000}";
+
+/// Расписание слота в prefs: одна JSON-строка с правилами и последовательностями коллекций.
+#[derive(Serialize, Deserialize)]
+struct PrefsSchedule {
+  schedule: Option<Schedule>,
+  #[serde(default)]
+  collections: BTreeMap<String, Vec<String>>,
+}
+
+/// Хранилище в SharedPreferences слотов (`prefs_file`). Слот без последовательности не хранится.
+pub struct PrefsStore<P> {
+  prefs: P,
+}
+
+impl<P: Preferences> PrefsStore<P> {
+  pub fn new(prefs: P) -> Self {
+    Self { prefs }
+  }
+
+  fn load_engine(&self, file: &str) -> Result<Option<RotationEngine>, String> {
+    let prefs = &self.prefs;
+    let split = |s: String| s.split(PREFS_SEPARATOR).filter(|p| !p.is_empty()).map(String::from).collect::<Vec<_>>();
+    let sequence = split(prefs.get_string(file, "sequence")?);
+    if sequence.is_empty() {
+      return Ok(None);
+    }
+    let saved = prefs.get_string(file, "schedule")?;
+    let schedule = if saved.is_empty() {
+      PrefsSchedule {
+        schedule: None,
+        collections: BTreeMap::new(),
+      }
+    } else {
+      serde_json::from_str(&saved).map_err(|e| format!("schedule: {}", e))?
+    };
+    let time = |t: i64| u64::try_from(t).ok().filter(|&t| t > 0);
+    Ok(Some(RotationEngine {
+      collection_id: prefs.get_string(file, "collection_id")?,
+      mode: match prefs.get_string(file, "mode")?.as_str() {
+        "random" => RotationMode::Random,
+        _ => RotationMode::Queue,
+      },
+      interval_minutes: (prefs.get_int(file, "interval_minutes")?.max(0) as u32).max(MIN_INTERVAL_MINUTES),
+      index: (prefs.get_int(file, "rotation_index")?.max(0) as usize).min(sequence.len() - 1),
+      sequence,
+      last_change_at: time(prefs.get_long(file, "last_change_at")?),
+      rng: prefs.get_string(file, "rng")?.parse().unwrap_or(0),
+      no_repeat: prefs.get_int(file, "no_repeat")?.max(0) as usize,
+      recent: split(prefs.get_string(file, "recent")?),
+      schedule: schedule.schedule,
+      collections: schedule.collections,
+      pinned_until: time(prefs.get_long(file, "pinned_until")?),
+    }))
+  }
+}
+
+impl<P: Preferences> RotationStore for PrefsStore<P> {
+  fn load(&self) -> Result<Option<LoopState>, String> {
+    let mut state = LoopState::default();
+    let mut found = false;
+    for slot in [RotationSlot::Home, RotationSlot::Lock] {
+      let file = prefs_file(slot);
+      let Some(engine) = self.load_engine(file)? else {
+        continue;
+      };
+      found = true;
+      state.set_engine(slot, Some(engine));
+      state.set_running(slot, self.prefs.get_bool(file, "running")?);
+      let target = self.prefs.get_string(file, "target")?;
+      if slot == RotationSlot::Home && !target.is_empty() {
+        state.target = target;
+      }
+    }
+    Ok(Some(state).filter(|_| found))
+  }
+
+  fn save(&self, state: &LoopState) -> Result<(), String> {
+    for slot in [RotationSlot::Home, RotationSlot::Lock] {
+      let Some(engine) = state.engine(slot) else {
+        self.prefs.replace(prefs_file(slot), &[])?;
+        continue;
+      };
+      let schedule = match (&engine.schedule, engine.collections.is_empty()) {
+        (None, true) => String::new(),
+        _ => serde_json::to_string(&PrefsSchedule {
+          schedule: engine.schedule.clone(),
+          collections: engine.collections.clone(),
+        })
+        .map_err(|e| e.to_string())?,
+      };
+      let mode = match engine.mode {
+        RotationMode::Queue => "queue",
+        RotationMode::Random => "random",
+      };
+      let mut values = vec![
+        ("collection_id", PrefValue::Str(engine.collection_id.clone())),
+        ("mode", PrefValue::Str(mode.to_string())),
+        ("interval_minutes", PrefValue::Int(engine.interval_minutes as i32)),
+        ("rotation_index", PrefValue::Int(engine.index as i32)),
+        ("sequence", PrefValue::Str(engine.sequence.join(PREFS_SEPARATOR))),
+        ("last_change_at", PrefValue::Long(engine.last_change_at.unwrap_or(0) as i64)),
+        ("rng", PrefValue::Str(engine.rng.to_string())),
+        ("no_repeat", PrefValue::Int(engine.no_repeat as i32)),
+        ("recent", PrefValue::Str(engine.recent.join(PREFS_SEPARATOR))),
+        ("pinned_until", PrefValue::Long(engine.pinned_until.unwrap_or(0) as i64)),
+        ("schedule", PrefValue::Str(schedule)),
+        ("running", PrefValue::Bool(state.is_running(slot))),
+      ];
+      if slot == RotationSlot::Home {
+        values.push(("target", PrefValue::Str(state.target.clone())));
+      }
+      self.prefs.replace(prefs_file(slot), &values)?;
+    }
+    Ok(())
+  }
+}
+
+/// Перенести состояние из прежнего хранилища `legacy` в `state`: движок слота берётся, если его
+/// последняя смена свежее уже известной, флаг запуска — всегда. После переноса `legacy`
+/// очищается, так что миграция выполняется один раз.
+pub fn migrate(legacy: &dyn RotationStore, state: &mut LoopState) -> Result<(), String> {
+  let Some(old) = legacy.load()? else {
+    return Ok(());
+  };
+  for slot in [RotationSlot::Home, RotationSlot::Lock] {
+    let Some(engine) = old.engine(slot) else {
+      continue;
+    };
+    let known = state.engine(slot).map(|engine| engine.last_change_at.unwrap_or(0));
+    if known.map_or(true, |known| engine.last_change_at.unwrap_or(0) > known) {
+      if slot == RotationSlot::Home && known.is_none() {
+        state.target = old.target.clone();
+      }
+      state.set_engine(slot, Some(engine.clone()));
+    }
+    state.set_running(slot, old.is_running(slot));
+    log::info!("rotation: migrated {} slot from service prefs", prefs_file(slot));
+  }
+  legacy.save(&LoopState::default())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+  use std::sync::Mutex;
+
+  /// SharedPreferences в памяти: файл → ключ → значение.
+  #[derive(Default)]
+  struct MemoryPrefs(Mutex<HashMap<String, HashMap<String, PrefValue>>>);
+
+  impl MemoryPrefs {
+    fn get(&self, file: &str, key: &str) -> Option<PrefValue> {
+      self.0.lock().unwrap().get(file).and_then(|values| values.get(key)).cloned()
+    }
+  }
+
+  impl Preferences for MemoryPrefs {
+    fn get_string(&self, file: &str, key: &str) -> Result<String, String> {
+      match self.get(file, key) {
+        Some(PrefValue::Str(s)) => Ok(s),
+        None => Ok(String::new()),
+        Some(other) => Err(format!("{}: not a string: {:?}", key, other)),
+      }
+    }
+
+    fn get_long(&self, file: &str, key: &str) -> Result<i64, String> {
+      match self.get(file, key) {
+        Some(PrefValue::Long(n)) => Ok(n),
+        None => Ok(0),
+        Some(other) => Err(format!("{}: not a long: {:?}", key, other)),
+      }
+    }
+
+    fn get_int(&self, file: &str, key: &str) -> Result<i32, String> {
+      match self.get(file, key) {
+        Some(PrefValue::Int(n)) => Ok(n),
+        None => Ok(0),
+        Some(other) => Err(format!("{}: not an int: {:?}", key, other)),
+      }
+    }
+
+    fn get_bool(&self, file: &str, key: &str) -> Result<bool, String> {
+      match self.get(file, key) {
+        Some(PrefValue::Bool(b)) => Ok(b),
+        None => Ok(false),
+        Some(other) => Err(format!("{}: not a boolean: {:?}", key, other)),
+      }
+    }
+
+    fn replace(&self, file: &str, values: &[(&str, PrefValue)]) -> Result<(), String> {
+      let values = values.iter().map(|(k, v)| (k.to_string(), v.clone())).collect();
+      self.0.lock().unwrap().insert(file.to_string(), values);
+      Ok(())
+    }
+  }
+
+  fn engine(last_change_at: u64) -> RotationEngine {
+    let mut engine = RotationEngine::new("c1", RotationMode::Random, 30, vec!["a".into(), "b".into(), "c".into()], 7);
+    engine.index = 2;
+    engine.last_change_at = Some(last_change_at);
+    engine.recent = vec!["b".into()];
+    engine.pinned_until = Some(last_change_at + 60_000);
+    engine.collections.insert("c2".into(), vec!["x".into()]);
+    engine
+  }
+
+  #[test]
+  fn prefs_store_round_trips_both_slots() {
+    let store = PrefsStore::new(MemoryPrefs::default());
+    assert_eq!(store.load().unwrap(), None);
+
+    let mut state = LoopState {
+      target: "home".into(),
+      ..LoopState::default()
+    };
+    state.set_engine(RotationSlot::Home, Some(engine(1_000)));
+    state.set_running(RotationSlot::Home, true);
+    state.set_engine(RotationSlot::Lock, Some(RotationEngine::new("c2", RotationMode::Queue, 15, vec!["x".into()], 0)));
+    store.save(&state).unwrap();
+    // Последовательность — одна строка через NUL, как её писал сервис
+    let sequence = state.engine(RotationSlot::Home).unwrap().sequence.join("\u{0}");
+    assert_eq!(store.prefs.get(prefs_file(RotationSlot::Home), "sequence"), Some(PrefValue::Str(sequence)));
+    assert_eq!(store.load().unwrap(), Some(state));
+
+    store.save(&LoopState::default()).unwrap();
+    assert_eq!(store.load().unwrap(), None);
+  }
+
+  #[test]
+  fn migration_takes_newer_progress_once() {
+    let legacy = PrefsStore::new(MemoryPrefs::default());
+    let mut old = LoopState {
+      target: "lock".into(),
+      ..LoopState::default()
+    };
+    old.set_engine(RotationSlot::Home, Some(engine(5_000)));
+    old.set_running(RotationSlot::Home, true);
+    old.set_engine(RotationSlot::Lock, Some(engine(1_000)));
+    legacy.save(&old).unwrap();
+
+    // Главный слот ещё не известен, у слота блокировки в файле прогресс свежее
+    let mut state = LoopState::default();
+    state.set_engine(RotationSlot::Lock, Some(engine(2_000)));
+    migrate(&legacy, &mut state).unwrap();
+    assert_eq!(state.engine(RotationSlot::Home), old.engine(RotationSlot::Home));
+    assert!(state.is_running(RotationSlot::Home));
+    assert_eq!(state.target, "lock");
+    assert_eq!(state.engine(RotationSlot::Lock).unwrap().last_change_at, Some(2_000));
+
+    assert_eq!(legacy.load().unwrap(), None);
+    let migrated = state.clone();
+    migrate(&legacy, &mut state).unwrap();
+    assert_eq!(state, migrated);
+  }
+}
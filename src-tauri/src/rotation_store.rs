@@ -0,0 +1,104 @@
+//! Где хранится состояние ротации (`LoopState`). RotationLoop и команды ротации работают с
+//! хранилищем через `RotationStore`: в приложении это JSON-файл в app_data_dir, который на Android
+//! читает и WallpaperRotationService, в прежних версиях — SharedPreferences сервиса
+//! (`rotation_prefs::PrefsStore`, оттуда состояние переносится в файл), а в тестах — память,
+//! так что команды ротации проверяются обычным `cargo test` на любой платформе.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::atomic_file;
+use crate::rotation_loop::LoopState;
+
+pub trait RotationStore: Send + Sync {
+  /// Сохранённое состояние; None — сохранять было нечего.
+  fn load(&self) -> Result<Option<LoopState>, String>;
+  /// Сохранить состояние целиком. Пустое (`LoopState::is_empty`) хранилище может не держать.
+  fn save(&self, state: &LoopState) -> Result<(), String>;
+
+  /// Прочитать, изменить и сохранить состояние. RotationLoop делает это под своей блокировкой
+  /// и будит цикл; остальным хранилищам достаточно `load` и `save`.
+  fn modify(&self, f: &mut dyn FnMut(&mut LoopState)) -> Result<(), String> {
+    let mut state = self.load()?.unwrap_or_default();
+    f(&mut state);
+    self.save(&state)
+  }
+}
+
+/// Общее хранилище: тест держит его у себя и смотрит, что сохранил RotationLoop.
+impl<S: RotationStore + ?Sized> RotationStore for std::sync::Arc<S> {
+  fn load(&self) -> Result<Option<LoopState>, String> {
+    (**self).load()
+  }
+
+  fn save(&self, state: &LoopState) -> Result<(), String> {
+    (**self).save(state)
+  }
+
+  fn modify(&self, f: &mut dyn FnMut(&mut LoopState)) -> Result<(), String> {
+    (**self).modify(f)
+  }
+}
+
+/// JSON-файл, записываемый атомарно. Остановленная ротация без движка файла не оставляет.
+pub struct FileStore {
+  path: PathBuf,
+}
+
+impl FileStore {
+  pub fn new(path: PathBuf) -> Self {
+    Self { path }
+  }
+}
+
+impl RotationStore for FileStore {
+  fn load(&self) -> Result<Option<LoopState>, String> {
+    let content = match fs::read_to_string(&self.path) {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(format!("read {:?}: {}", self.path, e)),
+    };
+    serde_json::from_str(&content).map(Some).map_err(|e| format!("parse {:?}: {}", self.path, e))
+  }
+
+  fn save(&self, state: &LoopState) -> Result<(), String> {
+    if state.is_empty() {
+      return match fs::remove_file(&self.path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("remove {:?}: {}", self.path, e)),
+        _ => Ok(()),
+      };
+    }
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(dir).map_err(|e| format!("create {:?}: {}", dir, e))?;
+    }
+    let json = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    atomic_file::write(&self.path, json.as_bytes())
+  }
+}
+
+/// Состояние в памяти — для тестов команд без файлов.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+  state: std::sync::Mutex<Option<LoopState>>,
+}
+
+#[cfg(test)]
+impl MemoryStore {
+  /// Что сейчас «на диске».
+  pub fn saved(&self) -> Option<LoopState> {
+    self.state.lock().unwrap().clone()
+  }
+}
+
+#[cfg(test)]
+impl RotationStore for MemoryStore {
+  fn load(&self) -> Result<Option<LoopState>, String> {
+    Ok(self.saved())
+  }
+
+  fn save(&self, state: &LoopState) -> Result<(), String> {
+    *self.state.lock().unwrap() = Some(state.clone()).filter(|state| !state.is_empty());
+    Ok(())
+  }
+}
@@ -0,0 +1,519 @@
+//! Расписание ротации: какая коллекция показывается в какие часы и дни недели и когда менять
+//! обои (cron). Считается по местному времени устройства; часовой пояс передаётся параметром,
+//! поэтому переходы на летнее время проверяются тестами на конкретных зонах.
+//!
+//! Местное время, которого нет (весенний перевод часов), сдвигается на момент перевода;
+//! повторяющееся осенью берётся один раз, в первый проход. Исключение, как в cron: правило
+//! с `*` в часах срабатывает в повторяющемся часе ещё раз.
+//!
+//! Вне окон правил коллекцию может выбирать Солнце (`SolarRule`): дневная от восхода до заката,
+//! ночная — от заката до восхода. Восход и закат считаются в абсолютном времени, без часового пояса.
+
+use chrono::{Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::solar;
+
+/// Коллекция по времени суток и дням недели.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRule {
+  pub collection_id: String,
+  /// Дни недели 1–7 (пн–вс); пусто — каждый день. Для окна через полночь — день начала.
+  #[serde(default)]
+  pub days: Vec<u8>,
+  /// Начало окна "ЧЧ:ММ" по местному времени.
+  pub start: String,
+  /// Конец окна (не включительно), "24:00" — до полуночи. Конец не позже начала — окно через полночь.
+  pub end: String,
+}
+
+/// Дневная и ночная коллекции по восходу и закату в точке с заданными координатами.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarRule {
+  /// Широта, градусы (северная — положительная).
+  pub latitude: f64,
+  /// Долгота, градусы (восточная — положительная).
+  pub longitude: f64,
+  /// Коллекция от восхода до заката.
+  pub day: String,
+  /// Коллекция от заката до восхода, например затемнённая копия дневной (`imaging::dim_collection`).
+  pub night: String,
+}
+
+impl SolarRule {
+  fn collection(&self, at: u64) -> &str {
+    if solar::is_day(self.latitude, self.longitude, at) {
+      &self.day
+    } else {
+      &self.night
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+  /// Правила проверяются по порядку, действует первое подходящее.
+  #[serde(default)]
+  pub rules: Vec<ScheduleRule>,
+  /// Коллекция вне окон правил; None — вне окон обои не меняются.
+  #[serde(default)]
+  pub fallback: Option<String>,
+  /// День и ночь по Солнцу вне окон правил; с ним `fallback` не используется.
+  #[serde(default)]
+  pub solar: Option<SolarRule>,
+  /// Моменты смены в формате cron ("мин час день месяц день_недели"); None — по interval_minutes.
+  #[serde(default)]
+  pub change_at: Option<String>,
+}
+
+impl ScheduleRule {
+  fn window(&self) -> Option<(u32, u32)> {
+    let start = parse_time(&self.start)?;
+    let end = parse_time(&self.end)?;
+    (start < 24 * 60).then_some((start, end))
+  }
+
+  fn on_day(&self, date: NaiveDate) -> bool {
+    self.days.is_empty() || self.days.contains(&(date.weekday().number_from_monday() as u8))
+  }
+
+  fn covers(&self, local: NaiveDateTime) -> bool {
+    let Some((start, end)) = self.window() else {
+      return false;
+    };
+    let minute = local.hour() * 60 + local.minute();
+    let date = local.date();
+    if start < end {
+      self.on_day(date) && (start..end).contains(&minute)
+    } else {
+      (self.on_day(date) && minute >= start) || (date.pred_opt().is_some_and(|d| self.on_day(d)) && minute < end)
+    }
+  }
+
+  /// Начало и конец окна, открывающегося в `date`.
+  fn edges(&self, date: NaiveDate) -> Option<[NaiveDateTime; 2]> {
+    let (start, end) = self.window()?;
+    if !self.on_day(date) {
+      return None;
+    }
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    let end_day = if end <= start { midnight + Duration::days(1) } else { midnight };
+    Some([midnight + Duration::minutes(start as i64), end_day + Duration::minutes(end as i64)])
+  }
+}
+
+impl Schedule {
+  pub fn validate(&self) -> Result<(), String> {
+    for rule in &self.rules {
+      if rule.collection_id.is_empty() {
+        return Err("Schedule rule without collection".to_string());
+      }
+      if rule.window().is_none() {
+        return Err(format!("Invalid schedule window {}–{}", rule.start, rule.end));
+      }
+      if let Some(day) = rule.days.iter().find(|d| !(1..=7).contains(*d)) {
+        return Err(format!("Invalid weekday: {}", day));
+      }
+    }
+    if let Some(rule) = &self.solar {
+      solar::validate(rule.latitude, rule.longitude)?;
+      if rule.day.is_empty() || rule.night.is_empty() {
+        return Err("Day and night collections are required".to_string());
+      }
+    }
+    if let Some(expr) = &self.change_at {
+      Cron::parse(expr)?;
+    }
+    Ok(())
+  }
+
+  /// Коллекции, которые может выбрать расписание.
+  pub fn collection_ids(&self) -> Vec<&str> {
+    let mut ids: Vec<&str> = self.rules.iter().map(|r| r.collection_id.as_str()).collect();
+    ids.extend(self.fallback.as_deref());
+    if let Some(rule) = &self.solar {
+      ids.extend([rule.day.as_str(), rule.night.as_str()]);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+  }
+
+  /// Коллекция для момента `at` (мс): первое правило, окно которого его покрывает, иначе дневная
+  /// или ночная по Солнцу, иначе `fallback`.
+  pub fn active_collection<Tz: TimeZone>(&self, at: u64, tz: &Tz) -> Option<&str> {
+    let local = local_time(at, tz)?;
+    self
+      .rules
+      .iter()
+      .find(|rule| rule.covers(local))
+      .map(|rule| rule.collection_id.as_str())
+      .or_else(|| self.solar.as_ref().map(|rule| rule.collection(at)))
+      .or(self.fallback.as_deref())
+  }
+
+  /// Ближайшая после `after` граница окна какого-либо правила, восход или закат — момент,
+  /// когда коллекция может смениться.
+  pub fn next_boundary<Tz: TimeZone>(&self, after: u64, tz: &Tz) -> Option<u64> {
+    let today = local_time(after, tz)?.date();
+    let windows = (-1..=8)
+      .filter_map(|offset| today.checked_add_signed(Duration::days(offset)))
+      .flat_map(|date| self.rules.iter().filter_map(move |rule| rule.edges(date)))
+      .flatten()
+      .map(|edge| instant(edge, tz))
+      .filter(|&t| t > after)
+      .min();
+    let sun = self
+      .solar
+      .as_ref()
+      .and_then(|rule| solar::next_change(rule.latitude, rule.longitude, after));
+    windows.into_iter().chain(sun).min()
+  }
+
+  /// Ближайший после `after` момент смены по `change_at`. None — cron не задан или не срабатывает.
+  pub fn next_change<Tz: TimeZone>(&self, after: u64, tz: &Tz) -> Option<u64> {
+    Cron::parse(self.change_at.as_deref()?).ok()?.next_after(after, tz)
+  }
+}
+
+/// Cron из пяти полей: минуты, часы, день месяца, месяц, день недели (0 и 7 — воскресенье).
+/// Поддерживаются `*`, списки, диапазоны, шаги и трёхбуквенные названия месяцев и дней.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+  minutes: u64,
+  hours: u64,
+  days: u64,
+  months: u64,
+  weekdays: u64,
+  any_hour: bool,
+  any_day: bool,
+  any_weekday: bool,
+}
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl Cron {
+  pub fn parse(expr: &str) -> Result<Self, String> {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let [minutes, hours, days, months, weekdays] = fields[..] else {
+      return Err(format!("Cron expression must have 5 fields: {}", expr));
+    };
+    let mut weekday_bits = parse_field(weekdays, 0, 7, &WEEKDAYS, 0)?;
+    if weekday_bits & (1 << 7) != 0 {
+      weekday_bits = (weekday_bits & !(1 << 7)) | 1;
+    }
+    Ok(Self {
+      minutes: parse_field(minutes, 0, 59, &[], 0)?,
+      hours: parse_field(hours, 0, 23, &[], 0)?,
+      days: parse_field(days, 1, 31, &[], 0)?,
+      months: parse_field(months, 1, 12, &MONTHS, 1)?,
+      weekdays: weekday_bits,
+      any_hour: hours.starts_with('*'),
+      any_day: days == "*",
+      any_weekday: weekdays == "*",
+    })
+  }
+
+  /// Как в классическом cron: если заданы и день месяца, и день недели, подходит любой из них.
+  fn matches_date(&self, date: NaiveDate) -> bool {
+    if self.months & (1 << date.month()) == 0 {
+      return false;
+    }
+    let day = self.days & (1 << date.day()) != 0;
+    let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+    match (self.any_day, self.any_weekday) {
+      (true, true) => true,
+      (true, false) => weekday,
+      (false, true) => day,
+      (false, false) => day || weekday,
+    }
+  }
+
+  /// Ближайшее срабатывание строго после `after` (мс). Ищет в пределах 8 лет (29 февраля).
+  pub fn next_after<Tz: TimeZone>(&self, after: u64, tz: &Tz) -> Option<u64> {
+    let start = local_time(after, tz)?.date();
+    for offset in 0..366 * 8 {
+      let date = start.checked_add_signed(Duration::days(offset))?;
+      if !self.matches_date(date) {
+        continue;
+      }
+      // В повторяющемся часе порядок моментов не совпадает с порядком местного времени — берём минимум за день
+      let mut next: Option<u64> = None;
+      for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+        for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0) {
+          let wall = date.and_hms_opt(hour, minute, 0)?;
+          let repeat = match tz.from_local_datetime(&wall) {
+            LocalResult::Ambiguous(_, second) if self.any_hour => Some(second.timestamp_millis().max(0) as u64),
+            _ => None,
+          };
+          for t in [Some(instant(wall, tz)), repeat].into_iter().flatten() {
+            if t > after && next.map_or(true, |n| t < n) {
+              next = Some(t);
+            }
+          }
+        }
+      }
+      if next.is_some() {
+        return next;
+      }
+    }
+    None
+  }
+}
+
+/// Поле cron → битовая маска допустимых значений.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first_name: u32) -> Result<u64, String> {
+  let value = |s: &str| -> Result<u32, String> {
+    let lower = s.to_ascii_lowercase();
+    let v = match names.iter().position(|n| *n == lower) {
+      Some(i) => i as u32 + first_name,
+      None => s.parse().map_err(|_| format!("Invalid cron value: {}", s))?,
+    };
+    if (min..=max).contains(&v) {
+      Ok(v)
+    } else {
+      Err(format!("Cron value out of range {}–{}: {}", min, max, s))
+    }
+  };
+  let mut bits = 0u64;
+  for part in field.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(|| format!("Invalid cron step: {}", part))?),
+      None => (part, 1),
+    };
+    let (from, to) = match range {
+      "*" => (min, max),
+      _ => match range.split_once('-') {
+        Some((a, b)) => (value(a)?, value(b)?),
+        // "5/15" — с 5 до конца диапазона
+        None if step > 1 => (value(range)?, max),
+        None => {
+          let v = value(range)?;
+          (v, v)
+        }
+      },
+    };
+    if from > to {
+      return Err(format!("Invalid cron range: {}", part));
+    }
+    for v in (from..=to).step_by(step as usize) {
+      bits |= 1 << v;
+    }
+  }
+  Ok(bits)
+}
+
+/// "ЧЧ:ММ" → минуты от полуночи; "24:00" допускается как конец дня.
+fn parse_time(s: &str) -> Option<u32> {
+  let (h, m) = s.trim().split_once(':')?;
+  let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+  (m < 60 && (h < 24 || (h == 24 && m == 0))).then_some(h * 60 + m)
+}
+
+fn local_time<Tz: TimeZone>(at: u64, tz: &Tz) -> Option<NaiveDateTime> {
+  tz.timestamp_millis_opt(at as i64).single().map(|t| t.naive_local())
+}
+
+/// Первый момент (мс), когда местное время достигает `local`: для несуществующего времени — момент
+/// перевода часов, для повторяющегося — первый проход.
+fn instant<Tz: TimeZone>(local: NaiveDateTime, tz: &Tz) -> u64 {
+  let mut wall = local;
+  for _ in 0..24 * 60 {
+    if let Some(t) = tz.from_local_datetime(&wall).earliest() {
+      return t.timestamp_millis().max(0) as u64;
+    }
+    wall += Duration::minutes(1);
+  }
+  local.and_utc().timestamp_millis().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Utc;
+  use chrono_tz::Europe::Berlin;
+
+  fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> u64 {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap().timestamp_millis() as u64
+  }
+
+  fn berlin(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> u64 {
+    Berlin.with_ymd_and_hms(y, mo, d, h, mi, 0).earliest().unwrap().timestamp_millis() as u64
+  }
+
+  fn rule(collection_id: &str, days: &[u8], start: &str, end: &str) -> ScheduleRule {
+    ScheduleRule {
+      collection_id: collection_id.into(),
+      days: days.to_vec(),
+      start: start.into(),
+      end: end.into(),
+    }
+  }
+
+  /// "A в будни 07:00–18:00, иначе B".
+  fn workdays() -> Schedule {
+    Schedule {
+      rules: vec![rule("A", &[1, 2, 3, 4, 5], "07:00", "18:00")],
+      fallback: Some("B".into()),
+      solar: None,
+      change_at: None,
+    }
+  }
+
+  #[test]
+  fn weekday_window_with_fallback() {
+    let schedule = workdays();
+    schedule.validate().unwrap();
+    assert_eq!(schedule.collection_ids(), vec!["A", "B"]);
+    // 2026-10-14 — среда, 2026-10-17 — суббота
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 7, 0), &Berlin), Some("A"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 17, 59), &Berlin), Some("A"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 18, 0), &Berlin), Some("B"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 17, 10, 0), &Berlin), Some("B"));
+
+    assert_eq!(schedule.next_boundary(berlin(2026, 10, 14, 10, 0), &Berlin), Some(berlin(2026, 10, 14, 18, 0)));
+    // Пятница вечером → понедельник утром
+    assert_eq!(schedule.next_boundary(berlin(2026, 10, 16, 18, 0), &Berlin), Some(berlin(2026, 10, 19, 7, 0)));
+
+    let no_fallback = Schedule { fallback: None, ..workdays() };
+    assert_eq!(no_fallback.active_collection(berlin(2026, 10, 17, 10, 0), &Berlin), None);
+    assert_eq!(Schedule::default().next_boundary(0, &Berlin), None);
+  }
+
+  #[test]
+  fn overnight_window_belongs_to_start_day() {
+    let schedule = Schedule {
+      rules: vec![rule("night", &[5], "22:00", "06:00")],
+      ..Schedule::default()
+    };
+    // Пятница 2026-10-16
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 16, 21, 59), &Berlin), None);
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 16, 23, 0), &Berlin), Some("night"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 17, 5, 59), &Berlin), Some("night"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 17, 23, 0), &Berlin), None);
+    assert_eq!(schedule.next_boundary(berlin(2026, 10, 16, 23, 0), &Berlin), Some(berlin(2026, 10, 17, 6, 0)));
+  }
+
+  #[test]
+  fn window_boundaries_follow_local_time_across_dst() {
+    let schedule = Schedule {
+      rules: vec![rule("day", &[], "07:00", "18:00")],
+      ..Schedule::default()
+    };
+    // 29 марта 2026 в Берлине часы переводятся вперёд: 07:00 — это уже 05:00 UTC, а не 06:00
+    assert_eq!(schedule.next_boundary(berlin(2026, 3, 28, 19, 0), &Berlin), Some(utc(2026, 3, 29, 5, 0)));
+    assert_eq!(schedule.next_boundary(berlin(2026, 3, 28, 10, 0), &Berlin), Some(utc(2026, 3, 28, 17, 0)));
+    // 25 октября часы переводятся назад: 07:00 снова 06:00 UTC
+    assert_eq!(schedule.next_boundary(berlin(2026, 10, 24, 19, 0), &Berlin), Some(utc(2026, 10, 25, 6, 0)));
+    assert_eq!(schedule.active_collection(utc(2026, 10, 25, 5, 30), &Berlin), None);
+    assert_eq!(schedule.active_collection(utc(2026, 10, 25, 6, 30), &Berlin), Some("day"));
+  }
+
+  #[test]
+  fn solar_day_and_night_outside_rule_windows() {
+    // Берлин, 14 октября 2026: восход 07:29, закат 18:15 по местному времени
+    let schedule = Schedule {
+      rules: vec![rule("work", &[3], "09:00", "12:00")],
+      solar: Some(SolarRule {
+        latitude: 52.52,
+        longitude: 13.405,
+        day: "day".into(),
+        night: "night".into(),
+      }),
+      fallback: Some("unused".into()),
+      ..Schedule::default()
+    };
+    schedule.validate().unwrap();
+    assert_eq!(schedule.collection_ids(), vec!["day", "night", "unused", "work"]);
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 7, 0), &Berlin), Some("night"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 8, 0), &Berlin), Some("day"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 10, 0), &Berlin), Some("work"));
+    assert_eq!(schedule.active_collection(berlin(2026, 10, 14, 19, 0), &Berlin), Some("night"));
+
+    let sunrise = schedule.next_boundary(berlin(2026, 10, 14, 6, 0), &Berlin).unwrap();
+    assert!(sunrise > berlin(2026, 10, 14, 7, 27) && sunrise < berlin(2026, 10, 14, 7, 31), "{}", sunrise);
+    assert_eq!(schedule.next_boundary(sunrise, &Berlin), Some(berlin(2026, 10, 14, 9, 0)));
+    let sunset = schedule.next_boundary(berlin(2026, 10, 14, 12, 0), &Berlin).unwrap();
+    assert!(sunset > berlin(2026, 10, 14, 18, 13) && sunset < berlin(2026, 10, 14, 18, 17), "{}", sunset);
+
+    // Заполярье в полярную ночь: ночная коллекция весь день, следующая смена — только в январе
+    let polar = Schedule {
+      solar: Some(SolarRule {
+        latitude: 69.6492,
+        longitude: 18.9553,
+        day: "day".into(),
+        night: "night".into(),
+      }),
+      ..Schedule::default()
+    };
+    let noon = utc(2026, 12, 21, 11, 0);
+    assert_eq!(polar.active_collection(noon, &Berlin), Some("night"));
+    assert!(polar.next_boundary(noon, &Berlin).unwrap() > utc(2027, 1, 14, 0, 0));
+
+    let invalid = |latitude: f64, day: &str| {
+      Schedule {
+        solar: Some(SolarRule {
+          latitude,
+          longitude: 0.0,
+          day: day.into(),
+          night: "night".into(),
+        }),
+        ..Schedule::default()
+      }
+      .validate()
+      .is_err()
+    };
+    assert!(invalid(91.0, "day"));
+    assert!(invalid(45.0, ""));
+    assert!(!invalid(-90.0, "day"));
+  }
+
+  #[test]
+  fn cron_in_skipped_and_repeated_hours() {
+    let cron = Cron::parse("30 2 * * *").unwrap();
+    // 02:30 29 марта не существует — смена в момент перевода (03:00 CEST = 01:00 UTC)
+    assert_eq!(cron.next_after(berlin(2026, 3, 29, 0, 0), &Berlin), Some(utc(2026, 3, 29, 1, 0)));
+    assert_eq!(cron.next_after(utc(2026, 3, 29, 1, 0), &Berlin), Some(utc(2026, 3, 30, 0, 30)));
+    // 02:30 25 октября бывает дважды — срабатывает один раз, в первый проход (CEST)
+    assert_eq!(cron.next_after(berlin(2026, 10, 25, 0, 0), &Berlin), Some(utc(2026, 10, 25, 0, 30)));
+    assert_eq!(cron.next_after(utc(2026, 10, 25, 0, 30), &Berlin), Some(utc(2026, 10, 26, 1, 30)));
+
+    // С `*` в часах повторяющийся час не пропускается: смены каждые 15 минут без перерыва
+    let quarter = Cron::parse("*/15 * * * *").unwrap();
+    let mut t = utc(2026, 10, 25, 0, 50);
+    for expected in [0, 15, 30, 45, 60, 75] {
+      t = quarter.next_after(t, &Berlin).unwrap();
+      assert_eq!(t, utc(2026, 10, 25, 1, 0) + expected * 60_000);
+    }
+  }
+
+  #[test]
+  fn cron_fields_and_errors() {
+    let cron = Cron::parse("0 7,19 * * mon-fri").unwrap();
+    // Суббота 2026-10-17 → понедельник 07:00
+    assert_eq!(cron.next_after(berlin(2026, 10, 17, 8, 0), &Berlin), Some(berlin(2026, 10, 19, 7, 0)));
+    assert_eq!(cron.next_after(berlin(2026, 10, 19, 7, 0), &Berlin), Some(berlin(2026, 10, 19, 19, 0)));
+
+    // День месяца или день недели — как в классическом cron
+    let either = Cron::parse("0 0 1 * 0").unwrap();
+    assert_eq!(either.next_after(berlin(2026, 10, 17, 0, 0), &Berlin), Some(berlin(2026, 10, 18, 0, 0)));
+    assert_eq!(either.next_after(berlin(2026, 10, 26, 0, 0), &Berlin), Some(berlin(2026, 11, 1, 0, 0)));
+    assert_eq!(Cron::parse("0 12 * * 7").unwrap(), Cron::parse("0 12 * * SUN").unwrap());
+    assert_eq!(Cron::parse("5/20 * * * *").unwrap(), Cron::parse("5,25,45 * * * *").unwrap());
+
+    // 29 февраля: ближайший високосный год
+    let leap = Cron::parse("0 0 29 feb *").unwrap();
+    assert_eq!(leap.next_after(berlin(2026, 1, 1, 0, 0), &Berlin), Some(berlin(2028, 2, 29, 0, 0)));
+    assert_eq!(Cron::parse("0 0 31 2 *").unwrap().next_after(0, &Berlin), None);
+
+    for bad in ["", "* * * *", "60 * * * *", "* 24 * * *", "*/0 * * * *", "5-1 * * * *", "x * * * *"] {
+      assert!(Cron::parse(bad).is_err(), "{:?}", bad);
+    }
+    let invalid = |rule: ScheduleRule| Schedule { rules: vec![rule], ..Schedule::default() }.validate().is_err();
+    assert!(invalid(rule("A", &[], "7:00", "25:00")));
+    assert!(invalid(rule("A", &[8], "07:00", "18:00")));
+    assert!(invalid(rule("", &[], "07:00", "18:00")));
+    assert!(!invalid(rule("A", &[], "18:00", "24:00")));
+  }
+}
@@ -0,0 +1,247 @@
+//! Восход и закат по широте и долготе без службы геолокации — по формулам NOAA (уравнение
+//! восхода, точность около минуты). Нужны расписанию для коллекций «день» и «ночь».
+//!
+//! Время считается в абсолютных моментах (мс с эпохи Unix), часовой пояс не нужен. Полярный
+//! день и полярная ночь дают вместо восхода и заката одно событие в начале суток, поэтому день
+//! или ночь в любой момент определяется последним событием перед ним.
+
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+/// Высота центра Солнца на восходе и закате с учётом рефракции и радиуса диска, градусы.
+const HORIZON_DEG: f64 = -0.833;
+/// Наклон оси Земли, градусы.
+const OBLIQUITY_DEG: f64 = 23.4397;
+const J2000: f64 = 2_451_545.0;
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+const DAY_MS: f64 = 86_400_000.0;
+/// Дальше года вперёд смену дня и ночи не ищем (на полюсе она раз в полгода).
+const SEARCH_DAYS: i64 = 370;
+
+/// Солнце в течение суток.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SolarDay {
+  /// Восход и закат, мс.
+  Normal { sunrise: u64, sunset: u64 },
+  /// Солнце не заходит.
+  PolarDay,
+  /// Солнце не восходит.
+  PolarNight,
+}
+
+/// Смена дня и ночи: момент (мс) и что наступает.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Event {
+  at: u64,
+  day: bool,
+}
+
+/// Восход и закат для солнечных суток `date` на долготе `longitude` (восточная — положительная).
+pub fn solar_day(latitude: f64, longitude: f64, date: NaiveDate) -> SolarDay {
+  let (transit, cos_hour_angle) = transit_and_hour_angle(latitude, longitude, date);
+  if cos_hour_angle < -1.0 {
+    return SolarDay::PolarDay;
+  }
+  if cos_hour_angle > 1.0 {
+    return SolarDay::PolarNight;
+  }
+  let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+  SolarDay::Normal {
+    sunrise: jd_to_millis(transit - half_day),
+    sunset: jd_to_millis(transit + half_day),
+  }
+}
+
+/// День ли в момент `at`: Солнце выше горизонта.
+pub fn is_day(latitude: f64, longitude: f64, at: u64) -> bool {
+  let date = utc_date(at);
+  let events = events(latitude, longitude, date - Duration::days(2), date + Duration::days(2));
+  events.iter().rev().find(|e| e.at <= at).is_some_and(|e| e.day)
+}
+
+/// Ближайший после `after` восход или закат, после которого день сменяется ночью или наоборот.
+/// Начало и конец полярного дня и ночи тоже считаются.
+pub fn next_change(latitude: f64, longitude: f64, after: u64) -> Option<u64> {
+  let day = is_day(latitude, longitude, after);
+  let mut from = utc_date(after) - Duration::days(1);
+  let last = utc_date(after) + Duration::days(SEARCH_DAYS);
+  // Порциями: в обычных широтах смена находится в первых же сутках
+  while from < last {
+    let to = from + Duration::days(30);
+    if let Some(event) = events(latitude, longitude, from, to).into_iter().find(|e| e.at > after && e.day != day) {
+      return Some(event.at);
+    }
+    from = to + Duration::days(1);
+  }
+  None
+}
+
+/// События суток с `from` по `to` включительно, по времени. Полярный день и ночь — событие
+/// за полсуток до кульминации: так у каждых суток есть хотя бы одно событие.
+fn events(latitude: f64, longitude: f64, from: NaiveDate, to: NaiveDate) -> Vec<Event> {
+  let mut events = Vec::new();
+  let mut date = from;
+  while date <= to {
+    match solar_day(latitude, longitude, date) {
+      SolarDay::Normal { sunrise, sunset } => {
+        events.push(Event { at: sunrise, day: true });
+        events.push(Event { at: sunset, day: false });
+      }
+      polar => {
+        let (transit, _) = transit_and_hour_angle(latitude, longitude, date);
+        events.push(Event {
+          at: jd_to_millis(transit - 0.5),
+          day: polar == SolarDay::PolarDay,
+        });
+      }
+    }
+    date += Duration::days(1);
+  }
+  // На границе полярного дня соседние сутки могут перекрываться на секунды
+  events.sort_by_key(|e| e.at);
+  events
+}
+
+/// Юлианская дата кульминации и косинус часового угла восхода (вне [-1, 1] — полярные сутки).
+fn transit_and_hour_angle(latitude: f64, longitude: f64, date: NaiveDate) -> (f64, f64) {
+  let noon = Utc.from_utc_datetime(&date.and_hms_opt(12, 0, 0).unwrap_or_default()).timestamp_millis();
+  let n = (noon as f64 / DAY_MS + UNIX_EPOCH_JD - J2000 + 0.0008).round();
+  let mean_noon = n - longitude / 360.0;
+  let anomaly = (357.5291 + 0.985_600_28 * mean_noon).rem_euclid(360.0).to_radians();
+  let center = 1.9148 * anomaly.sin() + 0.0200 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+  let ecliptic = (anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+  let transit = J2000 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+  let declination = (ecliptic.sin() * OBLIQUITY_DEG.to_radians().sin()).asin();
+  let lat = latitude.to_radians();
+  let cos_hour_angle = (HORIZON_DEG.to_radians().sin() - lat.sin() * declination.sin()) / (lat.cos() * declination.cos());
+  (transit, cos_hour_angle)
+}
+
+fn jd_to_millis(jd: f64) -> u64 {
+  ((jd - UNIX_EPOCH_JD) * DAY_MS).round().max(0.0) as u64
+}
+
+fn utc_date(at: u64) -> NaiveDate {
+  Utc
+    .timestamp_millis_opt(at as i64)
+    .single()
+    .map(|t| t.date_naive())
+    .unwrap_or_default()
+}
+
+/// Широта и долгота в допустимых пределах.
+pub fn validate(latitude: f64, longitude: f64) -> Result<(), String> {
+  if !(-90.0..=90.0).contains(&latitude) {
+    return Err(format!("Invalid latitude: {}", latitude));
+  }
+  if !(-180.0..=180.0).contains(&longitude) {
+    return Err(format!("Invalid longitude: {}", longitude));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+  }
+
+  fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> u64 {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap().timestamp_millis() as u64
+  }
+
+  /// Отличие от справочного времени не больше двух минут.
+  fn near(actual: u64, expected: u64) -> bool {
+    actual.abs_diff(expected) <= 2 * 60_000
+  }
+
+  // Тромсё, 69.65° с. ш.: полярная ночь с 27 ноября по 15 января, полярный день — с 18 мая по 26 июля
+  const TROMSO: (f64, f64) = (69.6492, 18.9553);
+
+  #[test]
+  fn sunrise_and_sunset_in_mid_latitudes() {
+    // Берлин, 14 октября 2026: восход 07:29, закат 18:15 по летнему времени (05:29 и 16:15 UTC)
+    let SolarDay::Normal { sunrise, sunset } = solar_day(52.52, 13.405, date(2026, 10, 14)) else {
+      panic!("expected normal day");
+    };
+    assert!(near(sunrise, utc(2026, 10, 14, 5, 29)), "{}", sunrise);
+    assert!(near(sunset, utc(2026, 10, 14, 16, 15)), "{}", sunset);
+
+    assert!(!is_day(52.52, 13.405, utc(2026, 10, 14, 5, 0)));
+    assert!(is_day(52.52, 13.405, utc(2026, 10, 14, 12, 0)));
+    assert!(!is_day(52.52, 13.405, utc(2026, 10, 14, 20, 0)));
+    assert_eq!(next_change(52.52, 13.405, utc(2026, 10, 14, 12, 0)), Some(sunset));
+    assert_eq!(next_change(52.52, 13.405, utc(2026, 10, 14, 1, 0)), Some(sunrise));
+    // Ровно в момент смены уже наступило новое время суток, следующая смена — закат
+    assert!(is_day(52.52, 13.405, sunrise));
+    assert_eq!(next_change(52.52, 13.405, sunrise), Some(sunset));
+
+    // Западное полушарие: Нью-Йорк, 21 июня 2026 — восход 09:25 UTC, закат 00:31 UTC следующих суток
+    let SolarDay::Normal { sunrise, sunset } = solar_day(40.7128, -74.006, date(2026, 6, 21)) else {
+      panic!("expected normal day");
+    };
+    assert!(near(sunrise, utc(2026, 6, 21, 9, 25)), "{}", sunrise);
+    assert!(near(sunset, utc(2026, 6, 22, 0, 31)), "{}", sunset);
+    assert!(is_day(40.7128, -74.006, utc(2026, 6, 22, 0, 0)));
+  }
+
+  #[test]
+  fn polar_night_has_no_day_until_the_sun_returns() {
+    let (lat, lon) = TROMSO;
+    assert_eq!(solar_day(lat, lon, date(2026, 12, 21)), SolarDay::PolarNight);
+    for h in [0, 6, 11, 12, 18] {
+      assert!(!is_day(lat, lon, utc(2026, 12, 21, h, 0)), "{}h", h);
+    }
+    // Первый восход после полярной ночи — в середине января, около полудня по местному времени
+    let first = next_change(lat, lon, utc(2026, 12, 21, 12, 0)).unwrap();
+    assert!(first > utc(2027, 1, 14, 0, 0) && first < utc(2027, 1, 17, 0, 0), "{}", first);
+    assert!(is_day(lat, lon, first));
+    let SolarDay::Normal { sunrise, sunset } = solar_day(lat, lon, utc_date(first)) else {
+      panic!("expected sunrise on {}", utc_date(first));
+    };
+    assert_eq!(first, sunrise);
+    assert_eq!(next_change(lat, lon, first), Some(sunset));
+    assert!(sunset - sunrise < 2 * 60 * 60_000);
+  }
+
+  #[test]
+  fn polar_day_has_no_night_until_the_first_sunset() {
+    let (lat, lon) = TROMSO;
+    assert_eq!(solar_day(lat, lon, date(2026, 6, 21)), SolarDay::PolarDay);
+    for h in [0, 6, 12, 18, 23] {
+      assert!(is_day(lat, lon, utc(2026, 6, 21, h, 0)), "{}h", h);
+    }
+    // Полярный день кончается закатом в конце июля, около полуночи по местному времени
+    let end = next_change(lat, lon, utc(2026, 6, 21, 12, 0)).unwrap();
+    assert!(end > utc(2026, 7, 25, 0, 0) && end < utc(2026, 7, 28, 0, 0), "{}", end);
+    assert!(!is_day(lat, lon, end));
+    assert!(is_day(lat, lon, end - 1));
+    // Смены дня и ночи чередуются и идут по возрастанию
+    let mut t = end;
+    let mut day = false;
+    for _ in 0..20 {
+      let next = next_change(lat, lon, t).unwrap();
+      assert!(next > t);
+      day = !day;
+      assert_eq!(is_day(lat, lon, next), day);
+      t = next;
+    }
+  }
+
+  #[test]
+  fn poles_change_twice_a_year() {
+    // Северный полюс: полгода день, полгода ночь; смены — у равноденствий
+    let sunset = next_change(90.0, 0.0, utc(2026, 6, 21, 0, 0)).unwrap();
+    assert!(sunset > utc(2026, 9, 20, 0, 0) && sunset < utc(2026, 10, 1, 0, 0), "{}", sunset);
+    let sunrise = next_change(90.0, 0.0, sunset).unwrap();
+    assert!(sunrise > utc(2027, 3, 10, 0, 0) && sunrise < utc(2027, 3, 25, 0, 0), "{}", sunrise);
+    // Южный полюс в то же время — наоборот
+    assert!(!is_day(-90.0, 0.0, utc(2026, 6, 21, 0, 0)));
+    assert!(is_day(-90.0, 0.0, utc(2026, 12, 21, 0, 0)));
+
+    assert!(validate(69.6, 18.9).is_ok());
+    assert!(validate(90.5, 0.0).is_err());
+    assert!(validate(0.0, -180.5).is_err());
+  }
+}
@@ -0,0 +1,303 @@
+//! Кеш миниатюр для сеток коллекций, чтобы не читать полные изображения.
+//!
+//! Миниатюры лежат в папке `_thumbs` рядом с исходником: `collections/{id}/_thumbs` для файлов
+//! коллекции и `blobs/_thumbs` для общего хранилища (блоб один на все коллекции — миниатюра тоже).
+//! Имя: `{файл}.{размер}.{отпечаток}.jpg`, где отпечаток — размер и mtime исходника. Изменённый
+//! исходник даёт новый отпечаток, и миниатюра пересоздаётся, а старая удаляется. Миниатюры
+//! зашифрованных коллекций шифруются ключом коллекции.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::atomic_file;
+use crate::blobs::{self, BlobStore};
+use crate::collection;
+use crate::crypto;
+use crate::error::AppError;
+use crate::imaging;
+use crate::paths;
+
+/// Папка кеша миниатюр (`_`-префикс — служебная, list_collection_files её не показывает).
+pub const THUMBS_DIR: &str = "_thumbs";
+/// Допустимые размеры (по длинной стороне). Запрошенный размер округляется вверх до ближайшего,
+/// чтобы кеш не разрастался от произвольных значений.
+pub const SIZES: [u32; 4] = [128, 256, 512, 1024];
+/// Размер, который создаётся сразу при импорте (сетка коллекции).
+pub const DEFAULT_SIZE: u32 = 256;
+const JPEG_QUALITY: u8 = 85;
+const THUMB_EXT: &str = "jpg";
+
+/// Где лежит исходник и куда класть его миниатюры.
+struct Source {
+  path: PathBuf,
+  cache_dir: PathBuf,
+  /// Имя исходника — префикс имён его миниатюр.
+  key: String,
+  /// Коллекция, если исходник зашифрован.
+  encrypted: Option<String>,
+}
+
+/// Миниатюра (JPEG) изображения по пути относительно base (`collections/...` или `blobs/...`).
+/// Берётся из кеша или создаётся и кешируется.
+pub fn get(base: &Path, path: &str, size: u32) -> Result<Vec<u8>, AppError> {
+  let source = locate(base, path)?;
+  let size = bucket(size);
+  let fingerprint = fingerprint(&source.path).map_err(|_| AppError::not_found("file", path))?;
+  let thumb = source.cache_dir.join(thumb_name(&source.key, size, &fingerprint));
+  if thumb.is_file() {
+    return match &source.encrypted {
+      Some(collection_id) => decrypt(collection_id, &thumb),
+      None => fs::read(&thumb).map_err(|e| AppError::io(&thumb, e)),
+    };
+  }
+
+  let data = match &source.encrypted {
+    Some(collection_id) => decrypt(collection_id, &source.path)?,
+    None => fs::read(&source.path).map_err(|e| AppError::io(&source.path, e))?,
+  };
+  let img = imaging::decode_bytes(&data).map_err(|e| AppError::decode_failed(path, e))?;
+  let jpeg = render(&img, size)?;
+  remove_stale(&source.cache_dir, &source.key, size);
+  fs::create_dir_all(&source.cache_dir).map_err(|e| AppError::io(&source.cache_dir, e))?;
+  match &source.encrypted {
+    Some(collection_id) => crypto::with_session(collection_id, |session| {
+      atomic_file::write_with(&thumb, |file| {
+        crypto::encrypt(session, &mut jpeg.as_slice(), jpeg.len() as u64, file)
+      })
+    })?,
+    None => atomic_file::write(&thumb, &jpeg)?,
+  }
+  Ok(jpeg)
+}
+
+/// Удалить миниатюры, исходники которых исчезли или изменились (удалённые элементы,
+/// блобы без ссылок). Вызывается при запуске после сверки хранилища.
+pub fn prune(base: &Path) -> Result<(), String> {
+  let mut dirs = vec![base.join(blobs::BLOBS_DIR).join(THUMBS_DIR)];
+  let collections = base.join("collections");
+  if collections.is_dir() {
+    for entry in fs::read_dir(&collections).map_err(|e| e.to_string())? {
+      dirs.push(entry.map_err(|e| e.to_string())?.path().join(THUMBS_DIR));
+    }
+  }
+  let store = BlobStore::new(base);
+  for dir in dirs.into_iter().filter(|d| d.is_dir()) {
+    let in_blobs = dir.parent().and_then(|p| p.file_name()) == Some(blobs::BLOBS_DIR.as_ref());
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+      let path = entry.map_err(|e| e.to_string())?.path();
+      let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+      let valid = parse_thumb_name(name).is_some_and(|(key, _, fp)| {
+        let source = if in_blobs {
+          store.path(key).ok()
+        } else {
+          paths::validate_segment(key).ok().map(|_| dir.parent().unwrap().join(key))
+        };
+        source.and_then(|s| fingerprint(&s).ok()).as_deref() == Some(fp)
+      });
+      if !valid {
+        log::info!("thumbnails: removing stale {:?}", path);
+        let _ = fs::remove_file(&path);
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Ближайший допустимый размер не меньше запрошенного.
+pub fn bucket(size: u32) -> u32 {
+  SIZES
+    .iter()
+    .copied()
+    .find(|&s| s >= size)
+    .unwrap_or(SIZES[SIZES.len() - 1])
+}
+
+fn locate(base: &Path, path: &str) -> Result<Source, AppError> {
+  let full = paths::resolve(base, path)?;
+  let base = paths::resolve(base, ".")?;
+  let rel = full
+    .strip_prefix(&base)
+    .map_err(|_| AppError::path_not_allowed(path))?;
+  let parts: Vec<&str> = rel
+    .components()
+    .map(|c| match c {
+      Component::Normal(part) => part.to_str().unwrap_or_default(),
+      _ => "",
+    })
+    .collect();
+  match parts.as_slice() {
+    ["collections", id, file] if !file.starts_with('_') => {
+      let dir = base.join("collections").join(id);
+      let encrypted = collection::read_meta(&dir)?.encryption.map(|_| id.to_string());
+      Ok(Source {
+        path: full.clone(),
+        cache_dir: dir.join(THUMBS_DIR),
+        key: file.to_string(),
+        encrypted,
+      })
+    }
+    [blobs_dir, _, id] if *blobs_dir == blobs::BLOBS_DIR && blobs::validate_blob_id(id).is_ok() => Ok(Source {
+      path: full.clone(),
+      cache_dir: base.join(blobs::BLOBS_DIR).join(THUMBS_DIR),
+      key: id.to_string(),
+      encrypted: None,
+    }),
+    // Служебные файлы и пути вне коллекций и хранилища
+    _ => Err(AppError::Unsupported { feature: "thumbnail" }),
+  }
+}
+
+/// Размер и mtime исходника в hex — меняется при любой перезаписи файла.
+fn fingerprint(path: &Path) -> Result<String, String> {
+  let meta = fs::metadata(path).map_err(|e| format!("stat {:?}: {}", path, e))?;
+  if !meta.is_file() {
+    return Err(format!("{:?} is not a file", path));
+  }
+  let mtime = meta
+    .modified()
+    .ok()
+    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+    .map(|d| d.as_nanos())
+    .unwrap_or(0);
+  Ok(format!("{:x}-{:x}", meta.len(), mtime))
+}
+
+fn thumb_name(key: &str, size: u32, fingerprint: &str) -> String {
+  format!("{}.{}.{}.{}", key, size, fingerprint, THUMB_EXT)
+}
+
+/// (имя исходника, размер, отпечаток) из имени миниатюры.
+fn parse_thumb_name(name: &str) -> Option<(&str, u32, &str)> {
+  let mut parts = name.rsplitn(4, '.');
+  let (ext, fp, size, key) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+  if ext != THUMB_EXT || key.is_empty() {
+    return None;
+  }
+  Some((key, size.parse().ok()?, fp))
+}
+
+/// Удалить миниатюры исходника этого размера (со старым отпечатком).
+fn remove_stale(cache_dir: &Path, key: &str, size: u32) {
+  let Ok(entries) = fs::read_dir(cache_dir) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let name = entry.file_name();
+    let stale = parse_thumb_name(name.to_str().unwrap_or_default())
+      .is_some_and(|(k, s, _)| k == key && s == size);
+    if stale {
+      let _ = fs::remove_file(entry.path());
+    }
+  }
+}
+
+fn render(img: &DynamicImage, size: u32) -> Result<Vec<u8>, String> {
+  let thumb = if img.width() > size || img.height() > size {
+    img.thumbnail(size, size)
+  } else {
+    img.clone()
+  };
+  let mut out = Vec::new();
+  JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+    .encode_image(&DynamicImage::ImageRgb8(thumb.into_rgb8()))
+    .map_err(|e| format!("encode thumbnail: {}", e))?;
+  Ok(out)
+}
+
+/// Расшифровать файл потоком, не читая шифротекст в память целиком.
+fn decrypt(collection_id: &str, path: &Path) -> Result<Vec<u8>, AppError> {
+  let file = fs::File::open(path).map_err(|e| AppError::io(path, e))?;
+  let mut plain = Vec::new();
+  crypto::with_session(collection_id, |session| {
+    crypto::decrypt(session, &mut std::io::BufReader::new(file), &mut plain)
+  })?;
+  Ok(plain)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::{ImageFormat, Rgb, RgbImage};
+
+  fn write_png(path: &Path, width: u32, height: u32, color: [u8; 3]) {
+    RgbImage::from_pixel(width, height, Rgb(color))
+      .save_with_format(path, ImageFormat::Png)
+      .unwrap();
+  }
+
+  fn thumbs(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+      .map(|rd| rd.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+      .unwrap_or_default();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn generates_caches_and_invalidates() {
+    let (_tmp, base) = collection::test_base("c1", None, &[]);
+    let src = base.join("collections/c1/a.png");
+    write_png(&src, 800, 400, [255, 0, 0]);
+
+    let jpeg = get(&base, "collections/c1/a.png", 200).unwrap();
+    let img = image::load_from_memory(&jpeg).unwrap();
+    assert_eq!((img.width(), img.height()), (256, 128));
+    let cache = base.join("collections/c1").join(THUMBS_DIR);
+    let cached = thumbs(&cache);
+    assert_eq!(cached.len(), 1);
+    assert!(cached[0].starts_with("a.png.256."));
+    assert_eq!(get(&base, "collections/c1/a.png", 256).unwrap(), jpeg);
+
+    // Исходник заменён — старая миниатюра удаляется, новая соответствует новому файлу
+    write_png(&src, 100, 200, [0, 0, 255]);
+    let img = image::load_from_memory(&get(&base, "collections/c1/a.png", 256).unwrap()).unwrap();
+    assert_eq!((img.width(), img.height()), (100, 200));
+    assert_eq!(thumbs(&cache).len(), 1);
+    assert_ne!(thumbs(&cache), cached);
+
+    assert_eq!(
+      get(&base, "collections/c1/_meta.json", 256),
+      Err(AppError::Unsupported { feature: "thumbnail" })
+    );
+    assert!(matches!(
+      get(&base, "collections/c1/../c1/a.png", 256),
+      Err(AppError::PathNotAllowed { .. })
+    ));
+    assert_eq!(get(&base, "collections/c1/b.png", 256), Err(AppError::not_found("file", "collections/c1/b.png")));
+  }
+
+  #[test]
+  fn blob_thumbnails_survive_reconcile_and_are_pruned() {
+    let (tmp, base) = collection::test_base("c1", None, &[]);
+    let src = tmp.path().join("b.png");
+    write_png(&src, 2000, 1000, [0, 255, 0]);
+    let store = BlobStore::new(&base);
+    let id = store.put_file(&src, "b.png").unwrap();
+    store.retain(&id).unwrap();
+
+    get(&base, &blobs::relative_path(&id), 128).unwrap();
+    let cache = base.join(blobs::BLOBS_DIR).join(THUMBS_DIR);
+    assert_eq!(thumbs(&cache).len(), 1);
+
+    store.reconcile(&[(id.clone(), 1)].into_iter().collect()).unwrap();
+    prune(&base).unwrap();
+    assert_eq!(thumbs(&cache).len(), 1);
+
+    store.release(&id).unwrap();
+    prune(&base).unwrap();
+    assert!(thumbs(&cache).is_empty());
+  }
+
+  #[test]
+  fn bucket_rounds_up() {
+    assert_eq!(bucket(1), 128);
+    assert_eq!(bucket(256), 256);
+    assert_eq!(bucket(300), 512);
+    assert_eq!(bucket(5000), 1024);
+    assert_eq!(parse_thumb_name("a.b.webp.256.1f-2a.jpg"), Some(("a.b.webp", 256, "1f-2a")));
+    assert_eq!(parse_thumb_name("junk.txt"), None);
+  }
+}
@@ -0,0 +1,495 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::atomic_file;
+use crate::blobs::BlobStore;
+use crate::collection::{self, CollectionItem, NewCollectionItem};
+use crate::error::AppError;
+
+/// Корзина внутри base: trash/{entry_id}/_entry.json + data (папка коллекции или файл).
+pub const TRASH_DIR: &str = "trash";
+const ENTRY_FILE_NAME: &str = "_entry.json";
+const PAYLOAD_NAME: &str = "data";
+const SETTINGS_FILE_NAME: &str = "_settings.json";
+/// Сколько дней хранить удалённое по умолчанию.
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+/// Папки base, которыми управляет приложение: их файлы удаляются только своими командами
+/// (элементы коллекций — remove_collection_item, плейлисты — delete_playlist и т. д.).
+const MANAGED_DIRS: [&str; 5] = [
+  "collections",
+  crate::playlist::PLAYLISTS_DIR,
+  crate::history::HISTORY_DIR,
+  crate::blobs::BLOBS_DIR,
+  TRASH_DIR,
+];
+
+/// Что лежит в корзине.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TrashedKind {
+  /// Коллекция целиком (папка с `_meta.json`).
+  Collection { collection_id: String },
+  /// Элемент коллекции. Ссылку на блоб держит корзина, пока элемент не восстановлен или не удалён окончательно.
+  Item {
+    collection_id: String,
+    item: CollectionItem,
+  },
+  /// Произвольный файл из delete_app_file.
+  File,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+  pub id: String,
+  /// Название для показа (имя коллекции или файла).
+  pub name: String,
+  /// Исходный путь относительно base.
+  pub original_path: String,
+  /// Время удаления, мс с эпохи Unix.
+  pub deleted_at: u64,
+  #[serde(flatten)]
+  pub kind: TrashedKind,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct TrashSettings {
+  /// 0 — не очищать автоматически.
+  retention_days: u32,
+}
+
+pub struct Trash {
+  base: PathBuf,
+  root: PathBuf,
+}
+
+impl Trash {
+  pub fn new(base: &Path) -> Self {
+    Self {
+      base: base.to_path_buf(),
+      root: base.join(TRASH_DIR),
+    }
+  }
+
+  /// Переместить папку коллекции в корзину.
+  pub fn trash_collection(&self, collection_id: &str) -> Result<TrashEntry, String> {
+    let dir = self.base.join("collections").join(collection_id);
+    let name = collection::read_meta(&dir)
+      .map(|m| m.name)
+      .unwrap_or_else(|_| collection_id.to_string());
+    let kind = TrashedKind::Collection {
+      collection_id: collection_id.to_string(),
+    };
+    // Под блокировкой коллекции: параллельный импорт не допишет `_meta.json` в уже перенесённую папку
+    collection::with_dir_lock(&dir, || {
+      self.put(&name, &format!("collections/{}", collection_id), kind, Some(&dir))
+    })
+  }
+
+  /// Положить удалённый элемент коллекции. Файл старого формата (в папке коллекции) переносится вместе с ним.
+  pub fn trash_item(&self, collection_id: &str, item: CollectionItem) -> Result<TrashEntry, String> {
+    let original_path = item.relative_path(collection_id);
+    let legacy_file = match item.blob {
+      Some(_) => None,
+      None => Some(self.base.join(&original_path)).filter(|p| p.is_file()),
+    };
+    let name = item.file.clone();
+    let kind = TrashedKind::Item {
+      collection_id: collection_id.to_string(),
+      item,
+    };
+    self.put(&name, &original_path, kind, legacy_file.as_deref())
+  }
+
+  /// Переместить файл (полный путь внутри base) в корзину.
+  pub fn trash_file(&self, full_path: &Path) -> Result<TrashEntry, AppError> {
+    let original_path = full_path
+      .strip_prefix(&self.base)
+      .map_err(|_| AppError::path_not_allowed(full_path.to_string_lossy()))?
+      .to_string_lossy()
+      .replace('\\', "/");
+    // Служебные файлы (`_`-префикс) и папки приложения через корзину файлов не удаляются
+    let top = original_path.split('/').next().unwrap_or_default();
+    if MANAGED_DIRS.contains(&top) || original_path.split('/').any(|part| part.starts_with('_')) {
+      return Err(AppError::path_not_allowed(original_path));
+    }
+    let name = full_path
+      .file_name()
+      .and_then(|n| n.to_str())
+      .unwrap_or_default()
+      .to_string();
+    Ok(self.put(&name, &original_path, TrashedKind::File, Some(full_path))?)
+  }
+
+  fn put(
+    &self,
+    name: &str,
+    original_path: &str,
+    kind: TrashedKind,
+    payload: Option<&Path>,
+  ) -> Result<TrashEntry, String> {
+    let deleted_at = collection::now_millis();
+    let mut id = deleted_at.to_string();
+    let mut counter = 0;
+    while self.root.join(&id).exists() {
+      counter += 1;
+      id = format!("{}_{}", deleted_at, counter);
+    }
+    let entry_dir = self.root.join(&id);
+    fs::create_dir_all(&entry_dir).map_err(|e| e.to_string())?;
+
+    let entry = TrashEntry {
+      id,
+      name: name.to_string(),
+      original_path: original_path.to_string(),
+      deleted_at,
+      kind,
+    };
+    write_entry(&entry_dir, &entry)?;
+    if let Some(payload) = payload {
+      // rename в пределах base — без копирования; при ошибке запись корзины откатываем
+      if let Err(e) = fs::rename(payload, entry_dir.join(PAYLOAD_NAME)) {
+        let _ = fs::remove_dir_all(&entry_dir);
+        return Err(format!("move {:?} to trash: {}", payload, e));
+      }
+    }
+    Ok(entry)
+  }
+
+  /// Содержимое корзины, новые сверху.
+  pub fn list(&self) -> Result<Vec<TrashEntry>, String> {
+    let mut entries = Vec::new();
+    if !self.root.exists() {
+      return Ok(entries);
+    }
+    for dir in fs::read_dir(&self.root).map_err(|e| e.to_string())? {
+      let dir = dir.map_err(|e| e.to_string())?.path();
+      if !dir.is_dir() {
+        continue;
+      }
+      match read_entry(&dir) {
+        Ok(entry) => entries.push(entry),
+        Err(e) => log::warn!("trash: skipping {:?}: {}", dir, e),
+      }
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.deleted_at));
+    Ok(entries)
+  }
+
+  /// Вернуть запись на место. Коллекция восстанавливается с тем же id, если он свободен.
+  pub fn restore(&self, entry_id: &str) -> Result<TrashEntry, AppError> {
+    let entry_dir = self.entry_dir(entry_id)?;
+    let entry = read_entry(&entry_dir)?;
+    let payload = entry_dir.join(PAYLOAD_NAME);
+    match &entry.kind {
+      TrashedKind::Collection { collection_id } => {
+        let collections = self.base.join("collections");
+        fs::create_dir_all(&collections).map_err(|e| AppError::io(&collections, e))?;
+        let mut final_id = collection_id.clone();
+        let mut counter = 0;
+        while collections.join(&final_id).exists() {
+          counter += 1;
+          final_id = format!("{}_{}", collection_id, counter);
+        }
+        let dest = collections.join(&final_id);
+        fs::rename(&payload, &dest).map_err(|e| AppError::io(&dest, e))?;
+        let taken = existing_names(&collections, &final_id);
+        let renamed = collection::update_meta(&dest, |meta| {
+          meta.id = final_id.clone();
+          meta.name = collection::unique_name(&meta.name, &taken);
+          Ok(())
+        });
+        if let Err(e) = renamed {
+          log::warn!("trash: restored {:?} without updating metadata: {}", dest, e);
+        }
+      }
+      TrashedKind::Item {
+        collection_id,
+        item,
+      } => {
+        let dir = self.base.join("collections").join(collection_id);
+        if !dir.is_dir() {
+          // Сначала нужно восстановить саму коллекцию
+          return Err(AppError::not_found("collection", collection_id));
+        }
+        let legacy_dest = dir.join(&item.file);
+        let move_file = item.blob.is_none() && payload.exists();
+        if move_file {
+          if legacy_dest.exists() {
+            return Err(AppError::duplicate_name("file", &item.file));
+          }
+          fs::rename(&payload, &legacy_dest).map_err(|e| AppError::io(&legacy_dest, e))?;
+        }
+        let restored = NewCollectionItem {
+          file: item.file.clone(),
+          blob: item.blob.clone(),
+          screen: item.screen,
+          image: item.image,
+          crop: item.crop,
+          saved_as_crop: item.saved_as_crop,
+          created_at: Some(item.created_at),
+        };
+        // Ссылка на блоб переходит от корзины обратно к коллекции — счётчик не меняется
+        if let Err(e) = collection::update_meta(&dir, |meta| meta.push_item(restored)) {
+          if move_file {
+            let _ = fs::rename(&legacy_dest, &payload);
+          }
+          return Err(e);
+        }
+      }
+      TrashedKind::File => {
+        let dest = self.base.join(&entry.original_path);
+        if dest.exists() {
+          return Err(AppError::duplicate_name("file", &entry.original_path));
+        }
+        if let Some(parent) = dest.parent() {
+          fs::create_dir_all(parent).map_err(|e| AppError::io(parent, e))?;
+        }
+        fs::rename(&payload, &dest).map_err(|e| AppError::io(&dest, e))?;
+      }
+    }
+    fs::remove_dir_all(&entry_dir).map_err(|e| AppError::io(&entry_dir, e))?;
+    Ok(entry)
+  }
+
+  /// Удалить запись окончательно, освободив ссылки на блобы.
+  pub fn purge(&self, entry_id: &str) -> Result<(), AppError> {
+    let entry_dir = self.entry_dir(entry_id)?;
+    let refs = read_entry(&entry_dir)
+      .map(|entry| entry_blob_refs(&entry_dir, &entry))
+      .unwrap_or_default();
+    fs::remove_dir_all(&entry_dir).map_err(|e| AppError::io(&entry_dir, e))?;
+    let store = BlobStore::new(&self.base);
+    for (blob, n) in refs {
+      for _ in 0..n {
+        store.release(&blob)?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Очистить корзину полностью.
+  pub fn empty(&self) -> Result<(), String> {
+    for entry in self.list()? {
+      self.purge(&entry.id)?;
+    }
+    Ok(())
+  }
+
+  /// Удалить записи старше срока хранения. Возвращает число удалённых.
+  pub fn purge_expired(&self, now_ms: u64) -> Result<usize, String> {
+    let days = self.retention_days();
+    if days == 0 {
+      return Ok(0);
+    }
+    let max_age = u64::from(days) * 24 * 60 * 60 * 1000;
+    let mut purged = 0;
+    for entry in self.list()? {
+      if now_ms.saturating_sub(entry.deleted_at) >= max_age {
+        self.purge(&entry.id)?;
+        purged += 1;
+      }
+    }
+    Ok(purged)
+  }
+
+  pub fn retention_days(&self) -> u32 {
+    fs::read_to_string(self.root.join(SETTINGS_FILE_NAME))
+      .ok()
+      .and_then(|s| serde_json::from_str::<TrashSettings>(&s).ok())
+      .map(|s| s.retention_days)
+      .unwrap_or(DEFAULT_RETENTION_DAYS)
+  }
+
+  pub fn set_retention_days(&self, retention_days: u32) -> Result<(), String> {
+    fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(&TrashSettings { retention_days }).map_err(|e| e.to_string())?;
+    atomic_file::write(&self.root.join(SETTINGS_FILE_NAME), json.as_bytes())
+  }
+
+  /// Ссылки на блобы, которые держат записи корзины (учитываются при сверке хранилища).
+  pub fn blob_refs(&self) -> Result<HashMap<String, u64>, String> {
+    let mut refs = HashMap::new();
+    if !self.root.exists() {
+      return Ok(refs);
+    }
+    for dir in fs::read_dir(&self.root).map_err(|e| e.to_string())? {
+      let dir = dir.map_err(|e| e.to_string())?.path();
+      if !dir.is_dir() {
+        continue;
+      }
+      let entry = read_entry(&dir)?;
+      for (blob, n) in entry_blob_refs(&dir, &entry) {
+        *refs.entry(blob).or_insert(0) += n;
+      }
+    }
+    Ok(refs)
+  }
+
+  fn entry_dir(&self, entry_id: &str) -> Result<PathBuf, AppError> {
+    crate::paths::validate_segment(entry_id)?;
+    let dir = self.root.join(entry_id);
+    if !dir.join(ENTRY_FILE_NAME).is_file() {
+      return Err(AppError::not_found("trash_entry", entry_id));
+    }
+    Ok(dir)
+  }
+}
+
+fn entry_blob_refs(entry_dir: &Path, entry: &TrashEntry) -> HashMap<String, u64> {
+  match &entry.kind {
+    TrashedKind::Collection { .. } => collection::read_meta(&entry_dir.join(PAYLOAD_NAME))
+      .map(|m| m.blob_refs())
+      .unwrap_or_default(),
+    TrashedKind::Item { item, .. } => item.blob.iter().map(|b| (b.clone(), 1)).collect(),
+    TrashedKind::File => HashMap::new(),
+  }
+}
+
+fn read_entry(entry_dir: &Path) -> Result<TrashEntry, String> {
+  let file = entry_dir.join(ENTRY_FILE_NAME);
+  let content = fs::read_to_string(&file).map_err(|e| format!("read {:?}: {}", file, e))?;
+  serde_json::from_str(&content).map_err(|e| format!("parse {:?}: {}", file, e))
+}
+
+fn write_entry(entry_dir: &Path, entry: &TrashEntry) -> Result<(), String> {
+  let json = serde_json::to_string_pretty(entry).map_err(|e| e.to_string())?;
+  atomic_file::write(&entry_dir.join(ENTRY_FILE_NAME), json.as_bytes())
+}
+
+/// Названия остальных коллекций (для разрешения совпадения имени при восстановлении).
+fn existing_names(collections: &Path, except_id: &str) -> Vec<String> {
+  fs::read_dir(collections)
+    .into_iter()
+    .flatten()
+    .flatten()
+    .map(|e| e.path())
+    .filter(|p| p.is_dir() && p.file_name().and_then(|n| n.to_str()) != Some(except_id))
+    .filter_map(|p| collection::read_meta(&p).ok().map(|m| m.name))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DAY: u64 = 24 * 60 * 60 * 1000;
+
+  /// base с коллекцией `c1`: элемент из общего хранилища и элемент старого формата.
+  fn setup() -> (tempfile::TempDir, PathBuf, String) {
+    let tmp = tempfile::tempdir().unwrap();
+    let base = tmp.path().to_path_buf();
+    let dir = base.join("collections").join("c1");
+    fs::create_dir_all(&dir).unwrap();
+    collection::write_meta(&dir, &collection::CollectionMeta::new("c1".into(), "Cats".into(), 1)).unwrap();
+    let store = BlobStore::new(&base);
+    let blob = store.put_bytes(b"img", "a.webp").unwrap();
+    store.retain(&blob).unwrap();
+    fs::write(dir.join("legacy.jpg"), b"old").unwrap();
+    collection::update_meta(&dir, |meta| {
+      meta.push_item(item("a.webp", Some(&blob)))?;
+      meta.push_item(item("legacy.jpg", None))
+    })
+    .unwrap();
+    (tmp, base, blob)
+  }
+
+  fn item(file: &str, blob: Option<&str>) -> NewCollectionItem {
+    NewCollectionItem {
+      file: file.into(),
+      blob: blob.map(String::from),
+      screen: None,
+      image: None,
+      crop: None,
+      saved_as_crop: false,
+      created_at: Some(1),
+    }
+  }
+
+  #[test]
+  fn trashed_items_and_collections_are_restored() {
+    let (_tmp, base, blob) = setup();
+    let dir = base.join("collections").join("c1");
+    let trash = Trash::new(&base);
+
+    let removed = collection::update_meta(&dir, |meta| meta.remove_item(2)).unwrap();
+    let entry = trash.trash_item("c1", removed).unwrap();
+    assert!(!dir.join("legacy.jpg").exists());
+    assert_eq!(trash.list().unwrap().len(), 1);
+    trash.restore(&entry.id).unwrap();
+    assert_eq!(fs::read(dir.join("legacy.jpg")).unwrap(), b"old");
+    assert_eq!(collection::read_meta(&dir).unwrap().items.len(), 2);
+    assert!(trash.list().unwrap().is_empty());
+
+    let entry = trash.trash_collection("c1").unwrap();
+    assert_eq!(entry.name, "Cats");
+    assert!(!dir.exists());
+    assert_eq!(trash.blob_refs().unwrap(), HashMap::from([(blob, 1)]));
+    // id занят новой коллекцией с тем же названием — восстановленная получает новые id и имя
+    fs::create_dir_all(&dir).unwrap();
+    collection::write_meta(&dir, &collection::CollectionMeta::new("c1".into(), "Cats".into(), 2)).unwrap();
+    trash.restore(&entry.id).unwrap();
+    let meta = collection::read_meta(&base.join("collections").join("c1_1")).unwrap();
+    assert_eq!((meta.id.as_str(), meta.name.as_str()), ("c1_1", "Cats (2)"));
+    assert_eq!(meta.items.len(), 2);
+
+    assert_eq!(trash.restore("missing").err(), Some(AppError::not_found("trash_entry", "missing")));
+  }
+
+  #[test]
+  fn emptying_releases_blobs() {
+    let (_tmp, base, blob) = setup();
+    let store = BlobStore::new(&base);
+    let trash = Trash::new(&base);
+    let file = base.join("notes.txt");
+    fs::write(&file, b"x").unwrap();
+    trash.trash_file(&file).unwrap();
+    trash.trash_collection("c1").unwrap();
+    assert_eq!(trash.list().unwrap().len(), 2);
+
+    trash.empty().unwrap();
+    assert!(trash.list().unwrap().is_empty());
+    assert!(!file.exists());
+    assert!(!store.path(&blob).unwrap().exists());
+  }
+
+  #[test]
+  fn expired_entries_are_purged_by_retention() {
+    let (_tmp, base, blob) = setup();
+    let trash = Trash::new(&base);
+    assert_eq!(trash.retention_days(), DEFAULT_RETENTION_DAYS);
+    let entry = trash.trash_collection("c1").unwrap();
+
+    trash.set_retention_days(0).unwrap();
+    assert_eq!(trash.purge_expired(entry.deleted_at + 365 * DAY).unwrap(), 0);
+    trash.set_retention_days(7).unwrap();
+    assert_eq!(trash.purge_expired(entry.deleted_at + 6 * DAY).unwrap(), 0);
+    assert_eq!(trash.purge_expired(entry.deleted_at + 7 * DAY).unwrap(), 1);
+    assert!(trash.list().unwrap().is_empty());
+    assert!(!BlobStore::new(&base).path(&blob).unwrap().exists());
+  }
+
+  #[test]
+  fn app_managed_files_are_not_trashed_as_files() {
+    let (_tmp, base, blob) = setup();
+    let trash = Trash::new(&base);
+    fs::create_dir_all(base.join("playlists")).unwrap();
+    fs::write(base.join("playlists").join("p1.json"), b"{}").unwrap();
+    fs::create_dir_all(base.join("images").join("_thumbs")).unwrap();
+    fs::write(base.join("images").join("_thumbs").join("t.webp"), b"t").unwrap();
+    fs::write(base.join("_settings.json"), b"{}").unwrap();
+    for path in [
+      base.join("collections/c1/_meta.json"),
+      base.join("collections/c1/legacy.jpg"),
+      base.join("playlists/p1.json"),
+      base.join("images/_thumbs/t.webp"),
+      base.join("_settings.json"),
+      BlobStore::new(&base).path(&blob).unwrap(),
+    ] {
+      assert!(matches!(trash.trash_file(&path), Err(AppError::PathNotAllowed { .. })), "{:?}", path);
+      assert!(path.is_file());
+    }
+    assert!(trash.list().unwrap().is_empty());
+  }
+}
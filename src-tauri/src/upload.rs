@@ -0,0 +1,151 @@
+//! Передача файлов из интерфейса без JSON-массивов чисел: массив раздувает 10 МБ фото до ~40 МБ
+//! JSON. Интерфейс режет файл на части и шлёт их по очереди (`upload_chunk`): на ПК — сырым
+//! телом запроса с заголовками `Upload-Id` / `Upload-Offset`, на Android, где тело запроса IPC
+//! всегда JSON, — объектом `{ uploadId, offset, data }` с частью в base64. Части дописываются во
+//! временный файл, который затем сохраняется как обычный файл с диска.
+
+use base64::Engine;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::collection;
+use crate::error::AppError;
+use crate::paths;
+
+const PART_EXT: &str = "part";
+
+/// Незавершённые загрузки: по файлу `{id}.part` на каждую.
+pub struct Uploads {
+  dir: PathBuf,
+}
+
+impl Uploads {
+  pub fn new(dir: PathBuf) -> Self {
+    Self { dir }
+  }
+
+  /// Начать приём файла. Возвращает id загрузки.
+  pub fn begin(&self) -> Result<String, AppError> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    fs::create_dir_all(&self.dir).map_err(|e| AppError::io(&self.dir, e))?;
+    let id = format!("{:x}-{:x}", collection::now_millis(), NEXT.fetch_add(1, Ordering::Relaxed));
+    let path = self.dir.join(format!("{}.{}", id, PART_EXT));
+    fs::File::create(&path).map_err(|e| AppError::io(&path, e))?;
+    Ok(id)
+  }
+
+  /// Дописать часть. `offset` должен совпасть с уже принятым размером: пропущенная или
+  /// повторённая часть — ошибка, а не испорченный файл. Возвращает принятый размер.
+  pub fn append(&self, chunk: &Chunk) -> Result<u64, AppError> {
+    let path = self.path(&chunk.upload_id)?;
+    let mut file = OpenOptions::new()
+      .append(true)
+      .open(&path)
+      .map_err(|e| AppError::io(&path, e))?;
+    let len = file.metadata().map_err(|e| AppError::io(&path, e))?.len();
+    if len != chunk.offset {
+      return Err(format!("Upload '{}': expected offset {}, got {}", chunk.upload_id, len, chunk.offset).into());
+    }
+    file.write_all(&chunk.data).map_err(|e| AppError::io(&path, e))?;
+    Ok(len + chunk.data.len() as u64)
+  }
+
+  /// Принятый файл. После сохранения его убирает `discard`.
+  pub fn path(&self, id: &str) -> Result<PathBuf, AppError> {
+    paths::validate_segment(id)?;
+    let path = self.dir.join(format!("{}.{}", id, PART_EXT));
+    if !path.is_file() {
+      return Err(AppError::not_found("upload", id));
+    }
+    Ok(path)
+  }
+
+  pub fn discard(&self, id: &str) -> Result<(), AppError> {
+    let path = self.path(id)?;
+    fs::remove_file(&path).map_err(|e| AppError::io(&path, e))
+  }
+
+  /// Убрать все незавершённые загрузки. Вызывается при запуске: id живут только в интерфейсе.
+  pub fn clear(&self) -> Result<(), AppError> {
+    match fs::remove_dir_all(&self.dir) {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::io(&self.dir, e)),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Часть файла из запроса `upload_chunk`.
+#[derive(Debug)]
+pub struct Chunk {
+  pub upload_id: String,
+  pub offset: u64,
+  pub data: Vec<u8>,
+}
+
+impl Chunk {
+  /// Сырое тело запроса; id и позиция — из заголовков `Upload-Id` и `Upload-Offset`.
+  pub fn from_raw(upload_id: Option<&str>, offset: Option<&str>, data: Vec<u8>) -> Result<Self, AppError> {
+    let upload_id = upload_id.ok_or_else(|| "Missing Upload-Id header".to_string())?;
+    let offset = offset
+      .and_then(|o| o.parse().ok())
+      .ok_or_else(|| "Missing or invalid Upload-Offset header".to_string())?;
+    Ok(Self {
+      upload_id: upload_id.to_string(),
+      offset,
+      data,
+    })
+  }
+
+  /// JSON `{ uploadId, offset, data }`, data — base64 (Android).
+  pub fn from_json(body: &serde_json::Value) -> Result<Self, AppError> {
+    let field = |name: &str| body.get(name).ok_or_else(|| format!("Upload chunk: missing '{}'", name));
+    let upload_id = field("uploadId")?.as_str().ok_or_else(|| "Upload chunk: invalid 'uploadId'".to_string())?;
+    let offset = field("offset")?.as_u64().ok_or_else(|| "Upload chunk: invalid 'offset'".to_string())?;
+    let data = field("data")?.as_str().ok_or_else(|| "Upload chunk: invalid 'data'".to_string())?;
+    let data = base64::engine::general_purpose::STANDARD
+      .decode(data)
+      .map_err(|e| format!("Upload chunk: {}", e))?;
+    Ok(Self {
+      upload_id: upload_id.to_string(),
+      offset,
+      data,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn chunks_are_appended_in_order_from_raw_and_base64_bodies() {
+    let dir = tempfile::tempdir().unwrap();
+    let uploads = Uploads::new(dir.path().join("uploads"));
+    let id = uploads.begin().unwrap();
+
+    let first = Chunk::from_raw(Some(&id), Some("0"), b"hello ".to_vec()).unwrap();
+    assert_eq!(uploads.append(&first).unwrap(), 6);
+    // Повтор той же части не дописывает её второй раз
+    assert!(uploads.append(&first).is_err());
+    let body = serde_json::json!({ "uploadId": id, "offset": 6, "data": "d29ybGQ=" });
+    assert_eq!(uploads.append(&Chunk::from_json(&body).unwrap()).unwrap(), 11);
+    assert_eq!(fs::read(uploads.path(&id).unwrap()).unwrap(), b"hello world");
+
+    assert!(Chunk::from_raw(Some(&id), None, vec![]).is_err());
+    assert!(Chunk::from_json(&serde_json::json!({ "uploadId": id, "offset": 0, "data": "%%" })).is_err());
+    assert_eq!(
+      uploads.append(&Chunk::from_raw(Some("missing"), Some("0"), vec![1]).unwrap()),
+      Err(AppError::not_found("upload", "missing"))
+    );
+    assert!(uploads.path("../x").is_err());
+
+    uploads.discard(&id).unwrap();
+    assert!(uploads.path(&id).is_err());
+    let stale = uploads.begin().unwrap();
+    uploads.clear().unwrap();
+    assert!(uploads.path(&stale).is_err());
+    uploads.clear().unwrap();
+  }
+}
@@ -0,0 +1,497 @@
+//! Установка обоев на ПК через внешние программы окружения рабочего стола. Окружение
+//! определяется по XDG_CURRENT_DESKTOP / WAYLAND_DISPLAY; пользователь может выбрать его
+//! вручную (настройка в `wallpaper.json`). Программы ищутся в PATH, который передаётся
+//! явно (`Tools`) — в тестах вместо них подкладываются заглушки.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+use crate::atomic_file;
+use crate::error::AppError;
+
+/// Имя файла настроек в app_data_dir.
+pub const SETTINGS_FILE_NAME: &str = "wallpaper.json";
+
+/// Поддерживаемые окружения (id для ручного выбора).
+pub const BACKENDS: [&str; 5] = ["gnome", "kde", "sway", "hyprland", "x11"];
+
+/// Способ установки обоев в конкретном окружении.
+pub trait WallpaperBackend {
+  fn id(&self) -> &'static str;
+
+  fn set_home(&self, path: &Path) -> Result<(), String>;
+
+  /// Экран блокировки есть не во всех окружениях.
+  fn set_lock(&self, _path: &Path) -> Result<(), String> {
+    Err(format!("Lock screen wallpaper is not supported by {}", self.id()))
+  }
+
+  fn supports_lock(&self) -> bool {
+    false
+  }
+}
+
+/// Запуск внешних программ с заданным PATH.
+#[derive(Debug, Clone)]
+pub struct Tools {
+  path: OsString,
+}
+
+impl Tools {
+  pub fn from_env() -> Self {
+    Self::with_path(std::env::var_os("PATH").unwrap_or_default())
+  }
+
+  pub fn with_path(path: impl Into<OsString>) -> Self {
+    Self { path: path.into() }
+  }
+
+  /// Полный путь программы или None, если её нет в PATH.
+  pub fn find(&self, program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&self.path)
+      .map(|dir| dir.join(program))
+      .find(|p| p.is_file())
+  }
+
+  fn command(&self, program: &str) -> Result<Command, String> {
+    let exe = self.find(program).ok_or_else(|| format!("{} not found in PATH", program))?;
+    let mut command = Command::new(exe);
+    command.env("PATH", &self.path);
+    Ok(command)
+  }
+
+  /// Выполнить программу и дождаться завершения; ненулевой код — ошибка с текстом stderr.
+  fn run<S: AsRef<std::ffi::OsStr>>(&self, program: &str, args: &[S]) -> Result<(), String> {
+    let output = self
+      .command(program)?
+      .args(args)
+      .stdin(Stdio::null())
+      .output()
+      .map_err(|e| format!("{}: {}", program, e))?;
+    if output.status.success() {
+      Ok(())
+    } else {
+      Err(format!("{} failed ({}): {}", program, output.status, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+  }
+}
+
+pub struct Gnome(pub Tools);
+
+impl Gnome {
+  fn gsettings(&self, schema: &str, key: &str, value: &str) -> Result<(), String> {
+    self.0.run("gsettings", &["set", schema, key, value])
+  }
+}
+
+impl WallpaperBackend for Gnome {
+  fn id(&self) -> &'static str {
+    "gnome"
+  }
+
+  fn set_home(&self, path: &Path) -> Result<(), String> {
+    let uri = file_uri(path);
+    self.gsettings("org.gnome.desktop.background", "picture-uri", &uri)?;
+    // picture-uri-dark появился в GNOME 42; на старых версиях ключа нет — это не ошибка
+    if let Err(e) = self.gsettings("org.gnome.desktop.background", "picture-uri-dark", &uri) {
+      log::debug!("wallpaper: {}", e);
+    }
+    Ok(())
+  }
+
+  fn set_lock(&self, path: &Path) -> Result<(), String> {
+    self.gsettings("org.gnome.desktop.screensaver", "picture-uri", &file_uri(path))
+  }
+
+  fn supports_lock(&self) -> bool {
+    true
+  }
+}
+
+pub struct Kde(pub Tools);
+
+impl WallpaperBackend for Kde {
+  fn id(&self) -> &'static str {
+    "kde"
+  }
+
+  fn set_home(&self, path: &Path) -> Result<(), String> {
+    self.0.run("plasma-apply-wallpaperimage", &[path])
+  }
+}
+
+/// sway и другие wlroots-композиторы. В sway обои ставит `swaymsg output * bg`
+/// (он сам перезапускает swaybg); без swaymsg запускаем swaybg сами.
+pub struct Sway(pub Tools);
+
+/// Запущенный нами swaybg: при смене обоев старый процесс завершается.
+static SWAYBG: Mutex<Option<Child>> = Mutex::new(None);
+
+impl WallpaperBackend for Sway {
+  fn id(&self) -> &'static str {
+    "sway"
+  }
+
+  fn set_home(&self, path: &Path) -> Result<(), String> {
+    if self.0.find("swaymsg").is_some() {
+      return self
+        .0
+        .run("swaymsg", &["output".as_ref(), "*".as_ref(), "bg".as_ref(), path.as_os_str(), "fill".as_ref()]);
+    }
+    let child = self
+      .0
+      .command("swaybg")?
+      .args(["-m".as_ref(), "fill".as_ref(), "-i".as_ref(), path.as_os_str()])
+      .stdin(Stdio::null())
+      .stdout(Stdio::null())
+      .stderr(Stdio::null())
+      .spawn()
+      .map_err(|e| format!("swaybg: {}", e))?;
+    let mut running = SWAYBG.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut old) = running.replace(child) {
+      let _ = old.kill();
+      let _ = old.wait();
+    }
+    Ok(())
+  }
+}
+
+/// Hyprland с hyprpaper: картинка загружается в hyprpaper и назначается всем мониторам.
+pub struct Hyprland(pub Tools);
+
+impl WallpaperBackend for Hyprland {
+  fn id(&self) -> &'static str {
+    "hyprland"
+  }
+
+  fn set_home(&self, path: &Path) -> Result<(), String> {
+    let file = path.to_string_lossy();
+    self.0.run("hyprctl", &["hyprpaper", "preload", &file])?;
+    self.0.run("hyprctl", &["hyprpaper", "wallpaper", &format!(",{}", file)])?;
+    // Предыдущие картинки больше не нужны — освобождаем память hyprpaper
+    if let Err(e) = self.0.run("hyprctl", &["hyprpaper", "unload", "unused"]) {
+      log::debug!("wallpaper: {}", e);
+    }
+    Ok(())
+  }
+}
+
+/// X11 без окружения рабочего стола: feh или xwallpaper, что найдётся.
+pub struct X11(pub Tools);
+
+impl WallpaperBackend for X11 {
+  fn id(&self) -> &'static str {
+    "x11"
+  }
+
+  fn set_home(&self, path: &Path) -> Result<(), String> {
+    if self.0.find("feh").is_some() {
+      self.0.run("feh", &["--no-fehbg".as_ref(), "--bg-fill".as_ref(), path.as_os_str()])
+    } else if self.0.find("xwallpaper").is_some() {
+      self.0.run("xwallpaper", &["--zoom".as_ref(), path.as_os_str()])
+    } else {
+      Err("Neither feh nor xwallpaper found in PATH".to_string())
+    }
+  }
+}
+
+/// Реализация по id из `BACKENDS`.
+pub fn backend(id: &str, tools: Tools) -> Option<Box<dyn WallpaperBackend>> {
+  let backend: Box<dyn WallpaperBackend> = match id {
+    "gnome" => Box::new(Gnome(tools)),
+    "kde" => Box::new(Kde(tools)),
+    "sway" => Box::new(Sway(tools)),
+    "hyprland" => Box::new(Hyprland(tools)),
+    "x11" => Box::new(X11(tools)),
+    _ => return None,
+  };
+  Some(backend)
+}
+
+/// Определить окружение по переменным среды. Неизвестный Wayland-композитор считаем
+/// wlroots-совместимым (swaybg), X11 без окружения — feh/xwallpaper.
+pub fn detect(var: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+  let desktop = var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+  for name in desktop.split(':') {
+    match name {
+      "gnome" | "gnome-classic" | "ubuntu" | "unity" | "budgie" | "pantheon" => return Some("gnome"),
+      "kde" | "plasma" => return Some("kde"),
+      "sway" => return Some("sway"),
+      "hyprland" => return Some("hyprland"),
+      _ => {}
+    }
+  }
+  let set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+  if set("HYPRLAND_INSTANCE_SIGNATURE") {
+    Some("hyprland")
+  } else if set("SWAYSOCK") || set("WAYLAND_DISPLAY") {
+    Some("sway")
+  } else if set("DISPLAY") {
+    Some("x11")
+  } else {
+    None
+  }
+}
+
+pub fn detect_from_env() -> Option<&'static str> {
+  detect(|name| std::env::var(name).ok())
+}
+
+/// Поставить файл обоями. `target`: both / home / lock; `backend_id` — ручной выбор окружения.
+/// Для `both` экран блокировки меняется только там, где он поддерживается.
+pub fn set(path: &Path, target: &str, backend_id: Option<&str>, tools: Tools) -> Result<(), AppError> {
+  if !path.is_file() {
+    return Err(AppError::not_found("file", path.to_string_lossy()));
+  }
+  let (home, lock) = match target {
+    "both" => (true, true),
+    "home" => (true, false),
+    "lock" => (false, true),
+    other => return Err(format!("Unknown wallpaper target: {}", other).into()),
+  };
+  let id = match backend_id {
+    Some(id) => id,
+    None => detect_from_env().ok_or(AppError::Unsupported {
+      feature: "desktop_environment",
+    })?,
+  };
+  let backend = backend(id, tools).ok_or_else(|| format!("Unknown wallpaper backend: {}", id))?;
+  if lock && !home && !backend.supports_lock() {
+    return Err(AppError::Unsupported { feature: "lock_screen" });
+  }
+  if home {
+    backend.set_home(path)?;
+  }
+  if lock && (!home || backend.supports_lock()) {
+    backend.set_lock(path)?;
+  }
+  Ok(())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Settings {
+  /// Окружение, выбранное вручную; None — определять автоматически.
+  #[serde(default)]
+  backend: Option<String>,
+}
+
+/// Ручной выбор окружения из файла настроек.
+pub fn backend_override(settings_file: &Path) -> Option<String> {
+  fs::read_to_string(settings_file)
+    .ok()
+    .and_then(|s| serde_json::from_str::<Settings>(&s).ok())
+    .and_then(|s| s.backend)
+}
+
+pub fn set_backend_override(settings_file: &Path, backend: Option<String>) -> Result<(), String> {
+  if let Some(id) = backend.as_deref() {
+    if !BACKENDS.contains(&id) {
+      return Err(format!("Unknown wallpaper backend: {}", id));
+    }
+  }
+  if let Some(dir) = settings_file.parent() {
+    fs::create_dir_all(dir).map_err(|e| format!("create {:?}: {}", dir, e))?;
+  }
+  let json = serde_json::to_string_pretty(&Settings { backend }).map_err(|e| e.to_string())?;
+  atomic_file::write(settings_file, json.as_bytes())
+}
+
+/// Окружения для настроек: определённое автоматически, выбранное вручную и все доступные.
+#[derive(Debug, Default, Serialize)]
+pub struct BackendInfo {
+  pub detected: Option<String>,
+  /// None — автоопределение.
+  pub selected: Option<String>,
+  pub available: Vec<String>,
+  /// Умеет ли действующее окружение менять экран блокировки — без этого отдельной ротации
+  /// блокировки на ПК нет.
+  pub supports_lock: bool,
+}
+
+pub fn info(settings_file: &Path) -> BackendInfo {
+  let detected = detect_from_env();
+  let selected = backend_override(settings_file);
+  let supports_lock = selected
+    .as_deref()
+    .or(detected)
+    .and_then(|id| backend(id, Tools::from_env()))
+    .is_some_and(|backend| backend.supports_lock());
+  BackendInfo {
+    detected: detected.map(String::from),
+    selected,
+    available: BACKENDS.iter().map(|id| id.to_string()).collect(),
+    supports_lock,
+  }
+}
+
+/// file:// URI с экранированием всего, кроме безопасных символов пути.
+fn file_uri(path: &Path) -> String {
+  let mut uri = String::from("file://");
+  for &b in path.to_string_lossy().as_bytes() {
+    if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+      uri.push(b as char);
+    } else {
+      uri.push_str(&format!("%{:02X}", b));
+    }
+  }
+  uri
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use std::os::unix::fs::PermissionsExt;
+
+  /// Каталог с заглушками программ: каждая дописывает своё имя и аргументы в calls.log.
+  struct Stubs {
+    dir: tempfile::TempDir,
+  }
+
+  impl Stubs {
+    fn new(programs: &[&str]) -> Self {
+      let dir = tempfile::tempdir().unwrap();
+      for program in programs {
+        Self::write(dir.path(), program, 0);
+      }
+      Self { dir }
+    }
+
+    fn write(dir: &Path, program: &str, code: i32) {
+      let log = dir.join("calls.log");
+      let script = format!("#!/bin/sh\necho \"{} $*\" >> '{}'\nexit {}\n", program, log.display(), code);
+      let path = dir.join(program);
+      fs::write(&path, script).unwrap();
+      fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn failing(self, program: &str) -> Self {
+      Self::write(self.dir.path(), program, 1);
+      self
+    }
+
+    fn tools(&self) -> Tools {
+      Tools::with_path(self.dir.path())
+    }
+
+    fn calls(&self) -> Vec<String> {
+      fs::read_to_string(self.dir.path().join("calls.log"))
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+    }
+  }
+
+  fn image() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a b.webp");
+    fs::write(&path, b"img").unwrap();
+    (dir, path)
+  }
+
+  #[test]
+  fn file_uri_escapes_path() {
+    assert_eq!(file_uri(Path::new("/home/u/a b/обои.webp")), "file:///home/u/a%20b/%D0%BE%D0%B1%D0%BE%D0%B8.webp");
+  }
+
+  #[test]
+  fn detect_from_desktop_variables() {
+    let env = |vars: &'static [(&'static str, &'static str)]| {
+      move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+    };
+    assert_eq!(detect(env(&[("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")])), Some("gnome"));
+    assert_eq!(detect(env(&[("XDG_CURRENT_DESKTOP", "KDE"), ("WAYLAND_DISPLAY", "wayland-0")])), Some("kde"));
+    assert_eq!(detect(env(&[("XDG_CURRENT_DESKTOP", "sway")])), Some("sway"));
+    assert_eq!(detect(env(&[("XDG_CURRENT_DESKTOP", "Hyprland")])), Some("hyprland"));
+    assert_eq!(detect(env(&[("WAYLAND_DISPLAY", "wayland-1")])), Some("sway"));
+    assert_eq!(detect(env(&[("XDG_CURRENT_DESKTOP", "i3"), ("DISPLAY", ":0")])), Some("x11"));
+    assert_eq!(detect(env(&[])), None);
+  }
+
+  #[test]
+  fn gnome_sets_both_uris_and_lock_screen() {
+    let stubs = Stubs::new(&["gsettings"]);
+    let (_dir, path) = image();
+    set(&path, "both", Some("gnome"), stubs.tools()).unwrap();
+    let uri = file_uri(&path);
+    assert_eq!(
+      stubs.calls(),
+      vec![
+        format!("gsettings set org.gnome.desktop.background picture-uri {}", uri),
+        format!("gsettings set org.gnome.desktop.background picture-uri-dark {}", uri),
+        format!("gsettings set org.gnome.desktop.screensaver picture-uri {}", uri),
+      ]
+    );
+  }
+
+  #[test]
+  fn gnome_failure_is_reported() {
+    let stubs = Stubs::new(&[]).failing("gsettings");
+    let (_dir, path) = image();
+    assert!(set(&path, "home", Some("gnome"), stubs.tools()).unwrap_err().to_string().contains("gsettings failed"));
+  }
+
+  #[test]
+  fn kde_sway_hyprland_and_x11_commands() {
+    let (_dir, path) = image();
+    let file = path.display().to_string();
+
+    let stubs = Stubs::new(&["plasma-apply-wallpaperimage"]);
+    set(&path, "both", Some("kde"), stubs.tools()).unwrap();
+    assert_eq!(stubs.calls(), vec![format!("plasma-apply-wallpaperimage {}", file)]);
+    assert_eq!(
+      set(&path, "lock", Some("kde"), stubs.tools()),
+      Err(AppError::Unsupported { feature: "lock_screen" })
+    );
+
+    let stubs = Stubs::new(&["swaymsg"]);
+    set(&path, "home", Some("sway"), stubs.tools()).unwrap();
+    assert_eq!(stubs.calls(), vec![format!("swaymsg output * bg {} fill", file)]);
+
+    let stubs = Stubs::new(&["hyprctl"]);
+    set(&path, "home", Some("hyprland"), stubs.tools()).unwrap();
+    assert_eq!(
+      stubs.calls(),
+      vec![
+        format!("hyprctl hyprpaper preload {}", file),
+        format!("hyprctl hyprpaper wallpaper ,{}", file),
+        "hyprctl hyprpaper unload unused".to_string(),
+      ]
+    );
+
+    let stubs = Stubs::new(&["xwallpaper"]);
+    set(&path, "home", Some("x11"), stubs.tools()).unwrap();
+    assert_eq!(stubs.calls(), vec![format!("xwallpaper --zoom {}", file)]);
+
+    let stubs = Stubs::new(&["feh", "xwallpaper"]);
+    set(&path, "home", Some("x11"), stubs.tools()).unwrap();
+    assert_eq!(stubs.calls(), vec![format!("feh --no-fehbg --bg-fill {}", file)]);
+
+    let stubs = Stubs::new(&[]);
+    assert!(set(&path, "home", Some("x11"), stubs.tools()).unwrap_err().to_string().contains("feh"));
+  }
+
+  #[test]
+  fn rejects_bad_input_and_persists_override() {
+    let (dir, path) = image();
+    let tools = Stubs::new(&[]).tools();
+    assert!(matches!(
+      set(&dir.path().join("missing.webp"), "home", Some("kde"), tools.clone()),
+      Err(AppError::NotFound { kind: "file", .. })
+    ));
+    assert!(set(&path, "desk", Some("kde"), tools.clone()).is_err());
+    assert!(set(&path, "home", Some("windows"), tools).is_err());
+
+    let settings = dir.path().join(SETTINGS_FILE_NAME);
+    assert_eq!(backend_override(&settings), None);
+    set_backend_override(&settings, Some("kde".into())).unwrap();
+    assert_eq!(backend_override(&settings).as_deref(), Some("kde"));
+    assert!(set_backend_override(&settings, Some("windows".into())).is_err());
+    set_backend_override(&settings, None).unwrap();
+    assert_eq!(backend_override(&settings), None);
+  }
+}